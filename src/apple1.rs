@@ -0,0 +1,152 @@
+use rust65::bus::{MemoryMap, Segment};
+use rust65::devices::Device;
+use rust65::devices::aci::Aci;
+use rust65::devices::acia6551::Acia6551;
+use rust65::devices::acia6850::Acia6850;
+use rust65::devices::cffa1::Cffa1;
+use rust65::devices::pia::Pia6821;
+use rust65::devices::serial;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+pub struct ApplePia {           //the 6821 as wired on the Apple I: keyboard on port A and CA1, display on port B and CB1/CB2
+    pub pia: Pia6821,
+}
+
+impl ApplePia {
+    pub fn new() -> ApplePia {
+        ApplePia { pia: Pia6821::new() }
+    }
+
+    fn sync_display_ready(&mut self) { //PB7 reads the display's busy line, which follows CB2 while a handshake is in progress
+        self.pia.set_port_b_input(if self.pia.cb2() { 0x7f } else { 0xff });
+    }
+
+    pub fn key_pressed(&mut self, key: u8) { //put the character on port A (PA7 is tied high) and strobe CA1
+        self.pia.set_port_a_input(key | 0x80);
+        self.pia.set_ca1(true);
+        self.pia.set_ca1(false);
+    }
+
+    pub fn display_pending(&self) -> Option<u8> { //the character written to port B, if the display hasn't taken it yet
+        if self.pia.cb2() { None } else { Some(self.pia.port_b_output()) }
+    }
+
+    pub fn display_done(&mut self) { //the display's ready strobe on CB1 ends the handshake
+        self.pia.set_cb1(true);
+        self.pia.set_cb1(false);
+        self.sync_display_ready();
+    }
+}
+
+impl Device for ApplePia {
+    fn read(&mut self, reg: u16) -> u8 {
+        return self.pia.read(reg);
+    }
+
+    fn write(&mut self, reg: u16, data: u8) {
+        self.pia.write(reg, data);
+        self.sync_display_ready();
+    }
+
+    fn peek(&self, reg: u16) -> u8 {
+        return self.pia.peek(reg);
+    }
+
+    fn tick(&mut self) {
+        self.pia.tick();
+        self.sync_display_ready();
+    }
+
+    fn irq(&self) -> bool { //IRQA and IRQB aren't connected on the Apple I
+        return false;
+    }
+}
+
+
+pub struct AppleOne<'a> {       //the expanded Apple I: 32K of DRAM, the ROM, and the PIA at 0xd010
+    pub memory: MemoryMap<'a>,
+    pub pia: Rc<RefCell<ApplePia>>,
+}
+
+impl<'a> AppleOne<'a> {
+    pub fn new(dram: &'a mut [u8], rom: &'a mut [u8]) -> AppleOne<'a> {
+        let pia = Rc::new(RefCell::new(ApplePia::new()));
+
+        let mut memory = MemoryMap::new(vec![
+            Segment::new(dram, 0, true, true),
+            Segment::new(rom, 0xe000, false, true),
+        ]);
+
+        memory.attach(0xd010, 0xd013, pia.clone());
+
+        AppleOne { memory, pia }
+    }
+
+    pub fn attach_serial(&mut self, settings: &HashMap<String, String>, clock: u64) {
+        //optional serial card, e.g. serial_port = "tcp:6551" and serial_address = "c200"
+        if let Some(port) = settings.get("serial_port") {
+            let host = match serial::open(port) {
+                Err(why) => panic!("couldn't open serial port {}: {}", port, why),
+                Ok(host) => host
+            };
+
+            let address: u16 = u16::from_str_radix(settings.get("serial_address").map_or("c200", |a| a.as_str()), 16).expect("serial_address should be a hex address");
+            match settings.get("serial_chip").map_or("6551", |c| c.as_str()) {
+                "6551" => {
+                    let end: u16 = address.checked_add(3).expect("serial_address should leave room for the 6551's 4 registers, fffc at most");
+                    self.memory.attach(address, end, Acia6551::new(host, clock));
+                },
+                "6850" => {
+                    let serial_clock: u64 = settings.get("serial_clock").map_or(1843200, |c| c.parse().expect("serial_clock should be a frequency in Hz"));
+                    let end: u16 = address.checked_add(1).expect("serial_address should leave room for the 6850's 2 registers, fffe at most");
+                    self.memory.attach(address, end, Acia6850::new(host, clock, serial_clock));
+                },
+                other => panic!("serial_chip should be 6551 or 6850, not {}", other)
+            }
+        }
+    }
+
+    pub fn attach_aci(&mut self, settings: &HashMap<String, String>, clock: u64) {
+        //optional cassette interface: aci_rom is its 256 byte ROM, tape_in and tape_out are WAV files
+        if let Some(rom_filename) = settings.get("aci_rom") {
+            let rom = match fs::read(rom_filename) {
+                Err(why) => panic!("couldn't open {}: {}", rom_filename, why),
+                Ok(data) => data
+            };
+
+            let mut aci = Aci::new(&rom, clock);
+
+            if let Some(tape) = settings.get("tape_in") {
+                if let Err(why) = aci.insert_tape(Path::new(tape)) { panic!("couldn't load tape {}: {}", tape, why) }
+            }
+            if let Some(tape) = settings.get("tape_out") {
+                if let Err(why) = aci.record_to(Path::new(tape)) { panic!("couldn't create {}: {}", tape, why) }
+            }
+
+            self.memory.attach(0xc000, 0xc1ff, aci);
+        }
+    }
+
+    pub fn attach_cffa1(&mut self, settings: &HashMap<String, String>) {
+        //optional CFFA1 CompactFlash card: cffa1_rom is its firmware, cffa1_image a raw disk image
+        if let Some(rom_filename) = settings.get("cffa1_rom") {
+            let rom = match fs::read(rom_filename) {
+                Err(why) => panic!("couldn't open {}: {}", rom_filename, why),
+                Ok(data) => data
+            };
+
+            let image = settings.get("cffa1_image").expect("cffa1_rom is set but cffa1_image isn't");
+            let cffa1 = match Cffa1::new(&rom, Path::new(image)) {
+                Err(why) => panic!("couldn't open disk image {}: {}", image, why),
+                Ok(card) => card
+            };
+
+            self.memory.attach(0x9000, 0xafff, cffa1);
+        }
+    }
+}
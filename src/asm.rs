@@ -0,0 +1,296 @@
+//Line assembler: the other half of the disassembler. It takes one line of 6502 assembly at a time, as typed into the
+//monitor, and keeps the labels defined so far so later lines can use them. A branch or jump to a label that isn't
+//defined yet gets patched once it is.
+
+use crate::cpu::Variant;
+use crate::disasm::mnemonic_name;
+use crate::opcodes::{Opcode, Mode};
+use crate::opcodes::Mnemonic::*;
+
+use std::collections::HashMap;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FixupKind
+{
+    Absolute,           //a 16 bit address
+    Relative(u16),      //a branch offset, counted from the address of the next instruction
+}
+
+struct Fixup //an operand waiting for a label to be defined
+{
+    at: u16,
+    kind: FixupKind,
+    label: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assembled //the output of one line
+{
+    pub bytes: Vec<u8>,             //the instruction, to go at the address the line was assembled at
+    pub patches: Vec<(u16, u8)>,    //bytes of earlier instructions that used a label this line defined
+}
+
+enum Value //an operand expression
+{
+    Known(u16, bool),       //the value, and whether it was written out as a 16 bit number, which rules out zero page
+    Label(String),          //a label that isn't defined yet
+}
+
+
+pub struct Assembler
+{
+    labels: HashMap<String, u16>,
+    fixups: Vec<Fixup>,
+}
+
+impl Default for Assembler
+{
+    fn default() -> Assembler
+    {
+        Assembler::new()
+    }
+}
+
+impl Assembler
+{
+    pub fn new() -> Assembler
+    {
+        Assembler { labels: HashMap::new(), fixups: Vec::new() }
+    }
+
+    pub fn labels(&self) -> &HashMap<String, u16>
+    {
+        return &self.labels
+    }
+
+    pub fn unresolved(&self) -> Vec<String> //labels that have been used but not defined yet
+    {
+        let mut names: Vec<String> = self.fixups.iter().map(|fixup| fixup.label.clone()).collect();
+        names.sort();
+        names.dedup();
+        return names
+    }
+
+
+    pub fn assemble_line(&mut self, variant: Variant, addr: u16, line: &str) -> Result<Assembled, String>
+    //one line: an optional "label:", then an instruction, or "name = value" to define a constant. ';' starts a comment.
+    {
+        let mut text: &str = line.split(';').next().unwrap_or("").trim();
+        let mut patches: Vec<(u16, u8)> = Vec::new();
+
+        if let Some((name, value)) = text.split_once('=')
+        {
+            let name: &str = name.trim();
+            let value: u16 = match self.value(value.trim())?
+            {
+                Value::Known(v, _) => v,
+                Value::Label(l) => return Err(format!("Unknown label {}", l))
+            };
+
+            self.define(name, value, &mut patches)?;
+            return Ok(Assembled { bytes: Vec::new(), patches })
+        }
+
+        if let Some((label, rest)) = text.split_once(':')
+        {
+            self.define(label.trim(), addr, &mut patches)?;
+            text = rest.trim();
+        }
+
+        if text.is_empty() { return Ok(Assembled { bytes: Vec::new(), patches }) }
+
+        let (name, operand) = match text.split_once(char::is_whitespace)
+        {
+            Some((name, operand)) => (name.to_uppercase(), operand.trim().replace(' ', "")),
+            None => (text.to_uppercase(), String::new())
+        };
+
+        let candidates: Vec<(u8, &Opcode)> = variant.opcodes().iter().enumerate().filter(|(code, op)| op.mnemonic != JAM && mnemonic_name(*code as u8, op) == name).map(|(code, op)| (code as u8, op)).collect();
+        if candidates.is_empty() { return Err(format!("Unknown instruction {}", name)) }
+
+        let bytes: Vec<u8> = self.encode(&candidates, &name, addr, &operand)?;
+        return Ok(Assembled { bytes, patches })
+    }
+
+
+    fn define(&mut self, name: &str, value: u16, patches: &mut Vec<(u16, u8)>) -> Result<(), String> //add a label, and patch anything that was waiting for it
+    {
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') || name.starts_with(|c: char| c.is_ascii_digit())
+        {
+            return Err(format!("Bad label name {:?}", name))
+        }
+
+        self.labels.insert(name.to_string(), value);
+
+        let waiting: Vec<Fixup>;
+        (waiting, self.fixups) = self.fixups.drain(..).partition(|fixup| fixup.label == name);
+
+        for fixup in waiting
+        {
+            match fixup.kind
+            {
+                FixupKind::Absolute => patches.extend([(fixup.at, value as u8), (fixup.at.wrapping_add(1), (value >> 8) as u8)]),
+                FixupKind::Relative(next) => patches.push((fixup.at, Assembler::offset(next, value)?))
+            }
+        }
+
+        return Ok(())
+    }
+
+    fn offset(next: u16, target: u16) -> Result<u8, String> //branch offset from the next instruction to target
+    {
+        let distance: i32 = target as i32 - next as i32;
+        let distance: i32 = if distance > 0x7fff { distance - 0x10000 } else if distance < -0x8000 { distance + 0x10000 } else { distance };
+
+        if !(-128..=127).contains(&distance) { return Err(format!("Branch to {:#06x} out of range", target)) }
+        return Ok(distance as i8 as u8)
+    }
+
+    fn value(&self, text: &str) -> Result<Value, String> //$hex, 0xhex, %binary, decimal, or a label
+    {
+        let parsed: Option<(u16, bool)> = if let Some(hex) = text.strip_prefix('$').or_else(|| text.strip_prefix("0x"))
+        {
+            u16::from_str_radix(hex, 16).ok().map(|v| (v, hex.len() > 2))
+        }
+        else if let Some(bin) = text.strip_prefix('%')
+        {
+            u16::from_str_radix(bin, 2).ok().map(|v| (v, bin.len() > 8))
+        }
+        else if text.starts_with(|c: char| c.is_ascii_digit())
+        {
+            text.parse().ok().map(|v| (v, v > 0xff))
+        }
+        else if text.chars().all(|c| c.is_alphanumeric() || c == '_') && !text.is_empty()
+        {
+            return Ok(match self.labels.get(text)
+            {
+                Some(&v) => Value::Known(v, v > 0xff),
+                None => Value::Label(text.to_string())
+            })
+        }
+        else { None };
+
+        return match parsed
+        {
+            Some((v, wide)) => Ok(Value::Known(v, wide)),
+            None => Err(format!("Can't read {:?} as a number or label", text))
+        }
+    }
+
+
+    fn encode(&mut self, candidates: &[(u8, &Opcode)], name: &str, addr: u16, operand: &str) -> Result<Vec<u8>, String> //pick the addressing mode from the operand's syntax and size
+    {
+        let find = |mode: Mode| -> Option<u8>
+        {
+            let documented = candidates.iter().find(|(_, op)| op.mode == mode && !op.undocumented);
+            return documented.or_else(|| candidates.iter().find(|(_, op)| op.mode == mode)).map(|(code, _)| *code)
+        };
+        let has = |mode: Mode| find(mode).is_some();
+        let wrong_mode = || format!("{} doesn't take the operand {:?}", name, operand);
+
+        let upper: String = operand.to_uppercase();
+
+        if operand.is_empty() || upper == "A"
+        {
+            let mode: Mode = if has(Mode::Accumulator) && (upper == "A" || !has(Mode::Implied)) { Mode::Accumulator } else { Mode::Implied };
+            return find(mode).map(|code| vec![code]).ok_or_else(wrong_mode)
+        }
+
+        if let Some(imm) = operand.strip_prefix('#')
+        {
+            let code: u8 = find(Mode::Immediate).ok_or_else(wrong_mode)?;
+            return match self.value(imm)?
+            {
+                Value::Known(v, _) if v <= 0xff => Ok(vec![code, v as u8]),
+                Value::Known(v, _) => Err(format!("Immediate value {:#x} doesn't fit in a byte", v)),
+                Value::Label(l) => Err(format!("Unknown label {}", l))
+            }
+        }
+
+        if has(Mode::ZeroPageRelative)                                    //BBR and BBS: zero page address, then the branch target
+        {
+            let (zp, target) = operand.split_once(',').ok_or_else(wrong_mode)?;
+            let code: u8 = find(Mode::ZeroPageRelative).unwrap();
+            let zp: u8 = match self.value(zp)?
+            {
+                Value::Known(v, false) => v as u8,
+                _ => return Err(format!("{} needs a zero page address", name))
+            };
+
+            let branch: u8 = self.operand_or_fixup(target, addr.wrapping_add(2), FixupKind::Relative(addr.wrapping_add(3)))?;
+            return Ok(vec![code, zp, branch])
+        }
+
+        if has(Mode::Relative)
+        {
+            let code: u8 = find(Mode::Relative).unwrap();
+            let offset: u8 = self.operand_or_fixup(operand, addr.wrapping_add(1), FixupKind::Relative(addr.wrapping_add(2)))?;
+            return Ok(vec![code, offset])
+        }
+
+        let (inner, zp_mode, abs_mode): (&str, Option<Mode>, Option<Mode>) =
+            if let Some(inner) = upper.strip_prefix('(').and_then(|o| o.strip_suffix(",X)")) { (&operand[1..1 + inner.len()], Some(Mode::IndirectX), Some(Mode::AbsoluteIndexedIndirect)) }
+            else if let Some(inner) = upper.strip_prefix('(').and_then(|o| o.strip_suffix("),Y")) { (&operand[1..1 + inner.len()], Some(Mode::IndirectY), None) }
+            else if let Some(inner) = upper.strip_prefix('(').and_then(|o| o.strip_suffix(')')) { (&operand[1..1 + inner.len()], Some(Mode::ZeroPageIndirect), Some(Mode::Indirect)) }
+            else if let Some(inner) = upper.strip_suffix(",X") { (&operand[..inner.len()], Some(Mode::ZeroPageX), Some(Mode::AbsoluteX)) }
+            else if let Some(inner) = upper.strip_suffix(",Y") { (&operand[..inner.len()], Some(Mode::ZeroPageY), Some(Mode::AbsoluteY)) }
+            else { (operand, Some(Mode::ZeroPage), Some(Mode::Absolute)) };
+
+        let zp_code: Option<u8> = zp_mode.and_then(find);
+        let abs_code: Option<u8> = abs_mode.and_then(find);
+
+        match self.value(inner)?
+        {
+            Value::Known(v, wide) =>
+            {
+                if let (Some(code), false, true) = (zp_code, wide, v <= 0xff) { return Ok(vec![code, v as u8]) }
+
+                let code: u8 = abs_code.ok_or_else(wrong_mode)?;
+                return Ok(vec![code, v as u8, (v >> 8) as u8])
+            }
+            Value::Label(label) =>                                       //not defined yet, so assume it's a full address
+            {
+                let code: u8 = abs_code.ok_or_else(|| format!("{} needs {} defined first, for a zero page address", name, label))?;
+                self.fixups.push(Fixup { at: addr.wrapping_add(1), kind: FixupKind::Absolute, label });
+                return Ok(vec![code, 0, 0])
+            }
+        }
+    }
+
+    fn operand_or_fixup(&mut self, text: &str, at: u16, kind: FixupKind) -> Result<u8, String> //a branch offset now, or a placeholder to patch later
+    {
+        let next: u16 = match kind { FixupKind::Relative(next) => next, FixupKind::Absolute => at };
+
+        match self.value(text)?
+        {
+            Value::Known(target, _) => return Assembler::offset(next, target),
+            Value::Label(label) =>
+            {
+                self.fixups.push(Fixup { at, kind, label });
+                return Ok(0)
+            }
+        }
+    }
+}
+
+
+pub fn assemble(variant: Variant, origin: u16, source: &str) -> Result<Vec<u8>, String> //a whole snippet, one instruction per line, with every label resolved
+{
+    let mut assembler: Assembler = Assembler::new();
+    let mut code: Vec<u8> = Vec::new();
+
+    for (number, line) in source.lines().enumerate()
+    {
+        let addr: u16 = origin.wrapping_add(code.len() as u16);
+        let out: Assembled = assembler.assemble_line(variant, addr, line).map_err(|e| format!("line {}: {}", number + 1, e))?;
+
+        code.extend(out.bytes);
+        for (at, byte) in out.patches { code[at.wrapping_sub(origin) as usize] = byte }
+    }
+
+    let missing: Vec<String> = assembler.unresolved();
+    if !missing.is_empty() { return Err(format!("Undefined labels: {}", missing.join(", "))) }
+
+    return Ok(code)
+}
@@ -0,0 +1,196 @@
+//PC breakpoints for the monitor: plain, conditional on a register, or only after a number of hits, plus the one-off
+//stops that "until" and "next" use. The front end checks them before each instruction, so stopping costs nothing
+//when there aren't any.
+
+use crate::cpu::{CpuError, CpuStatus};
+
+use std::fmt;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Register
+{
+    A, X, Y, Sp, Sr, Pc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compare
+{
+    Eq, Ne, Lt, Le, Gt, Ge,
+}
+
+const COMPARISONS: [(&str, Compare); 6] = [("==", Compare::Eq), ("!=", Compare::Ne), ("<=", Compare::Le), (">=", Compare::Ge), ("<", Compare::Lt), (">", Compare::Gt)];
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Condition //a register compared with a value, like a==0x8d
+{
+    pub register: Register,
+    pub compare: Compare,
+    pub value: u16,
+}
+
+impl Condition
+{
+    pub fn parse(text: &str) -> Option<Condition> //"a==0x8d", "x < 10", "sp>=$f0"...
+    {
+        let text: String = text.replace(' ', "").to_lowercase();
+        let (symbol, compare) = COMPARISONS.iter().find(|(symbol, _)| text.contains(symbol))?;
+        let (name, value) = text.split_once(symbol)?;
+
+        let register: Register = match name
+        {
+            "a" => Register::A,
+            "x" => Register::X,
+            "y" => Register::Y,
+            "sp" | "s" => Register::Sp,
+            "p" | "sr" => Register::Sr,
+            "pc" => Register::Pc,
+            _ => return None
+        };
+
+        return Some(Condition { register, compare: *compare, value: CpuStatus::parse_number(value)? })
+    }
+
+    pub fn holds(&self, cpu: &CpuStatus) -> bool
+    {
+        let current: u16 = match self.register
+        {
+            Register::A => cpu.a as u16,
+            Register::X => cpu.x as u16,
+            Register::Y => cpu.y as u16,
+            Register::Sp => cpu.sp as u16,
+            Register::Sr => cpu.sr as u16,
+            Register::Pc => cpu.pc,
+        };
+
+        match self.compare
+        {
+            Compare::Eq => current == self.value,
+            Compare::Ne => current != self.value,
+            Compare::Lt => current < self.value,
+            Compare::Le => current <= self.value,
+            Compare::Gt => current > self.value,
+            Compare::Ge => current >= self.value,
+        }
+    }
+}
+
+impl fmt::Display for Condition
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let symbol: &str = COMPARISONS.iter().find(|(_, compare)| *compare == self.compare).map_or("?", |(symbol, _)| symbol);
+        write!(f, "{:?}{}{:#x}", self.register, symbol, self.value)
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breakpoint
+{
+    pub id: usize,
+    pub addr: u16,
+    pub condition: Option<Condition>,
+    pub after: u32,         //only stop once it's been hit this many times
+    pub hits: u32,          //times PC reached addr with the condition true
+}
+
+impl fmt::Display for Breakpoint
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}: {:#06x}", self.id, self.addr)?;
+        if let Some(condition) = &self.condition { write!(f, " if {}", condition)? }
+        if self.after > 1 { write!(f, " after {}", self.after)? }
+        write!(f, ", {} hits", self.hits)
+    }
+}
+
+
+pub struct Breakpoints
+{
+    list: Vec<Breakpoint>,
+    next_id: usize,
+    until: Option<(u16, Option<u8>)>,  //a one-off stop, and the lowest SP it counts at, so "next" doesn't stop inside a recursive call
+    skip: Option<u16>,                  //the address execution is resuming from, which doesn't stop again straight away
+}
+
+impl Default for Breakpoints
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl Breakpoints
+{
+    pub fn new() -> Breakpoints
+    {
+        Breakpoints { list: Vec::new(), next_id: 1, until: None, skip: None }
+    }
+
+    pub fn add(&mut self, addr: u16, condition: Option<Condition>, after: u32) -> usize //returns the new breakpoint's id
+    {
+        let id: usize = self.next_id;
+        self.next_id += 1;
+        self.list.push(Breakpoint { id, addr, condition, after, hits: 0 });
+        return id
+    }
+
+    pub fn remove(&mut self, id: usize) -> bool
+    {
+        let before: usize = self.list.len();
+        self.list.retain(|bp| bp.id != id);
+        return self.list.len() != before
+    }
+
+    pub fn list(&self) -> &[Breakpoint]
+    {
+        return &self.list
+    }
+
+    pub fn run_until(&mut self, addr: u16, min_sp: Option<u8>) //stop once at addr, with SP at min_sp or above if given
+    {
+        self.until = Some((addr, min_sp));
+    }
+
+    pub fn resume(&mut self, pc: u16) //execution carries on from pc, so a breakpoint there shouldn't stop it before it moves
+    {
+        self.skip = Some(pc);
+    }
+
+    pub fn check(&mut self, cpu: &CpuStatus) -> Option<CpuError> //call before each instruction: Some if it should stop
+    {
+        let pc: u16 = cpu.pc;
+        if self.skip.take() == Some(pc) { return None }
+
+        if let Some((addr, min_sp)) = self.until
+        {
+            if addr == pc && min_sp.is_none_or(|sp| cpu.sp >= sp)
+            {
+                self.until = None;
+                return Some(CpuError::Breakpoint { addr: pc })
+            }
+        }
+
+        let mut stop: bool = false;
+        for bp in self.list.iter_mut().filter(|bp| bp.addr == pc)
+        {
+            if bp.condition.is_none_or(|condition| condition.holds(cpu))
+            {
+                bp.hits += 1;
+                if bp.hits >= bp.after { stop = true }
+            }
+        }
+
+        if stop
+        {
+            self.until = None;
+            return Some(CpuError::Breakpoint { addr: pc })
+        }
+
+        return None
+    }
+}
@@ -1,4 +1,129 @@
-use crate::cpu::CpuStatus;
+use crate::cpu::{CpuError, CpuStatus, Variant, BUS_SOURCE};
+use crate::devices::Device;
+
+use std::fmt;
+
+pub trait Bus //anything the CPU can be attached to: a memory map, a test harness, a whole machine
+{
+    fn read(&mut self, addr: u16) -> u8;            //a CPU read cycle, including any side effects it has on devices
+    fn write(&mut self, addr: u16, data: u8);       //a CPU write cycle
+    fn peek(&self, addr: u16) -> u8;                //look at a byte without triggering side effects, for monitors and debuggers
+
+    fn cycle(&mut self) {}                          //one CPU clock cycle has gone by, called after every access in cycle accurate mode
+
+    fn update_inputs(&self, _cpu: &mut CpuStatus) {} //drive the CPU's IRQ, NMI, RDY and SO inputs, called whenever the CPU samples them
+
+    fn take_fault(&mut self) -> Option<CpuError> { None } //an error caused by the last access, like a read of an unmapped address
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpenBus { //what happens on a read nothing answers, or a write nothing takes
+    Float,         //reads see whatever was last on the data bus, like the real machine, and writes go nowhere
+    Fill(u8),      //reads see a fixed byte
+    Trap,          //stop with CpuError::BusTrap, to drop into the debugger
+    Error,         //stop with CpuError::BusFault or CpuError::ReadOnly
+}
+
+impl OpenBus {
+    pub fn from_name(name: &str) -> Option<OpenBus> { //the names accepted by the open_bus setting: float, trap, error, or a fill byte in hex
+        match name.trim().to_lowercase().as_str() {
+            "float" => Some(OpenBus::Float),
+            "trap" => Some(OpenBus::Trap),
+            "error" => Some(OpenBus::Error),
+            byte => {
+                let digits: &str = byte.strip_prefix("0x").or_else(|| byte.strip_prefix('$')).unwrap_or(byte); //the prefixes the monitor takes
+                u8::from_str_radix(digits, 16).ok().map(OpenBus::Fill)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BusStats { //accesses that went nowhere, counted whatever the open bus policy is
+    pub unmapped_reads: u64,
+    pub unmapped_writes: u64,
+    pub read_only_writes: u64,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access { //which accesses a watchpoint stops on
+    Read,
+    Write,
+    Both,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Watchpoint {
+    pub id: usize,
+    pub start: u16,
+    pub end: u16,
+    pub access: Access,
+    pub value: Option<u8>, //only stop when this value is read or written
+    pub hits: u32,
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {:#06x}-{:#06x} {:?}", self.id, self.start, self.end, self.access)?;
+        if let Some(value) = self.value { write!(f, " value {:#04x}", value)? }
+        write!(f, ", {} hits", self.hits)
+    }
+}
+
+pub struct Watchpoints { //checked by read and write below, so they see every access the CPU makes, dummy ones and device registers included
+    list: Vec<Watchpoint>,
+    next_id: usize,
+}
+
+impl Default for Watchpoints {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Watchpoints {
+    pub fn new() -> Watchpoints {
+        Watchpoints { list: Vec::new(), next_id: 1 }
+    }
+
+    pub fn add(&mut self, start: u16, end: u16, access: Access, value: Option<u8>) -> usize { //returns the new watchpoint's id
+        let id: usize = self.next_id;
+        self.next_id += 1;
+        self.list.push(Watchpoint { id, start, end, access, value, hits: 0 });
+        return id;
+    }
+
+    pub fn remove(&mut self, id: usize) -> bool {
+        let before: usize = self.list.len();
+        self.list.retain(|wp| wp.id != id);
+        return self.list.len() != before;
+    }
+
+    pub fn list(&self) -> &[Watchpoint] {
+        return &self.list;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.list.is_empty();
+    }
+
+    fn hit(&mut self, addr: u16, data: u8, write: bool) -> bool { //count the access against every watchpoint it matches
+        let mut matched: bool = false;
+
+        for wp in self.list.iter_mut() {
+            let access: bool = match wp.access { Access::Read => !write, Access::Write => write, Access::Both => true };
+
+            if access && addr >= wp.start && addr <= wp.end && wp.value.is_none_or(|v| v == data) {
+                wp.hits += 1;
+                matched = true;
+            }
+        }
+
+        return matched;
+    }
+}
+
 
 pub struct Segment<'a> {
     pub data: &'a mut [u8],
@@ -14,7 +139,7 @@ impl Segment<'_> {
         i_start_addr: u16,
         i_write_enabled: bool,
         i_read_enabled: bool,
-    ) -> Segment {
+    ) -> Segment<'_> {
         let length = i_data.len() - 1;
         Segment {
             data: i_data,
@@ -24,18 +149,166 @@ impl Segment<'_> {
             read_enabled: i_read_enabled,
         }
     }
+
+    pub fn contains(&self, addr: u16) -> bool
+    {
+        return addr >= self.start_addr && addr <= self.end_addr;
+    }
+}
+
+
+pub struct DeviceMapping<'a> { //a device attached to a range of addresses
+    pub device: Box<dyn Device + 'a>,
+    pub start_addr: u16,
+    pub end_addr: u16,
+}
+
+impl DeviceMapping<'_> {
+    pub fn contains(&self, addr: u16) -> bool {
+        return addr >= self.start_addr && addr <= self.end_addr;
+    }
+}
+
+
+pub struct MemoryMap<'a> { //a set of RAM and ROM segments, plus memory-mapped devices which take priority over them
+    pub segments: Vec<Segment<'a>>,
+    pub devices: Vec<DeviceMapping<'a>>,
+    pub open_bus: OpenBus,
+    pub stats: BusStats,
+    last_data: u8, //the value left on the data bus by the last access
+    fault: Option<CpuError>,
+}
+
+impl<'a> MemoryMap<'a> {
+    pub fn new(i_segments: Vec<Segment<'a>>) -> MemoryMap<'a> {
+        MemoryMap { segments: i_segments, devices: Vec::new(), open_bus: OpenBus::Float, stats: BusStats::default(), last_data: 0, fault: None }
+    }
+
+    pub fn attach(&mut self, start_addr: u16, end_addr: u16, device: impl Device + 'a) -> usize {
+        //map a device's registers to start_addr..=end_addr, returns its index in the device list
+        self.devices.push(DeviceMapping { device: Box::new(device), start_addr, end_addr });
+        return self.devices.len() - 1;
+    }
+
+    pub fn tick(&mut self, cycles: u32) { //run every device's clock for the given number of CPU cycles
+        for _ in 0..cycles {
+            for mapping in self.devices.iter_mut() {
+                mapping.device.tick();
+            }
+        }
+    }
+
+    fn open_value(&self) -> u8 { //what a read sees when nothing drives the data bus
+        match self.open_bus {
+            OpenBus::Fill(byte) => byte,
+            _ => self.last_data
+        }
+    }
+
+    fn missed_write(&mut self, addr: u16, data: u8, read_only: bool) { //count a write that went nowhere and apply the policy to it
+        if read_only { self.stats.read_only_writes += 1 } else { self.stats.unmapped_writes += 1 }
+
+        match self.open_bus {
+            OpenBus::Trap => self.fault = Some(CpuError::BusTrap { addr, write: true }),
+            OpenBus::Error if read_only => self.fault = Some(CpuError::ReadOnly { addr, data }),
+            OpenBus::Error => self.fault = Some(CpuError::BusFault { addr, write: true }),
+            _ => ()
+        }
+    }
+}
+
+impl Bus for MemoryMap<'_> {
+    fn read(&mut self, addr: u16) -> u8 {
+        for mapping in self.devices.iter_mut() {
+            if mapping.contains(addr) {
+                self.last_data = mapping.device.read(addr - mapping.start_addr);
+                return self.last_data;
+            }
+        }
+
+        for bank in self.segments.iter() {
+            if bank.contains(addr) && bank.read_enabled {
+                self.last_data = bank.data[(addr - bank.start_addr) as usize];
+                return self.last_data;
+            }
+        }
+
+        self.stats.unmapped_reads += 1;
+        match self.open_bus {
+            OpenBus::Trap => self.fault = Some(CpuError::BusTrap { addr, write: false }),
+            OpenBus::Error => self.fault = Some(CpuError::BusFault { addr, write: false }),
+            _ => ()
+        }
+
+        self.last_data = self.open_value();
+        return self.last_data;
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        self.last_data = data;
+
+        for mapping in self.devices.iter_mut() {
+            if mapping.contains(addr) {
+                mapping.device.write(addr - mapping.start_addr, data);
+                return;
+            }
+        }
+
+        for bank in self.segments.iter_mut() {
+            if bank.contains(addr) && bank.write_enabled {
+                bank.data[(addr - bank.start_addr) as usize] = data;
+                return;
+            }
+        }
+
+        let read_only: bool = self.segments.iter().any(|bank| bank.contains(addr));
+        self.missed_write(addr, data, read_only);
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        for mapping in self.devices.iter() {
+            if mapping.contains(addr) {
+                return mapping.device.peek(addr - mapping.start_addr);
+            }
+        }
+
+        for bank in self.segments.iter() {
+            if bank.contains(addr) && bank.read_enabled {
+                return bank.data[(addr - bank.start_addr) as usize];
+            }
+        }
+
+        return self.open_value();
+    }
+
+    fn cycle(&mut self) {
+        self.tick(1);
+    }
+
+    fn take_fault(&mut self) -> Option<CpuError> {
+        return self.fault.take();
+    }
+
+    fn update_inputs(&self, cpu: &mut CpuStatus) { //the devices' outputs are wired-OR on each input, and drive it together as BUS_SOURCE
+        let asserted = |output: fn(&dyn Device) -> bool| self.devices.iter().any(|mapping| output(mapping.device.as_ref()));
+
+        cpu.set_irq(BUS_SOURCE, asserted(|device| device.irq()));
+        cpu.set_nmi(BUS_SOURCE, asserted(|device| device.nmi()));
+        cpu.set_rdy(BUS_SOURCE, asserted(|device| device.rdy()));
+        cpu.set_so(BUS_SOURCE, asserted(|device| device.so()));
+    }
 }
 
 
-pub fn absolute(memspace: &mut [Segment], reg: &mut CpuStatus) -> u16 //Absolute
+pub fn absolute(memspace: &mut dyn Bus, reg: &mut CpuStatus) -> u16 //Absolute
 {
     let lo_byte: u8;
     let hi_byte: u8;
     let o_addr: u16;
 
-    lo_byte = read(memspace, reg.pc);
+    lo_byte = read(memspace, reg, reg.pc);
     reg.pc = reg.pc.wrapping_add(1);
-    hi_byte = read(memspace, reg.pc);
+    hi_byte = read(memspace, reg, reg.pc);
     reg.pc = reg.pc.wrapping_add(1);
 
     o_addr = ((hi_byte as u16) << 8) + lo_byte as u16;
@@ -43,16 +316,16 @@ pub fn absolute(memspace: &mut [Segment], reg: &mut CpuStatus) -> u16 //Absolute
     return o_addr;
 }
 
-pub fn absolute_x(memspace: &mut [Segment], reg: &mut CpuStatus, wrap_check: bool) -> u16 //Absolute + X
+pub fn absolute_x(memspace: &mut dyn Bus, reg: &mut CpuStatus, wrap_check: bool) -> u16 //Absolute + X
 {
     let lo_byte: u8;
     let hi_byte: u8;
     let addr: u16;
     let o_addr: u16;
 
-    lo_byte = read(memspace, reg.pc);
+    lo_byte = read(memspace, reg, reg.pc);
     reg.pc = reg.pc.wrapping_add(1);
-    hi_byte = read(memspace, reg.pc);
+    hi_byte = read(memspace, reg, reg.pc);
     reg.pc = reg.pc.wrapping_add(1);
 
     addr = ((hi_byte as u16) << 8) + lo_byte as u16;
@@ -63,19 +336,23 @@ pub fn absolute_x(memspace: &mut [Segment], reg: &mut CpuStatus, wrap_check: boo
         reg.cycles_used += 1;
     }
 
+    if addr & 0xff00 != o_addr & 0xff00 || !wrap_check { //stores and read-modify-writes always take the fixup cycle
+        index_fixup(memspace, reg, addr, o_addr);
+    }
+
     return o_addr;
 }
 
-pub fn absolute_y(memspace: &mut [Segment], reg: &mut CpuStatus, wrap_check: bool) -> u16 //Absolute + Y
+pub fn absolute_y(memspace: &mut dyn Bus, reg: &mut CpuStatus, wrap_check: bool) -> u16 //Absolute + Y
 {
     let lo_byte: u8;
     let hi_byte: u8;
     let addr: u16;
     let o_addr: u16;
 
-    lo_byte = read(memspace, reg.pc);
+    lo_byte = read(memspace, reg, reg.pc);
     reg.pc = reg.pc.wrapping_add(1);
-    hi_byte = read(memspace, reg.pc);
+    hi_byte = read(memspace, reg, reg.pc);
     reg.pc = reg.pc.wrapping_add(1);
 
     addr = ((hi_byte as u16) << 8) + lo_byte as u16;
@@ -86,40 +363,46 @@ pub fn absolute_y(memspace: &mut [Segment], reg: &mut CpuStatus, wrap_check: boo
         reg.cycles_used += 1;
     }
 
+    if addr & 0xff00 != o_addr & 0xff00 || !wrap_check { //stores and read-modify-writes always take the fixup cycle
+        index_fixup(memspace, reg, addr, o_addr);
+    }
+
     return o_addr;
 }
 
-pub fn zp(memspace: &mut [Segment], reg: &mut CpuStatus) -> u16 //Zero Page
+pub fn zp(memspace: &mut dyn Bus, reg: &mut CpuStatus) -> u16 //Zero Page
 {
     let o_addr: u16;
 
-    o_addr = read(memspace, reg.pc) as u16;
+    o_addr = read(memspace, reg, reg.pc) as u16;
     reg.pc = reg.pc.wrapping_add(1);
 
     return o_addr;
 }
 
-pub fn zp_x(memspace: &mut [Segment], reg: &mut CpuStatus) -> u16 //Zero Page + X
+pub fn zp_x(memspace: &mut dyn Bus, reg: &mut CpuStatus) -> u16 //Zero Page + X
 {
     let o_addr: u8;
 
-    o_addr = read(memspace, reg.pc).wrapping_add(reg.x);
+    o_addr = read(memspace, reg, reg.pc).wrapping_add(reg.x);
     reg.pc = reg.pc.wrapping_add(1);
+    index_dummy(memspace, reg, o_addr.wrapping_sub(reg.x));
 
     return o_addr as u16;
 }
 
-pub fn zp_y(memspace: &mut [Segment], reg: &mut CpuStatus) -> u16 //Zero Page + Y
+pub fn zp_y(memspace: &mut dyn Bus, reg: &mut CpuStatus) -> u16 //Zero Page + Y
 {
     let o_addr: u8;
 
-    o_addr = read(memspace, reg.pc).wrapping_add(reg.y);
+    o_addr = read(memspace, reg, reg.pc).wrapping_add(reg.y);
     reg.pc = reg.pc.wrapping_add(1);
+    index_dummy(memspace, reg, o_addr.wrapping_sub(reg.y));
 
     return o_addr as u16;
 }
 
-pub fn indirect(memspace: &mut [Segment], reg: &mut CpuStatus) -> u16 //indirect addressing, only used by JMP. Kinda jank to implement.
+pub fn indirect(memspace: &mut dyn Bus, reg: &mut CpuStatus) -> u16 //indirect addressing, only used by JMP. Kinda jank to implement.
 {
     let lo_byte: u8;
     let hi_byte: u8;
@@ -128,23 +411,60 @@ pub fn indirect(memspace: &mut [Segment], reg: &mut CpuStatus) -> u16 //indirect
     let mut i_addr2: u16;
     let mut o_addr: u16;
 
-    lo_byte = read(memspace, reg.pc);
+    lo_byte = read(memspace, reg, reg.pc);
     reg.pc = reg.pc.wrapping_add(1);
-    hi_byte = read(memspace, reg.pc);
+    hi_byte = read(memspace, reg, reg.pc);
     reg.pc = reg.pc.wrapping_add(1);
 
+    if reg.variant != Variant::Nmos {
+        dummy_read(memspace, reg, reg.pc.wrapping_sub(1)); //the extra cycle the 65C02 spends getting the page crossing right
+    }
+
     i_addr = (hi_byte as u16) << 8;
     i_addr2 = i_addr;
     i_addr += lo_byte as u16;
-    i_addr2 += lo_byte.wrapping_add(1) as u16; //We use wrapping_add here to mimic the NMOS 6502 bug where indirect jumps don't work right at page boundaries
 
-    o_addr = read(memspace, i_addr) as u16;
-    o_addr += (read(memspace, i_addr2) as u16) << 8;
+    if reg.variant == Variant::Nmos {
+        i_addr2 += lo_byte.wrapping_add(1) as u16; //We use wrapping_add here to mimic the NMOS 6502 bug where indirect jumps don't work right at page boundaries
+    } else {
+        i_addr2 = i_addr.wrapping_add(1); //fixed on the 65C02
+    }
+
+    o_addr = read(memspace, reg, i_addr) as u16;
+    o_addr += (read(memspace, reg, i_addr2) as u16) << 8;
 
     return o_addr;
 }
 
-pub fn indirect_x(memspace: &mut [Segment], reg: &mut CpuStatus) -> u16 //Indirect + X.
+pub fn zp_indirect(memspace: &mut dyn Bus, reg: &mut CpuStatus) -> u16 //(Zero Page), 65C02 only.
+{
+    let zp_addr: u8;
+    let lo_byte: u8;
+    let hi_byte: u8;
+
+    zp_addr = read(memspace, reg, reg.pc);
+    reg.pc = reg.pc.wrapping_add(1);
+    lo_byte = read(memspace, reg, zp_addr as u16);
+    hi_byte = read(memspace, reg, zp_addr.wrapping_add(1) as u16);
+
+    return ((hi_byte as u16) << 8) + lo_byte as u16;
+}
+
+pub fn absolute_indexed_indirect(memspace: &mut dyn Bus, reg: &mut CpuStatus) -> u16 //(Absolute + X), only used by the 65C02's JMP.
+{
+    let i_addr: u16;
+    let lo_byte: u8;
+    let hi_byte: u8;
+
+    i_addr = absolute(memspace, reg).wrapping_add(reg.x as u16);
+    dummy_read(memspace, reg, reg.pc.wrapping_sub(1));
+    lo_byte = read(memspace, reg, i_addr);
+    hi_byte = read(memspace, reg, i_addr.wrapping_add(1));
+
+    return ((hi_byte as u16) << 8) + lo_byte as u16;
+}
+
+pub fn indirect_x(memspace: &mut dyn Bus, reg: &mut CpuStatus) -> u16 //Indirect + X.
 {
     let zp_addr: u8;
     let lo_byte: u8;
@@ -152,17 +472,18 @@ pub fn indirect_x(memspace: &mut [Segment], reg: &mut CpuStatus) -> u16 //Indire
 
     let o_addr: u16;
 
-    zp_addr = read(memspace, reg.pc).wrapping_add(reg.x);
+    zp_addr = read(memspace, reg, reg.pc).wrapping_add(reg.x);
     reg.pc = reg.pc.wrapping_add(1);
-    lo_byte = read(memspace, zp_addr as u16);
-    hi_byte = read(memspace, (zp_addr as u16).wrapping_add(1));
+    index_dummy(memspace, reg, zp_addr.wrapping_sub(reg.x));
+    lo_byte = read(memspace, reg, zp_addr as u16);
+    hi_byte = read(memspace, reg, zp_addr.wrapping_add(1) as u16); //the pointer wraps around within zero page
 
     o_addr = ((hi_byte as u16) << 8) + lo_byte as u16;
 
     return o_addr;
 }
 
-pub fn indirect_y(memspace: &mut [Segment], reg: &mut CpuStatus, wrap_check: bool) -> u16 //Indirect + Y. Significantly different to Indirect + X in operation.
+pub fn indirect_y(memspace: &mut dyn Bus, reg: &mut CpuStatus, wrap_check: bool) -> u16 //Indirect + Y. Significantly different to Indirect + X in operation.
 {
     let zp_addr: u8;
     let lo_byte: u8;
@@ -171,10 +492,10 @@ pub fn indirect_y(memspace: &mut [Segment], reg: &mut CpuStatus, wrap_check: boo
     let mut i_addr: u16 = 0;
     let o_addr: u16;
 
-    zp_addr = read(memspace, reg.pc);
+    zp_addr = read(memspace, reg, reg.pc);
     reg.pc = reg.pc.wrapping_add(1);
-    lo_byte = read(memspace, zp_addr as u16);
-    hi_byte = read(memspace, (zp_addr as u16).wrapping_add(1));
+    lo_byte = read(memspace, reg, zp_addr as u16);
+    hi_byte = read(memspace, reg, zp_addr.wrapping_add(1) as u16); //the pointer wraps around within zero page
 
     i_addr += ((hi_byte as u16) << 8) + lo_byte as u16;
 
@@ -184,88 +505,151 @@ pub fn indirect_y(memspace: &mut [Segment], reg: &mut CpuStatus, wrap_check: boo
         reg.cycles_used += 1;
     }
 
+    if i_addr & 0xff00 != o_addr & 0xff00 || !wrap_check {
+        index_fixup(memspace, reg, i_addr, o_addr);
+    }
+
     return o_addr;
 }
 
 
-pub fn read(memspace: &mut [Segment], addr: u16) -> u8 //bus arbitration for reading bytes
+fn index_dummy(memspace: &mut dyn Bus, reg: &mut CpuStatus, base: u8) //the cycle spent adding the index in zero page modes
 {
-    match addr //put special effects that happen upon a read from a certain address here
-    {
-        0xd010 => memspace[2].data[1] &= !0b10000000, //when reading PIA port A input register, clear bit 7 of the output register
-        _ => ()
+    if reg.variant == Variant::Nmos {
+        dummy_read(memspace, reg, base as u16);         //the NMOS part reads the unindexed address
+    } else {
+        dummy_read(memspace, reg, reg.pc.wrapping_sub(1)); //the 65C02 reads the operand byte again
     }
+}
 
-    for bank in memspace.iter() {
-        if addr >= bank.start_addr && addr <= bank.end_addr {
-            if bank.read_enabled {
-                return bank.data[(addr - bank.start_addr) as usize];
-            }
-        }
+//The cycle spent carrying the index into the high byte. The CMOS parts' datasheets list this among their differences
+//from the NMOS 6502 (the "Microprocessor Operational Enhancements" table in the W65C02S and R65C02 sheets): indexed
+//addressing across a page boundary makes an extra read of the last instruction byte, not of an invalid address.
+fn index_fixup(memspace: &mut dyn Bus, reg: &mut CpuStatus, base: u16, addr: u16)
+{
+    if reg.variant == Variant::Nmos || base & 0xff00 == addr & 0xff00 {
+        dummy_read(memspace, reg, (base & 0xff00) | (addr & 0x00ff)); //the NMOS part reads from the address before the carry
+    } else {
+        dummy_read(memspace, reg, reg.pc.wrapping_sub(1)); //the 65C02 reads the last operand byte again instead
+    }
+}
+
+
+pub fn read(memspace: &mut dyn Bus, reg: &mut CpuStatus, addr: u16) -> u8 //bus arbitration for reading bytes
+{
+    let data: u8 = read_cycle(memspace, reg, addr);
+
+    if let Some(e) = memspace.take_fault() {
+        reg.raise(e);
     }
 
-    println!("Attempt to read from unmapped address {:#06x}!", addr);
-    return 0xAA;
+    return data;
 }
 
-pub fn write(memspace: &mut [Segment], addr: u16, data: u8) //bus arbitration for writing bytes
+pub fn write(memspace: &mut dyn Bus, reg: &mut CpuStatus, addr: u16, data: u8) //bus arbitration for writing bytes
 {
-    for bank in memspace.iter_mut() {
-        if addr >= bank.start_addr && addr <= bank.end_addr {
-            if bank.write_enabled {
-                bank.data[(addr - bank.start_addr) as usize] = data;
-                break;
-            }
+    write_cycle(memspace, reg, addr, data);
+
+    if let Some(e) = memspace.take_fault() {
+        reg.raise(e);
+    }
+}
+
+fn read_cycle(memspace: &mut dyn Bus, reg: &mut CpuStatus, addr: u16) -> u8 //one read cycle, stretched by RDY
+{
+    if reg.cycle_accurate {
+        memspace.update_inputs(reg);
+
+        while reg.rdy_held() { //RDY low stretches a read cycle: the address stays on the bus and the read repeats
+            stalled_cycle(memspace, reg, Some(addr));
+            memspace.update_inputs(reg);
         }
     }
 
-    match addr //put special effects that happen upon a write to a certain address here
-    {
-        0xd012 => memspace[2].data[2] |= 0b10000000, //when writing to PIA port B output register, set bit 7 of the input register
-        _ => ()
+    let data: u8 = memspace.read(addr);
+
+    if !reg.watchpoints.is_empty() && reg.watchpoints.hit(addr, data, false) {
+        reg.raise(CpuError::Watchpoint { addr, pc: reg.watch_pc, old: data, new: data, write: false });
     }
 
-    return;
-}
+    if reg.cycle_accurate {
+        memspace.cycle();
+    }
 
+    return data;
+}
 
-pub fn push_stack(memory: &mut [Segment], reg: &mut CpuStatus, data: u8)
-//push a byte onto the stack and update the pointer
+fn write_cycle(memspace: &mut dyn Bus, reg: &mut CpuStatus, addr: u16, data: u8) //one write cycle
 {
-    if reg.debug_text {
-        print!("Pushing {:#04x} onto stack... ", data)
+    if reg.cycle_accurate && reg.variant != Variant::Nmos { //the NMOS part ignores RDY on write cycles, the 65C02 stops on them too
+        memspace.update_inputs(reg);
+
+        while reg.rdy_held() {
+            stalled_cycle(memspace, reg, None);
+            memspace.update_inputs(reg);
+        }
     }
 
-    reg.sp = reg.sp.wrapping_add(1);
+    if !reg.watchpoints.is_empty() && reg.watchpoints.hit(addr, data, true) {
+        let old: u8 = memspace.peek(addr);
+        reg.raise(CpuError::Watchpoint { addr, pc: reg.watch_pc, old, new: data, write: true });
+    }
+
+    memspace.write(addr, data);
+
+    if reg.cycle_accurate {
+        memspace.cycle();
+    }
+}
 
-    if reg.debug_text {
-        if reg.sp == 0 {
-            println!("stack overflow!")
-        } else {
-            println!("push succeeded")
+pub fn stalled_cycle(memspace: &mut dyn Bus, reg: &mut CpuStatus, addr: Option<u16>) //a clock cycle spent waiting for RDY
+{
+    if reg.cycle_accurate {
+        if let Some(a) = addr {
+            memspace.read(a);
         }
+
+        memspace.cycle();
     }
 
-    write(memory, reg.sp as u16 + 0x101, data)
+    reg.cycles_used += 1;
 }
 
-pub fn pull_stack(memory: &mut [Segment], reg: &mut CpuStatus) -> u8 //pull a byte from the stack and update the pointer
+pub fn dummy_read(memspace: &mut dyn Bus, reg: &mut CpuStatus, addr: u16) //a read the CPU only makes because it has nothing else to do that cycle
 {
-    let pulled: u8 = read(memory, reg.sp as u16 + 0x101);
+    if reg.cycle_accurate {
+        read_cycle(memspace, reg, addr);
+        memspace.take_fault(); //the CPU ignores what it reads, so whether anything answered doesn't matter
+    }
+}
 
-    if reg.debug_text {
-        print!("Pulling {:#04x} from stack... ", pulled)
+pub fn dummy_write(memspace: &mut dyn Bus, reg: &mut CpuStatus, addr: u16, data: u8) //the NMOS read-modify-write instructions write the old value back first
+{
+    if reg.cycle_accurate {
+        write(memspace, reg, addr, data);
+    }
+}
+
+
+pub fn push_stack(memory: &mut dyn Bus, reg: &mut CpuStatus, data: u8)
+//push a byte onto the stack and update the pointer: write to $0100 + SP, then decrement it
+{
+    write(memory, reg, 0x100 | reg.sp as u16, data);
+
+    if reg.sp == 0x00 {
+        reg.stack_fault(CpuError::StackOverflow { pc: reg.pc });
     }
 
     reg.sp = reg.sp.wrapping_sub(1);
+}
 
-    if reg.debug_text {
-        if reg.sp == 0xff {
-            println!("stack underflow!")
-        } else {
-            println!("pull succeeded")
-        }
+pub fn pull_stack(memory: &mut dyn Bus, reg: &mut CpuStatus) -> u8 //pull a byte from the stack and update the pointer: increment SP, then read $0100 + SP
+{
+    if reg.sp == 0xff {
+        reg.stack_fault(CpuError::StackUnderflow { pc: reg.pc });
     }
 
-    return pulled;
+    reg.sp = reg.sp.wrapping_add(1);
+
+    return read(memory, reg, 0x100 | reg.sp as u16);
 }
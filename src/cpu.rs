@@ -1,8 +1,142 @@
+use crate::asm::{Assembled, Assembler};
+use crate::breakpoints::{Breakpoints, Condition};
 use crate::bus;
-use crate::bus::Segment;
+use crate::bus::{Access, Bus, Watchpoints};
+use crate::disasm;
+use crate::opcodes::{Opcode, Mode, NMOS, CMOS, ROCKWELL, WDC};
+use crate::trace::{TraceEntry, Tracer};
+use crate::opcodes::Mnemonic::*;
 
 use text_io::{try_scan, read};
-use std::io::{Read, Error, ErrorKind, Write, stdout};
+use std::fmt;
+use std::io::{Write, stdout};
+
+pub const INPUT_SOURCES: usize = 32;       //sources on each of the IRQ, NMI, RDY and SO inputs. Out of range ids are ignored.
+pub const BUS_SOURCE: usize = 31;          //the source a MemoryMap drives for all of its devices together, 0-30 are free for anything else
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variant //which member of the 6502 family to emulate
+{
+    Nmos,           //the original MOS 6502, undocumented opcodes and bugs included
+    Cmos,           //the plain 65C02, without the bit instructions
+    Rockwell,       //Rockwell R65C02: adds BBR, BBS, RMB and SMB
+    Wdc,            //WDC W65C02S: the Rockwell set plus WAI and STP
+}
+
+impl Variant
+{
+    pub fn from_name(name: &str) -> Option<Variant> //the names accepted by the cpu_variant setting
+    {
+        match name.trim().to_lowercase().as_str()
+        {
+            "6502" | "nmos" => Some(Variant::Nmos),
+            "65c02" | "cmos" => Some(Variant::Cmos),
+            "r65c02" | "rockwell" => Some(Variant::Rockwell),
+            "w65c02s" | "w65c02" | "wdc" => Some(Variant::Wdc),
+            _ => None
+        }
+    }
+
+    pub fn opcodes(self) -> &'static [Opcode; 256]
+    {
+        match self
+        {
+            Variant::Nmos => &NMOS,
+            Variant::Cmos => &CMOS,
+            Variant::Rockwell => &ROCKWELL,
+            Variant::Wdc => &WDC
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StackCheck //what to do when SP wraps around the stack page
+{
+    Off,            //nothing, like the real chip
+    Warn,           //carry on, and leave a warning for take_warnings
+    Halt,           //stop execution with an error
+}
+
+impl StackCheck
+{
+    pub fn from_name(name: &str) -> Option<StackCheck> //the names accepted by the stack_check setting
+    {
+        match name.trim().to_lowercase().as_str()
+        {
+            "off" => Some(StackCheck::Off),
+            "warn" => Some(StackCheck::Warn),
+            "halt" => Some(StackCheck::Halt),
+            _ => None
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CpuError //why execute stopped. The instruction that raised it has finished, so execution can carry on from pc.
+{
+    IllegalOpcode { opcode: u8, addr: u16 },       //an undocumented opcode in strict mode
+    Jam { opcode: u8, addr: u16 },                 //a JAM opcode, which would lock up a real NMOS 6502
+    BusFault { addr: u16, write: bool },           //an access to an address nothing is mapped at
+    BusTrap { addr: u16, write: bool },            //the same, or a write to ROM, with the open bus policy set to trap into the debugger
+    ReadOnly { addr: u16, data: u8 },              //a write to ROM
+    StackOverflow { pc: u16 },                     //SP wrapped from $00 to $ff, with stack_check set to halt
+    StackUnderflow { pc: u16 },                    //SP wrapped from $ff to $00
+    Breakpoint { addr: u16 },
+    Watchpoint { addr: u16, pc: u16, old: u8, new: u8, write: bool }, //pc is the instruction that made the access (the vector during reset and interrupts), old and new match on a read
+}
+
+impl fmt::Display for CpuError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match *self
+        {
+            CpuError::IllegalOpcode { opcode, addr } => write!(f, "Unrecognized opcode {:#04x} at {:#06x}! Halting execution...", opcode, addr),
+            CpuError::Jam { opcode, addr } => write!(f, "Illegal (JAM/HLT/KIL) opcode {:#04x} at {:#06x}! Halting execution...", opcode, addr),
+            CpuError::BusFault { addr, write: false } => write!(f, "Attempt to read from unmapped address {:#06x}!", addr),
+            CpuError::BusFault { addr, write: true } => write!(f, "Attempt to write to unmapped address {:#06x}!", addr),
+            CpuError::BusTrap { addr, write: false } => write!(f, "Trapped a read from unmapped address {:#06x}", addr),
+            CpuError::BusTrap { addr, write: true } => write!(f, "Trapped a write to unmapped or read-only address {:#06x}", addr),
+            CpuError::ReadOnly { addr, data } => write!(f, "Attempt to write {:#04x} to read-only address {:#06x}!", data, addr),
+            CpuError::StackOverflow { pc } => write!(f, "Stack overflow: pushed past $0100, SP wrapped around to $ff at {:#06x}", pc),
+            CpuError::StackUnderflow { pc } => write!(f, "Stack underflow: pulled past $01ff, SP wrapped around to $00 at {:#06x}", pc),
+            CpuError::Breakpoint { addr } => write!(f, "Breakpoint at {:#06x}", addr),
+            CpuError::Watchpoint { addr, pc, new, write: false, .. } => write!(f, "Watchpoint: {:#06x} read {:#04x} from {:#06x}", pc, new, addr),
+            CpuError::Watchpoint { addr, pc, old, new, write: true } => write!(f, "Watchpoint: {:#06x} wrote {:#04x} to {:#06x}, was {:#04x}", pc, new, addr, old),
+        }
+    }
+}
+
+impl std::error::Error for CpuError {}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FrameKind //what pushed a call frame
+{
+    Subroutine,
+    Break,
+    Irq,
+    Nmi,
+}
+
+struct StackFrame //a return address (and status, for interrupts) on the stack, remembered for the stack view
+{
+    kind: FrameKind,
+    sp: u8,         //SP after the frame was pushed, so the frame starts at $0100 + sp + 1
+    from: u16,      //address of the JSR or BRK, or of the instruction the interrupt came in before
+    to: u16,        //the subroutine or handler it went to
+}
+
+impl StackFrame
+{
+    fn size(&self) -> u16
+    {
+        if self.kind == FrameKind::Subroutine { 2 } else { 3 }
+    }
+}
+
+
 pub struct CpuStatus //contains the registers of the CPU, the clock speed, and other settings.
 {
     pub a: u8,
@@ -12,13 +146,35 @@ pub struct CpuStatus //contains the registers of the CPU, the clock speed, and o
     pub sr: u8,
     pub sp: u8,
     pub last_op: u8,
-    pub cycles_used: u8,
+    pub last_pc: u16,       //address of the last instruction's opcode
+    pub watch_pc: u16,      //what watchpoint hits report as the culprit: the running instruction, or the vector during reset and interrupts
+    pub cycles_used: u32,
     pub reset: bool,
-    pub debug_text: bool,
+    pub debug_text: bool,   //print a trace line for every instruction as it runs
     pub clock_time: u64,
     pub running: bool,
-    external_irq: bool,
-    external_nmi: bool
+    pub strict: bool,       //treat undocumented opcodes as errors instead of running them
+    pub magic: u8,          //the constant ORed into A by the unstable ANE and LXA opcodes, it varies from chip to chip
+    pub variant: Variant,
+    pub cycle_accurate: bool, //make every bus cycle of an instruction, dummy reads and writes included, and clock the bus after each one
+    pub stack_check: StackCheck, //how SP wrapping around the stack page is reported
+    pub breakpoints: Breakpoints, //checked by the front end before each instruction, through breakpoint()
+    pub watchpoints: Watchpoints, //checked on every bus access the CPU makes
+    pub tracer: Tracer,     //the instruction trace: a ring buffer of recent instructions, and optionally a log file
+    fault: Option<CpuError>, //a stack or bus error waiting to be returned from execute
+    warnings: Vec<CpuError>, //stack wraps under StackCheck::Warn, waiting for the front end to report them
+    frames: Vec<StackFrame>, //JSR, BRK and interrupt frames still on the stack, oldest first
+    waiting: bool,          //WAI: sleeping until an interrupt comes in
+    stopped: bool,          //STP: stopped until reset
+    irq_sources: u32,       //one bit for each input wired-ORed onto the IRQ line
+    irq_request: bool,      //a one-shot IRQ from the monitor, held until it's taken
+    irq_delay: Option<bool>, //after CLI, SEI and PLP the next interrupt poll still sees the old I flag
+    nmi_sources: u32,
+    nmi_pending: bool,      //an NMI edge that hasn't been taken yet
+    rdy_sources: u32,       //inputs holding RDY low, the CPU stalls while any of them do
+    so_sources: u32,        //inputs on the SO pin, V is set when the line goes low
+    reset_line: bool,       //RESET held low: the CPU idles until it's released, then runs the reset sequence
+    assembler: Assembler,   //the monitor's line assembler, which remembers labels for the rest of the session
 }
 
 
@@ -26,374 +182,385 @@ impl CpuStatus
 {
     pub fn new(speed: u64) -> CpuStatus
     {
-        CpuStatus {a:0, x:0, y:0, pc:0xfffc, sr:0b00100100, sp:0, last_op: 0, cycles_used: 0, reset: true, debug_text: false, clock_time: (1000000000 / speed), running: true, external_irq: false, external_nmi: false}
+        CpuStatus {a:0, x:0, y:0, pc:0xfffc, sr:0b00100100, sp:0, last_op: 0, last_pc: 0, watch_pc: 0xfffc, cycles_used: 0, reset: true, debug_text: false, clock_time: (1000000000 / speed), running: true, strict: false, magic: 0xee, variant: Variant::Nmos, cycle_accurate: false, stack_check: StackCheck::Off, breakpoints: Breakpoints::new(), watchpoints: Watchpoints::new(), tracer: Tracer::new(0), fault: None, warnings: Vec::new(), frames: Vec::new(), waiting: false, stopped: false, irq_sources: 0, irq_request: false, irq_delay: None, nmi_sources: 0, nmi_pending: false, rdy_sources: 0, so_sources: 0, reset_line: false, assembler: Assembler::new()}
     }
 
     pub fn status_report(&mut self)
     {
         println!("Current CPU status:");
-        println!("Last Opcode: {:#04x} X: {:#04x} Y: {:#04x} A: {:#04x} SP: {:#04x} SR: {:#010b} PC: {:#06x}", self.last_op, self.x, self.y, self.a, self.sp, self.sr, self.pc)
+        println!("Last Opcode: {:#04x} ({:?}) X: {:#04x} Y: {:#04x} A: {:#04x} SP: {:#04x} SR: {:#010b} PC: {:#06x}", self.last_op, self.variant.opcodes()[self.last_op as usize].mnemonic, self.x, self.y, self.a, self.sp, self.sr, self.pc)
+    }
+
+
+    pub fn stack_report(&self, memory: &dyn Bus) //print the stack page from SP up, with the JSR, BRK and interrupt frames marked
+    {
+        println!("Stack page, SP: {:#04x}", self.sp);
+        if self.sp == 0xff { println!("(empty)") }
+
+        for offset in (self.sp as u16 + 1)..=0xff
+        {
+            let addr: u16 = 0x100 | offset;
+            let mut note: String = String::new();
+
+            if let Some(frame) = self.frames.iter().find(|frame| frame.sp as u16 + 1 == offset)
+            {
+                let status: u8 = memory.peek(addr);
+                let pc_addr: u16 = if frame.kind == FrameKind::Subroutine { addr } else { addr + 1 };
+                let pushed_pc: u16 = memory.peek(pc_addr) as u16 | (memory.peek(pc_addr.wrapping_add(1)) as u16) << 8;
+                let flags: String = "NV-BDIZC".chars().enumerate().map(|(bit, c)| if status & (0x80 >> bit) != 0 { c } else { '.' }).collect();
+
+                note = match frame.kind
+                {
+                    FrameKind::Subroutine => format!("JSR {:#06x} from {:#06x}, returns to {:#06x}", frame.to, frame.from, pushed_pc.wrapping_add(1)),
+                    FrameKind::Break => format!("BRK at {:#06x} to {:#06x}, P: {}, returns to {:#06x}", frame.from, frame.to, flags, pushed_pc),
+                    FrameKind::Irq => format!("IRQ before {:#06x} to {:#06x}, P: {}, returns to {:#06x}", frame.from, frame.to, flags, pushed_pc),
+                    FrameKind::Nmi => format!("NMI before {:#06x} to {:#06x}, P: {}, returns to {:#06x}", frame.from, frame.to, flags, pushed_pc),
+                };
+            }
+
+            println!("{}", format!("{:#06x}: {:#04x}  {}", addr, memory.peek(addr), note).trim_end());
+        }
     }
 
 
-    pub fn execute<'a>(&mut self, memory: &mut [Segment]) -> Result<u8, String> //runs a single CPU instruction, returns errors if there are any
+    pub fn execute(&mut self, memory: &mut dyn Bus) -> Result<u32, CpuError> //runs a single CPU instruction, returns errors if there are any
     {
         self.cycles_used = 0;
-        let addr: u16;
         let flag: bool;
+        let byte: u8;
+
+        if self.reset_line                                               //RESET held low: nothing runs until it's let go
+        {
+            bus::stalled_cycle(memory, self, None);
+            return self.finish()
+        }
 
         if self.reset                                                    //do we need to reset the CPU?
         {
+            self.watch_pc = 0xfffc;
+            bus::dummy_read(memory, self, self.pc);                 //reset runs the interrupt sequence with writes turned off
+            bus::dummy_read(memory, self, self.pc);
+            for _ in 0..3                                           //the three pushes become reads, but SP still goes down
+            {
+                bus::dummy_read(memory, self, 0x100 | self.sp as u16);
+                self.sp = self.sp.wrapping_sub(1);
+            }
+
             self.pc = 0xfffc;
             self.pc = bus::absolute(memory, self);           //set new program counter at reset routine
             
+            self.set_interrupt(true);
+            if self.variant != Variant::Nmos { self.set_decimal(false) }  //the 65C02 comes out of reset in binary mode
+
             self.cycles_used += 7;
             self.reset = false;
+            self.frames.clear();
+            self.waiting = false;
+            self.stopped = false;
+            self.irq_delay = None;
 
             if self.debug_text { println!("Starting program execution at {:#06x}", self.pc) }
         }
 
-        if self.external_nmi                                           //was there a non-maskable interrupt request?
+        memory.update_inputs(self);                              //sample the IRQ, NMI, RDY and SO inputs at the instruction boundary
+        if self.rdy_held()                                              //RDY low holds the CPU on its next read, a cycle at a time
         {
-            bus::push_stack(memory, self, ((self.pc & 0xff00) >> 8) as u8); //handle NMI
-            bus::push_stack(memory, self, (self.pc & 0x00ff) as u8);
-            bus::push_stack(memory, self, self.sr);
+            bus::stalled_cycle(memory, self, Some(self.pc));
+            return self.finish()
+        }
 
-            self.pc = 0xfffa;
-            self.pc = bus::absolute(memory, self);
+        if self.nmi_pending || self.irq_line() { self.waiting = false }  //WAI wakes up even if the interrupt is masked
 
-            self.set_interrupt(true);
+        let masked: bool = self.irq_delay.take().unwrap_or(self.interrupt_flag());
 
+        if self.stopped {}                                              //only a reset gets out of STP
+        else if self.nmi_pending                                        //was there a non-maskable interrupt request?
+        {
+            self.watch_pc = 0xfffa;
+            bus::dummy_read(memory, self, self.pc);
+            bus::dummy_read(memory, self, self.pc);
+            self.interrupt(memory, FrameKind::Nmi);
             self.cycles_used += 7;
-            self.external_nmi = false;
-            self.external_irq = false;
         }
-        else if self.external_irq                                            //was there an interrupt request?
+        else if self.irq_line() && !masked                              //is the IRQ line held low with interrupts enabled?
         {
-            bus::push_stack(memory, self, ((self.pc & 0xff00) >> 8) as u8); //handle IRQ
-            bus::push_stack(memory, self, (self.pc & 0x00ff) as u8);
-            bus::push_stack(memory, self, self.sr);
-
-            self.pc = 0xfffe;
-            self.pc = bus::absolute(memory, self);
-
-            self.set_interrupt(true);
-
+            self.watch_pc = 0xfffe;
+            bus::dummy_read(memory, self, self.pc);
+            bus::dummy_read(memory, self, self.pc);
+            self.interrupt(memory, FrameKind::Irq);
             self.cycles_used += 7;
-            self.external_irq = false;
         }
 
-        let opcode: u8 = bus::read(memory, self.pc);        //get the current opcode
-        self.last_op = opcode;
-
-        self.pc = self.pc.wrapping_add(1); 
-
-        match opcode            //which instruction is it?
+        if self.stopped || self.waiting                     //after STP or WAI the clock keeps running but nothing happens
         {
-            //Add With Carry
-            0x69 => {self.adc(memory, 2, self.pc); self.pc = self.pc.wrapping_add(1)}, //ADC Immediate
-            0x65 => {addr = bus::zp(memory, self); self.adc(memory, 3, addr)}, //ADC ZP
-            0x75 => {addr = bus::zp_x(memory, self); self.adc(memory, 4, addr)}, //ADC ZP,X
-            0x6d => {addr = bus::absolute(memory, self); self.adc(memory, 4, addr)}, //ADC Absolute
-            0x7d => {addr = bus::absolute_x(memory, self, true); self.adc(memory, 4, addr)}, //ADC Absolute,X
-            0x79 => {addr = bus::absolute_y(memory, self, true); self.adc(memory, 4, addr)}, //ADC Absolute,Y
-            0x61 => {addr = bus::indirect_x(memory, self); self.adc(memory, 6, addr)}, //ADC Indirect,X
-            0x71 => {addr = bus::indirect_y(memory, self, true); self.adc(memory, 5, addr)}, //ADC Indirect,Y
-
-            //And Bitwise with Accumulator
-            0x29 => {self.and(memory, 2, self.pc); self.pc = self.pc.wrapping_add(1)}, //AND Immediate
-            0x25 => {addr = bus::zp(memory, self); self.and(memory, 3, addr)}, //AND ZP
-            0x35 => {addr = bus::zp_x(memory, self); self.and(memory, 4, addr)}, //AND ZP,X
-            0x2d => {addr = bus::absolute(memory, self); self.and(memory, 4, addr)}, //AND Absolute
-            0x3d => {addr = bus::absolute_x(memory, self, true); self.and(memory, 4, addr)}, //AND Absolute,X
-            0x39 => {addr = bus::absolute_y(memory, self, true); self.and(memory, 4, addr)}, //AND Absolute,Y
-            0x21 => {addr = bus::indirect_x(memory, self); self.and(memory, 6, addr)}, //AND Indirect,X
-            0x31 => {addr = bus::indirect_y(memory, self, true); self.and(memory, 5, addr)}, //AND Indirect,Y
-
-            //Arithmetic Shift Left
-            0x0a => {self.asl(memory, 2, None)}, //ASL A
-            0x06 => {addr = bus::zp(memory, self); self.asl(memory, 5, Some(addr))}, //ASL ZP
-            0x16 => {addr = bus::zp_x(memory, self); self.asl(memory, 6, Some(addr))}, //ASL ZP,X
-            0x0e => {addr = bus::absolute(memory, self); self.asl(memory, 6, Some(addr))}, //ASL Absolute
-            0x1e => {addr = bus::absolute_x(memory, self, true); self.asl(memory, 7, Some(addr))}, //ASL Absolute,X
-
-            //Bit Test
-            0x24 => {addr = bus::zp(memory, self); self.bit(memory, 3, addr)}, // BIT ZP
-            0x2c => {addr = bus::absolute(memory, self); self.bit(memory, 4, addr)}, // BIT Absolute
-
-            //Branch Instructions
-            0x10 => {flag = !self.negative_flag(); self.branch(memory, flag)}, //BPL Branch on PLus
-            0x30 => {flag = self.negative_flag(); self.branch(memory, flag)}, //BMI Branch on MInus
-            0x50 => {flag = !self.overflow_flag(); self.branch(memory, flag)}, //BVC Branch on oVerflow Clear
-            0x70 => {flag = self.overflow_flag(); self.branch(memory, flag)}, //BVS Branch on oVerflow Set
-            0x90 => {flag = !self.carry_flag(); self.branch(memory, flag)}, //BCC Branch on Carry Clear
-            0xb0 => {flag = self.carry_flag(); self.branch(memory, flag)}, //BCS Branch on Carry Set
-            0xd0 => {flag = !self.zero_flag(); self.branch(memory, flag)}, //BNE Branch on Not Equal
-            0xf0 => {flag = self.zero_flag(); self.branch(memory, flag)}, //BEQ Branch on EQual
-
-            //Break
-            0x00 => {self.brk(memory, 7)},
-
-            //Clear Flag Instructions
-            0x18 => {self.cycles_used += 2; self.set_carry(false)}, //CLC
-            0xd8 => {self.cycles_used += 2; self.set_decimal(false)}, //CLD
-            0x58 => {self.cycles_used += 2; self.set_interrupt(false)}, //CLI
-            0xb8 => {self.cycles_used += 2; self.set_overflow(false)}, //CLV
-
-
-            //Compare with Accumulator
-            0xc9 => {self.cmp(memory, 2, self.pc); self.pc = self.pc.wrapping_add(1)}, //CMP Immediate
-            0xc5 => {addr = bus::zp(memory, self); self.cmp(memory, 3, addr)}, //CMP ZP
-            0xd5 => {addr = bus::zp_x(memory, self); self.cmp(memory, 4, addr)}, //CMP ZP,X
-            0xcd => {addr = bus::absolute(memory, self); self.cmp(memory, 4, addr)}, //CMP Absolute
-            0xdd => {addr = bus::absolute_x(memory, self, true); self.cmp(memory, 4, addr)}, //CMP Absolute,X
-            0xd9 => {addr = bus::absolute_y(memory, self, true); self.cmp(memory, 4, addr)}, //CMP Absolute,Y
-            0xc1 => {addr = bus::indirect_x(memory, self); self.cmp(memory, 6, addr)}, //CMP Indirect,X
-            0xd1 => {addr = bus::indirect_y(memory, self, true); self.cmp(memory, 5, addr)}, //CMP Indirect,Y
-
-
-            //Compare with X
-            0xe0 => {self.cpx(memory, 2, self.pc); self.pc = self.pc.wrapping_add(1)}, //CPX Immediate
-            0xe4 => {addr = bus::zp(memory, self); self.cpx(memory, 3, addr)}, //CPX ZP
-            0xec => {addr = bus::absolute(memory, self); self.cpx(memory, 4, addr)}, //CPX Absolute
-
-
-            //Compare with Y
-            0xc0 => {self.cpy(memory, 2, self.pc); self.pc = self.pc.wrapping_add(1)}, //CPY Immediate
-            0xc4 => {addr = bus::zp(memory, self); self.cpy(memory, 3, addr)}, //CPY ZP
-            0xcc => {addr = bus::absolute(memory, self); self.cpy(memory, 4, addr)}, //CPY Absolute
-
-
-            //Decrement Memory
-            0xc6 => {addr = bus::zp(memory, self); self.dec(memory, 5, addr)}, //DEC ZP
-            0xd6 => {addr = bus::zp_x(memory, self); self.dec(memory, 6, addr)}, //DEC ZP,X
-            0xce => {addr = bus::absolute(memory, self); self.dec(memory, 6, addr)}, //DEC Absolute
-            0xde => {addr = bus::absolute_x(memory, self, true); self.dec(memory, 7, addr)}, //DEC Absolute,X
-
-
-            //Decrement X
-            0xca => {self.x = self.x.wrapping_sub(1); self.set_zero(self.x == 0); self.set_negative(self.x > 0x7f); self.cycles_used += 2}, //DEX
-
-
-            //Decrement Y
-            0x88 => {self.y = self.y.wrapping_sub(1); self.set_zero(self.y == 0); self.set_negative(self.y > 0x7f);  self.cycles_used += 2}, //DEY
-
-
-            //Exclusive OR
-            0x49 => {self.eor(memory, 2, self.pc); self.pc = self.pc.wrapping_add(1)}, //EOR Immediate
-            0x45 => {addr = bus::zp(memory, self); self.eor(memory, 3, addr)}, //EOR ZP
-            0x55 => {addr = bus::zp_x(memory, self); self.eor(memory, 4, addr)}, //EOR ZP,X
-            0x4d => {addr = bus::absolute(memory, self); self.eor(memory, 4, addr)}, //EOR Absolute
-            0x5d => {addr = bus::absolute_x(memory, self, true); self.eor(memory, 4, addr)}, //EOR Absolute,X
-            0x59 => {addr = bus::absolute_y(memory, self, true); self.eor(memory, 4, addr)}, //EOR Absolute,Y
-            0x41 => {addr = bus::indirect_x(memory, self); self.eor(memory, 4, addr)}, //EOR Indirect,X
-            0x51 => {addr = bus::indirect_y(memory, self, true); self.eor(memory, 4, addr)}, //EOR Indirect,Y
-
-            //Halt (invalid opcodes that would freeze the CPU on a real NMOS 6502)
-            0x02 | 0x12 | 0x22 | 0x32 | 0x42 | 0x52 | 0x62 | 0x72 | 0x92 | 0xb2 | 0xd2 | 0xf2 => return Err(format!("Illegal (JAM/HLT/KIL) opcode {:#04x}! Halting execution...", self.last_op)),
-
-            //Increment Memory
-            0xe6 => {addr = bus::zp(memory, self); self.inc(memory, 5, addr)}, //INC ZP
-            0xf6 => {addr = bus::zp_x(memory, self); self.inc(memory, 6, addr)}, //INC ZP,X
-            0xee => {addr = bus::absolute(memory, self); self.inc(memory, 6, addr)}, //INC Absolute
-            0xfe => {addr = bus::absolute_x(memory, self, true); self.inc(memory, 7, addr)}, //INC Absolute,X
-
-
-            //Increment X
-            0xe8 => {self.x = self.x.wrapping_add(1); self.set_zero(self.x == 0); self.set_negative(self.x > 0x7f);  self.cycles_used += 2}, //INX
-
-
-            //Increment Y
-            0xc8 => {self.y = self.y.wrapping_add(1); self.set_zero(self.y == 0); self.set_negative(self.y > 0x7f);  self.cycles_used += 2}, //INY
+            bus::dummy_read(memory, self, self.pc);
+            self.cycles_used += 1;
+            return self.finish()
+        }
 
+        if self.debug_text || self.tracer.active() { self.trace(memory) }
 
-            //Jump
-            0x4c => {addr = bus::absolute(memory, self); self.jmp(3, addr)}, //JMP Absolute
-            0x6c => {addr = bus::indirect(memory, self); self.jmp(5, addr)}, //JMP Indirect
+        self.watch_pc = self.pc;
+        let opcode: u8 = bus::read(memory, self, self.pc);        //get the current opcode
+        self.last_op = opcode;
+        self.last_pc = self.pc;
 
+        let op: &Opcode = &self.variant.opcodes()[opcode as usize]; //mnemonic, addressing mode and timing all come from the table
 
-            //Jump to Subroutine
-            0x20 => {addr = bus::absolute(memory, self); self.jsr(memory, 6, addr)}, //JSR Absolute
+        if op.mnemonic == JAM { return Err(CpuError::Jam { opcode, addr: self.pc }) } //these would freeze the CPU on a real NMOS 6502
+        if op.undocumented && self.strict { return Err(CpuError::IllegalOpcode { opcode, addr: self.pc }) }
 
+        self.pc = self.pc.wrapping_add(1);
 
-            //Load A
-            0xa9 => {self.lda(memory, 2, self.pc); self.pc = self.pc.wrapping_add(1)}, //LDA Immediate
-            0xa5 => {addr = bus::zp(memory, self); self.lda(memory, 3, addr)}, //LDA ZP
-            0xb5 => {addr = bus::zp_x(memory, self); self.lda(memory, 4, addr)}, //LDA ZP,X
-            0xad => {addr = bus::absolute(memory, self); self.lda(memory, 4, addr)}, //LDA Absolute
-            0xbd => {addr = bus::absolute_x(memory, self, true); self.lda(memory, 4, addr)}, //LDA Absolute,X
-            0xb9 => {addr = bus::absolute_y(memory, self, true); self.lda(memory, 4, addr)}, //LDA Absolute,Y
-            0xa1 => {addr = bus::indirect_x(memory, self); self.lda(memory, 6, addr)}, //LDA Indirect,X
-            0xb1 => {addr = bus::indirect_y(memory, self, true); self.lda(memory, 5, addr)}, //LDA Indirect,Y
+        let i_before: bool = self.interrupt_flag();
+        let operand: Option<u16> = if op.mnemonic == JSR { None } else { self.operand_address(memory, op) }; //None for implied and accumulator instructions, JSR fetches its own
+        let addr: u16 = operand.unwrap_or(0);
+        self.cycles_used += op.cycles as u32;
 
+        match op.mnemonic            //which instruction is it?
+        {
+            ADC => self.adc(memory, addr),  //Add With Carry
+            AND => self.and(memory, addr),  //And Bitwise with Accumulator
+            ASL => {self.asl(memory, operand);},   //Arithmetic Shift Left
+            BIT => if op.mode == Mode::Immediate { byte = bus::read(memory, self, addr); self.set_zero(byte & self.a == 0) } else { self.bit(memory, addr) }, //Bit Test. BIT # only sets Z.
 
-            //Load X
-            0xa2 => {self.ldx(memory, 2, self.pc); self.pc = self.pc.wrapping_add(1)}, //LDX Immediate
-            0xa6 => {addr = bus::zp(memory, self); self.ldx(memory, 3, addr)}, //LDX ZP
-            0xb6 => {addr = bus::zp_y(memory, self); self.ldx(memory, 4, addr)}, //LDX ZP,Y
-            0xae => {addr = bus::absolute(memory, self); self.ldx(memory, 4, addr)}, //LDX Absolute
-            0xbe => {addr = bus::absolute_y(memory, self, true); self.ldx(memory, 4, addr)}, //LDX Absolute,Y
+            //Branch Instructions
+            BPL => {flag = !self.negative_flag(); self.branch(memory, flag, addr)}, //BPL Branch on PLus
+            BMI => {flag = self.negative_flag(); self.branch(memory, flag, addr)}, //BMI Branch on MInus
+            BVC => {flag = !self.overflow_flag(); self.branch(memory, flag, addr)}, //BVC Branch on oVerflow Clear
+            BVS => {flag = self.overflow_flag(); self.branch(memory, flag, addr)}, //BVS Branch on oVerflow Set
+            BCC => {flag = !self.carry_flag(); self.branch(memory, flag, addr)}, //BCC Branch on Carry Clear
+            BCS => {flag = self.carry_flag(); self.branch(memory, flag, addr)}, //BCS Branch on Carry Set
+            BNE => {flag = !self.zero_flag(); self.branch(memory, flag, addr)}, //BNE Branch on Not Equal
+            BEQ => {flag = self.zero_flag(); self.branch(memory, flag, addr)}, //BEQ Branch on EQual
+            BRA => self.branch(memory, true, addr), //BRA BRanch Always
+
+            BRK => self.brk(memory),        //Break
 
+            //Clear Flag Instructions
+            CLC => self.set_carry(false),
+            CLD => self.set_decimal(false),
+            CLI => self.set_interrupt(false),
+            CLV => self.set_overflow(false),
 
-            //Load Y
-            0xa0 => {self.ldy(memory, 2, self.pc); self.pc = self.pc.wrapping_add(1)}, //LDY Immediate
-            0xa4 => {addr = bus::zp(memory, self); self.ldy(memory, 3, addr)}, //LDY ZP
-            0xb4 => {addr = bus::zp_x(memory, self); self.ldy(memory, 4, addr)}, //LDY ZP,X
-            0xac => {addr = bus::absolute(memory, self); self.ldy(memory, 4, addr)}, //LDY Absolute
-            0xbc => {addr = bus::absolute_x(memory, self, true); self.ldy(memory, 4, addr)}, //LDY Absolute,X
+            //Compare Instructions
+            CMP => self.cmp(memory, addr),
+            CPX => self.cpx(memory, addr),
+            CPY => self.cpy(memory, addr),
 
+            //Decrement Instructions
+            DEC => {self.dec(memory, operand);},
+            DEX => {self.x = self.x.wrapping_sub(1); self.set_zero(self.x == 0); self.set_negative(self.x > 0x7f)},
+            DEY => {self.y = self.y.wrapping_sub(1); self.set_zero(self.y == 0); self.set_negative(self.y > 0x7f)},
 
-            //Logical Shift Right
-            0x4a => {self.lsr(memory, 2, None)}, //LSR A
-            0x46 => {addr = bus::zp(memory, self); self.lsr(memory, 5, Some(addr))}, //LSR ZP
-            0x56 => {addr = bus::zp_x(memory, self); self.lsr(memory, 6, Some(addr))}, //LSR ZP,X
-            0x4e => {addr = bus::absolute(memory, self); self.lsr(memory, 6, Some(addr))}, //LSR Absolute
-            0x5e => {addr = bus::absolute_x(memory, self, false); self.lsr(memory, 7, Some(addr))}, //LSR Absolute,X
+            EOR => self.eor(memory, addr),  //Exclusive OR
 
+            //Increment Instructions
+            INC => {self.inc(memory, operand);},
+            INX => {self.x = self.x.wrapping_add(1); self.set_zero(self.x == 0); self.set_negative(self.x > 0x7f)},
+            INY => {self.y = self.y.wrapping_add(1); self.set_zero(self.y == 0); self.set_negative(self.y > 0x7f)},
 
-            //OR with Accumulator
-            0x09 => {self.ora(memory, 2, self.pc); self.pc = self.pc.wrapping_add(1)}, //ORA Immediate
-            0x05 => {addr = bus::zp(memory, self); self.ora(memory, 3, addr)}, //ORA ZP
-            0x15 => {addr = bus::zp_x(memory, self); self.ora(memory, 4, addr)}, //ORA ZP,X
-            0x0d => {addr = bus::absolute(memory, self); self.ora(memory, 4, addr)}, //ORA Absolute
-            0x1d => {addr = bus::absolute_x(memory, self, true); self.ora(memory, 4, addr)}, //ORA Absolute,X
-            0x19 => {addr = bus::absolute_y(memory, self, true); self.ora(memory, 4, addr)}, //ORA Absolute,Y
-            0x01 => {addr = bus::indirect_x(memory, self); self.ora(memory, 4, addr)}, //ORA Indirect,X
-            0x11 => {addr = bus::indirect_y(memory, self, true); self.ora(memory, 4, addr)}, //ORA Indirect,Y
+            JMP => self.jmp(addr),          //Jump
+            JSR => self.jsr(memory),        //Jump to Subroutine
 
+            //Load Instructions
+            LDA => self.lda(memory, addr),
+            LDX => self.ldx(memory, addr),
+            LDY => self.ldy(memory, addr),
 
-            //No Operation
-            0xea => {self.cycles_used += 2}, //NOP
+            LSR => {self.lsr(memory, operand);},   //Logical Shift Right
+            NOP => self.nop(memory, operand, op.cycles),  //No Operation. The undocumented ones still read their operand.
+            ORA => self.ora(memory, addr),  //OR with Accumulator
 
+            //Rotate Instructions
+            ROL => {self.rol(memory, operand);},
+            ROR => {self.ror(memory, operand);},
 
-            //Rotate Left
-            0x2a => {self.rol(memory, 2, None)}, //ROL A
-            0x26 => {addr = bus::zp(memory, self); self.rol(memory, 5, Some(addr))}, //ROL ZP
-            0x36 => {addr = bus::zp_x(memory, self); self.rol(memory, 6, Some(addr))}, //ROL ZP,X
-            0x2e => {addr = bus::absolute(memory, self); self.rol(memory, 6, Some(addr))}, //ROL Absolute
-            0x3e => {addr = bus::absolute_x(memory, self, true); self.rol(memory, 7, Some(addr))}, //ROL Absolute,X
+            RTI => self.rti(memory),        //Return from Interrupt
+            RTS => self.rts(memory),        //Return from Subroutine
+            SBC => self.sbc(memory, addr),  //Subtract with Carry
 
+            //Stack Instructions
+            TXS => self.transfer('x', 's'),
+            TSX => self.transfer('s', 'x'),
+            PHA => bus::push_stack(memory, self, self.a),
+            PLA => {self.stack_dummy(memory); self.a = bus::pull_stack(memory, self); self.set_negative(self.a > 0x7f); self.set_zero(self.a == 0)},
+            PHP => bus::push_stack(memory, self, self.sr | 0x30),
+            PLP => {self.stack_dummy(memory); self.sr = (bus::pull_stack(memory, self) & 0xcf) | 0x20},  //B only exists in the pushed copy of P
+            PHX => bus::push_stack(memory, self, self.x),
+            PHY => bus::push_stack(memory, self, self.y),
+            PLX => {self.stack_dummy(memory); self.x = bus::pull_stack(memory, self); self.set_negative(self.x > 0x7f); self.set_zero(self.x == 0)},
+            PLY => {self.stack_dummy(memory); self.y = bus::pull_stack(memory, self); self.set_negative(self.y > 0x7f); self.set_zero(self.y == 0)},
 
-            //Rotate Right
-            0x6a => {self.ror(memory, 2, None)}, //ROR A
-            0x66 => {addr = bus::zp(memory, self); self.ror(memory, 5, Some(addr))}, //ROR ZP
-            0x76 => {addr = bus::zp_x(memory, self); self.ror(memory, 6, Some(addr))}, //ROR ZP,X
-            0x6e => {addr = bus::absolute(memory, self); self.ror(memory, 6, Some(addr))}, //ROR Absolute
-            0x7e => {addr = bus::absolute_x(memory, self, true); self.ror(memory, 7, Some(addr))}, //ROR Absolute,X
+            //Set Flag Instructions
+            SEC => self.set_carry(true),
+            SED => self.set_decimal(true),
+            SEI => self.set_interrupt(true),
 
+            //Store Instructions
+            STA => self.sta(memory, addr),
+            STX => self.stx(memory, addr),
+            STY => self.sty(memory, addr),
+            STZ => bus::write(memory, self, addr, 0),
 
-            //Return from Interrupt
-            0x40 => {self.rti(memory, 6)},
+            //Transfer Register Value
+            TAX => self.transfer('a', 'x'),
+            TAY => self.transfer('a', 'y'),
+            TXA => self.transfer('x', 'a'),
+            TYA => self.transfer('y', 'a'),
+
+            //Undocumented NMOS Instructions
+            ALR => {self.and(memory, addr); self.lsr(memory, None);},    //AND, then LSR A
+            ANC => {self.and(memory, addr); flag = self.negative_flag(); self.set_carry(flag)},   //AND, then N copied into C
+            ANE => {self.a = (self.a | self.magic) & self.x & bus::read(memory, self, addr); self.set_negative(self.a > 0x7f); self.set_zero(self.a == 0)},    //unstable
+            ARR => self.arr(memory, addr),
+            DCP => {byte = self.dec(memory, operand); self.compare(self.a, byte)},    //DEC, then CMP
+            ISC => {byte = self.inc(memory, operand); self.subtract(byte)},           //INC, then SBC
+            LAS => {self.a = bus::read(memory, self, addr) & self.sp; self.x = self.a; self.sp = self.a; self.set_negative(self.a > 0x7f); self.set_zero(self.a == 0)},
+            LAX => {self.lda(memory, addr); self.x = self.a},
+            LXA => {self.a = (self.a | self.magic) & bus::read(memory, self, addr); self.x = self.a; self.set_negative(self.a > 0x7f); self.set_zero(self.a == 0)},   //unstable
+            RLA => {byte = self.rol(memory, operand); self.a &= byte; self.set_negative(self.a > 0x7f); self.set_zero(self.a == 0)},    //ROL, then AND
+            RRA => {byte = self.ror(memory, operand); self.add(byte)},          //ROR, then ADC
+            SAX => bus::write(memory, self, addr, self.a & self.x),
+            SBX => self.sbx(memory, addr),
+            SHA => self.unstable_store(memory, addr, self.y, self.a & self.x),
+            SHX => self.unstable_store(memory, addr, self.y, self.x),
+            SHY => self.unstable_store(memory, addr, self.x, self.y),
+            SLO => {byte = self.asl(memory, operand); self.a |= byte; self.set_negative(self.a > 0x7f); self.set_zero(self.a == 0)},    //ASL, then ORA
+            SRE => {byte = self.lsr(memory, operand); self.a ^= byte; self.set_negative(self.a > 0x7f); self.set_zero(self.a == 0)},    //LSR, then EOR
+            TAS => {self.sp = self.a & self.x; self.unstable_store(memory, addr, self.y, self.sp)},
+
+            //65C02 Instructions
+            TRB => self.trb(memory, addr),  //Test and Reset Bits
+            TSB => self.tsb(memory, addr),  //Test and Set Bits
+            RMB => self.set_bit(memory, addr, (opcode >> 4) & 7, false),   //Reset Memory Bit
+            SMB => self.set_bit(memory, addr, (opcode >> 4) & 7, true),    //Set Memory Bit
+            BBR => self.bit_branch(memory, addr, (opcode >> 4) & 7, false), //Branch on Bit Reset
+            BBS => self.bit_branch(memory, addr, (opcode >> 4) & 7, true),  //Branch on Bit Set
+            WAI => {bus::dummy_read(memory, self, self.pc); self.waiting = true},     //WAit for Interrupt
+            STP => {bus::dummy_read(memory, self, self.pc); self.stopped = true},     //SToP the clock
+
+            JAM => ()                       //caught before the operand fetch
+        }
 
+        if matches!(op.mnemonic, CLI | SEI | PLP) { self.irq_delay = Some(i_before) } //the poll for the next instruction happens before I changes
 
-            //Return from Subroutine
-            0x60 => {self.rts(memory, 6)},
+        return self.finish()
+    }
 
 
-            //Subtract with Carry
-            0xe9 => {self.sbc(memory, 2, self.pc); self.pc = self.pc.wrapping_add(1)}, //SBC Immediate
-            0xe5 => {addr = bus::zp(memory, self); self.sbc(memory, 3, addr)}, //SBC ZP
-            0xf5 => {addr = bus::zp_x(memory, self); self.sbc(memory, 4, addr)}, //SBC ZP,X
-            0xed => {addr = bus::absolute(memory, self); self.sbc(memory, 4, addr)}, //SBC Absolute
-            0xfd => {addr = bus::absolute_x(memory, self, true); self.sbc(memory, 4, addr)}, //SBC Absolute,X
-            0xf9 => {addr = bus::absolute_y(memory, self, true); self.sbc(memory, 4, addr)}, //SBC Absolute,Y
-            0xe1 => {addr = bus::indirect_x(memory, self); self.sbc(memory, 6, addr)}, //SBC Indirect,X
-            0xf1 => {addr = bus::indirect_y(memory, self, true); self.sbc(memory, 5, addr)}, //SBC Indirect,Y
+    fn finish(&mut self) -> Result<u32, CpuError> //end of an instruction: forget frames that have been pulled, and report any stack or bus error
+    {
+        while let Some(frame) = self.frames.last()
+        {
+            if (self.sp as u16) < frame.sp as u16 + frame.size() { break }
+            self.frames.pop();
+        }
 
+        self.tracer.cycles += self.cycles_used as u64;
+        if let Some(e) = self.fault.take() { return Err(e) }
 
-            //Stack Instructions
-            0x9a => {self.cycles_used += 2; self.transfer('x', 's')}, //TXS
-            0xba => {self.cycles_used += 2; self.transfer('s', 'x')}, //TSX
-            0x48 => {self.cycles_used += 3; bus::push_stack(memory, self, self.a)}, //PHA
-            0x68 => {self.cycles_used += 4; self.a = bus::pull_stack(memory, self); self.set_negative(self.a > 0x7f); self.set_zero(self.a == 0)},     //PLA
-            0x08 => {self.cycles_used += 3; bus::push_stack(memory, self, self.sr | 0x30)},                   //PHP
-            0x28 => {self.cycles_used += 4; self.sr = self.sr & 0x30 | (bus::pull_stack(memory, self) & 0xcf)},    //PLP
+        Ok(self.cycles_used)
+    }
 
 
-            //Set Flag Instructions
-            0x38 => {self.cycles_used += 2; self.set_carry(true)}, //SEC
-            0xf8 => {self.cycles_used += 2; self.set_decimal(true)}, //SED
-            0x78 => {self.cycles_used += 2; self.set_interrupt(true)}, //SEI
+    fn trace(&mut self, memory: &dyn Bus) //log the instruction at PC before it runs
+    {
+        let entry: TraceEntry = TraceEntry { pc: self.pc, bytes: [memory.peek(self.pc), memory.peek(self.pc.wrapping_add(1)), memory.peek(self.pc.wrapping_add(2))],
+                                             a: self.a, x: self.x, y: self.y, p: self.sr, sp: self.sp, cycles: self.tracer.cycles + self.cycles_used as u64, variant: self.variant };
 
+        if self.debug_text { println!("{}", entry.format(self.tracer.format)) }
+        self.tracer.record(entry);
+    }
 
-            //Store A
-            0x85 => {addr = bus::zp(memory, self); self.sta(memory, 3, addr)}, //STA ZP
-            0x95 => {addr = bus::zp_x(memory, self); self.sta(memory, 4, addr)}, //STA ZP,X
-            0x8d => {addr = bus::absolute(memory, self); self.sta(memory, 4, addr)}, //STA Absolute
-            0x9d => {addr = bus::absolute_x(memory, self, true); self.sta(memory, 5, addr)}, //STA Absolute,X
-            0x99 => {addr = bus::absolute_y(memory, self, true); self.sta(memory, 5, addr)}, //STA Absolute,Y
-            0x81 => {addr = bus::indirect_x(memory, self); self.sta(memory, 6, addr)}, //STA Indirect,X
-            0x91 => {addr = bus::indirect_y(memory, self, true); self.sta(memory, 6, addr)}, //STA Indirect,Y
 
+    fn push_frame(&mut self, kind: FrameKind, from: u16) //remember a frame that was just pushed, for the stack view
+    {
+        if self.frames.len() == 128 { self.frames.remove(0); }    //a runaway recursion can't fill up more than the stack page anyway
 
-            //Store X
-            0x86 => {addr = bus::zp(memory, self); self.stx(memory, 3, addr)}, //STX ZP
-            0x96 => {addr = bus::zp_y(memory, self); self.stx(memory, 4, addr)}, //STX ZP,Y
-            0x8e => {addr = bus::absolute(memory, self); self.stx(memory, 4, addr)}, //STX Absolute
+        self.frames.push(StackFrame { kind, sp: self.sp, from, to: self.pc });
+    }
 
-            
-            //Store Y
-            0x84 => {addr = bus::zp(memory, self); self.sty(memory, 3, addr)}, //STY ZP
-            0x94 => {addr = bus::zp_x(memory, self); self.sty(memory, 4, addr)}, //STY ZP,X
-            0x8c => {addr = bus::absolute(memory, self); self.sty(memory, 4, addr)}, //STY Absolute
 
+    pub(crate) fn stack_fault(&mut self, error: CpuError) //called by push_stack and pull_stack when SP wraps around
+    {
+        match self.stack_check
+        {
+            StackCheck::Off => (),
+            StackCheck::Warn =>
+            {
+                if self.warnings.len() == 128 { self.warnings.remove(0); }    //nobody's collecting them, keep the latest
+                self.warnings.push(error);
+            },
+            StackCheck::Halt => self.raise(error)
+        }
+    }
 
-            //Transfer Register Value
-            0xaa => {self.cycles_used += 2; self.transfer('a', 'x')}, //TAX
-            0xa8 => {self.cycles_used += 2; self.transfer('a', 'y')}, //TAY
-            0x8a => {self.cycles_used += 2; self.transfer('x', 'a')}, //TXA
-            0x98 => {self.cycles_used += 2; self.transfer('y', 'a')}, //TYA
+    pub fn breakpoint(&mut self) -> Option<CpuError> //should execution stop before the instruction at PC? Counts the hit if so.
+    {
+        let mut breakpoints: Breakpoints = std::mem::take(&mut self.breakpoints);
+        let stop: Option<CpuError> = breakpoints.check(self);
+        self.breakpoints = breakpoints;
 
+        return stop
+    }
 
-            other => return Err(format!("Unrecognized opcode {:#04x}! Halting execution...", other)) //whoops! invalid opcode
-        }
+    pub fn take_warnings(&mut self) -> Vec<CpuError> //the warnings since the last call, oldest first
+    {
+        return std::mem::take(&mut self.warnings)
+    }
 
-        Ok(self.cycles_used)
+    pub(crate) fn raise(&mut self, error: CpuError) //hold an error until the instruction finishes, only the first one counts
+    {
+        if self.fault.is_none() { self.fault = Some(error) }
     }
 
     
-   pub fn debug_mode(&mut self, memory: &mut [Segment]) -> bool
+   pub fn debug_mode(&mut self, memory: &mut dyn Bus) -> bool
    {
+        self.tracer.flush();                        //so the log file is up to date while paused
         let last_cmd: String = read!("{}\n");       //get text input and store it whole
 
         let poke = CpuStatus::parse_poke(&last_cmd);
         let peek = CpuStatus::parse_peek(&last_cmd);
+        let listing = CpuStatus::parse_listing(&last_cmd);
+        let assembly = CpuStatus::parse_assemble(&last_cmd);
 
-        if poke.is_ok()
+        if let Ok(poke_t) = poke
         {
-            let poke_t = poke.unwrap();
-            bus::write(memory, poke_t.0, poke_t.1);
+            memory.write(poke_t.0, poke_t.1);           //straight to the bus, the monitor doesn't use up clock cycles
             println!("Wrote {:#04x} to address {:#06x}", poke_t.1, poke_t.0);
         }
-        else if peek.is_ok()
+        else if let Ok(peek_a) = peek
         {
-            let peek_a = peek.unwrap();
-            let peek_b = bus::read(memory,peek_a);
+            let peek_b = memory.peek(peek_a);
             println!("Read {:#04x} from address {:#06x}", peek_b, peek_a);
         }
+        else if let Some((start, count)) = listing                     //d command: disassemble from an address, or from PC
+        {
+            for line in disasm::disassemble(memory, self.variant, start.unwrap_or(self.pc), count) { println!("{}", line) }
+        }
+        else if let Some(start) = assembly                             //a command: assemble lines into memory until a blank one
+        {
+            self.assemble_lines(memory, start.unwrap_or(self.pc));
+        }
+        else if self.breakpoint_command(memory, &last_cmd) || self.watchpoint_command(&last_cmd) {} //b, bc, bl, until and next, then w, wc and wl
+        else if let Some(count) = CpuStatus::parse_trace(&last_cmd)   //trace command: the last few instructions from the ring buffer
+        {
+            if self.tracer.recent(count).next().is_none() { println!("Trace buffer is empty") }
+            for entry in self.tracer.recent(count) { println!("{}", entry.format(self.tracer.format)) }
+        }
         else
         {
             match last_cmd.trim()           //check for single-word commands with no arguments
             {
-                "verbose" => self.debug_text = !self.debug_text, //enable or disable debug commentary
-                "run" => self.running = true,                     //run command: start running code
+                "verbose" => self.debug_text = !self.debug_text, //enable or disable the trace line printed for each instruction
+                "run" => self.resume(),                           //run command: start running code
                 "reset" => self.reset = true,                    //reset command: reset the CPU
                 "status" => self.status_report(),      //status command: get status of registers
+                "stack" => self.stack_report(memory),  //stack command: show the stack page and the call frames on it
     
-                "step" =>                                        //step command: run a single operation and display results
-                {   let check: Result<u8, String> = self.execute(memory);
-                    if check.is_err()
-                    {
-                        println!("{}",check.unwrap_err());
-                    }
-                    else 
-                    {
-                        let cycles_taken: u8 = check.unwrap();
-                        if self.debug_text {println!("Instruction used {} cycles...", cycles_taken)};
-                    }
-    
-                    self.status_report(); 
-                },
+                "step" => self.step(memory),                     //step command: run a single operation and display results
                 
                 "irq" => self.irq(),
                 "nmi" => self.nmi(),
@@ -403,121 +570,419 @@ impl CpuStatus
         }
         
         print!(">");
-        stdout().flush();
+        let _ = stdout().flush();
         return true;
    }
 
 
-   fn parse_peek(cmd: &String) -> Result<u16, Box<dyn std::error::Error>>
+   fn operand_address(&mut self, memory: &mut dyn Bus, op: &Opcode) -> Option<u16> //effective address for the opcode's addressing mode, with the page crossing cycle added where the table says so
    {
         let addr: u16;
 
-        try_scan!(cmd.trim().bytes() => "{}", addr);
+        match op.mode
+        {
+            Mode::Implied | Mode::Accumulator =>
+            {
+                if op.cycles > 1 { bus::dummy_read(memory, self, self.pc) }  //everything but the 65C02's one cycle NOPs reads the next byte and ignores it
+                return None
+            }
+            Mode::Immediate => {addr = self.pc; self.pc = self.pc.wrapping_add(1)},
+            Mode::ZeroPage => addr = bus::zp(memory, self),
+            Mode::ZeroPageX => addr = bus::zp_x(memory, self),
+            Mode::ZeroPageY => addr = bus::zp_y(memory, self),
+            Mode::Absolute => addr = bus::absolute(memory, self),
+            Mode::AbsoluteX => addr = bus::absolute_x(memory, self, op.page_cross),
+            Mode::AbsoluteY => addr = bus::absolute_y(memory, self, op.page_cross),
+            Mode::Indirect => addr = bus::indirect(memory, self),
+            Mode::IndirectX => addr = bus::indirect_x(memory, self),
+            Mode::IndirectY => addr = bus::indirect_y(memory, self, op.page_cross),
+            Mode::ZeroPageIndirect => addr = bus::zp_indirect(memory, self),
+            Mode::AbsoluteIndexedIndirect => addr = bus::absolute_indexed_indirect(memory, self),
+            Mode::ZeroPageRelative => addr = bus::zp(memory, self),     //the branch offset is fetched by BBR/BBS
+            Mode::Relative =>                                   //branch target, relative to the next instruction
+            {
+                let offset: u8 = bus::read(memory, self, self.pc);
+                self.pc = self.pc.wrapping_add(1);
+                addr = self.pc.wrapping_add(offset as i8 as u16);
+            }
+        }
 
-        Ok(addr)
+        return Some(addr)
    }
 
 
-   fn parse_poke(cmd: &String) -> Result<(u16, u8), Box<dyn std::error::Error>>
+   fn parse_peek(cmd: &str) -> Result<u16, Box<dyn std::error::Error>>
    {
         let addr: u16;
-        let byte: u8;
 
-        try_scan!(cmd.trim().bytes() => "{}:{}", addr, byte);
+        try_scan!(cmd.trim().bytes() => "{}", addr);
 
-        Ok((addr, byte))
+        Ok(addr)
    }
 
 
-   fn adc(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+   fn step(&mut self, memory: &mut dyn Bus) //run one instruction and show it, ignoring breakpoints
+   {
+        let check: Result<u32, CpuError> = self.execute(memory);
+        println!("{}", disasm::disassemble_one(memory, self.variant, self.last_pc));
+        for warning in self.take_warnings() { println!("{}", warning) }
+        match check
+        {
+            Err(e) => println!("{}", e),
+            Ok(cycles_taken) => if self.debug_text {println!("Instruction used {} cycles...", cycles_taken)}
+        }
+
+        self.status_report();
+   }
+
+   fn resume(&mut self) //leave the monitor, without stopping at a breakpoint on the instruction at PC straight away
    {
-        let byte: u8 = bus::read(memory, i_addr);
+        self.breakpoints.resume(self.pc);
+        self.running = true;
+   }
 
-        let mut result: u16;
 
-        match self.decimal_flag() //is this a BCD operation?
+   fn breakpoint_command(&mut self, memory: &mut dyn Bus, cmd: &str) -> bool //returns false if cmd isn't one of these
+   {
+        let words: Vec<&str> = cmd.split_whitespace().collect();
+
+        match words.as_slice()
         {
-            true => //BCD add (this implementation is based upon Py6502's version)
+            ["b", addr, rest @ ..] =>                           //b <addr> [if <register><comparison><value>] [after <hits>]
             {
-                let mut half_carry: bool = false;
-                let mut hi_adjust: u8 = 0;
-                let mut lo_adjust: u8 = 0;
+                let addr: Option<u16> = CpuStatus::parse_number(addr);
+                let after_at: usize = rest.iter().position(|w| *w == "after").unwrap_or(rest.len());
+                let condition: Option<Option<Condition>> = match rest[..after_at]
+                {
+                    [] => Some(None),
+                    ["if", ref condition @ ..] => Condition::parse(&condition.concat()).map(Some),
+                    _ => None
+                };
+                let after: Option<u32> = match rest[after_at..]
+                {
+                    [] => Some(1),
+                    ["after", hits] => hits.parse().ok(),
+                    _ => None
+                };
 
-                let mut lo_nibble: u8 = (self.a & 0xf) + (byte & 0xf) + (self.carry_flag() as u8); //low bits of A + low bits of byte + Carry flag
-                
-                if lo_nibble > 9
+                match (addr, condition, after)
+                {
+                    (Some(addr), Some(condition), Some(after)) =>
+                    {
+                        let id: usize = self.breakpoints.add(addr, condition, after);
+                        println!("Breakpoint {} at {:#06x}", id, addr);
+                    }
+                    _ => println!("Usage: b <addr> [if <a|x|y|sp|p|pc><==|!=|<|<=|>|>=><value>] [after <hits>]")
+                }
+            }
+            ["bc", id] =>
+            {
+                match id.parse::<usize>()
+                {
+                    Ok(id) if self.breakpoints.remove(id) => println!("Cleared breakpoint {}", id),
+                    _ => println!("No breakpoint {}", id)
+                }
+            }
+            ["bl"] =>
+            {
+                if self.breakpoints.list().is_empty() { println!("No breakpoints") }
+                for bp in self.breakpoints.list() { println!("{}", bp) }
+            }
+            ["until", addr] =>                                  //run until PC gets to addr
+            {
+                match CpuStatus::parse_number(addr)
+                {
+                    Some(addr) =>
+                    {
+                        self.breakpoints.run_until(addr, None);
+                        self.resume();
+                    }
+                    None => println!("Usage: until <addr>")
+                }
+            }
+            ["next"] =>                                         //step, but run a whole subroutine if it's a JSR
+            {
+                if memory.peek(self.pc) == 0x20
                 {
-                    lo_adjust = 6;
-                    half_carry = true;
+                    self.breakpoints.run_until(self.pc.wrapping_add(3), Some(self.sp));
+                    self.resume();
                 }
+                else { self.step(memory) }
+            }
+            _ => return false
+        }
+
+        return true
+   }
 
-                let mut hi_nibble: u8 = ( (self.a >> 4) & 0xf ) + ( (byte>> 4) & 0xf ) + (half_carry as u8); //high bits of A + high bits of byte + Carry from low bits result
 
-                self.set_carry(hi_nibble > 9);
-                if self.carry_flag()
+   fn watchpoint_command(&mut self, cmd: &str) -> bool //returns false if cmd isn't one of these
+   {
+        let words: Vec<&str> = cmd.split_whitespace().collect();
+
+        match words.as_slice()
+        {
+            ["w", range, rest @ ..] =>                          //w <addr>[-<end>] [r|w|rw] [value]
+            {
+                let (start, end) = match range.split_once('-')
                 {
-                    hi_adjust = 6;
+                    Some((start, end)) => (CpuStatus::parse_number(start), CpuStatus::parse_number(end)),
+                    None => (CpuStatus::parse_number(range), CpuStatus::parse_number(range))
+                };
+                let (access, rest): (Option<Access>, &[&str]) = match rest
+                {
+                    ["r", rest @ ..] => (Some(Access::Read), rest),
+                    ["w", rest @ ..] => (Some(Access::Write), rest),
+                    ["rw", rest @ ..] => (Some(Access::Both), rest),
+                    _ => (Some(Access::Both), rest)
+                };
+                let value: Option<Option<u8>> = match rest
+                {
+                    [] => Some(None),
+                    [value] => CpuStatus::parse_number(value).and_then(|v| u8::try_from(v).ok()).map(Some),
+                    _ => None
+                };
+
+                match (start, end, access, value)
+                {
+                    (Some(start), Some(end), Some(access), Some(value)) if start <= end =>
+                    {
+                        let id: usize = self.watchpoints.add(start, end, access, value);
+                        println!("Watchpoint {} on {:#06x}-{:#06x}", id, start, end);
+                    }
+                    _ => println!("Usage: w <addr>[-<end>] [r|w|rw] [value]")
                 }
+            }
+            ["wc", id] =>
+            {
+                match id.parse::<usize>()
+                {
+                    Ok(id) if self.watchpoints.remove(id) => println!("Cleared watchpoint {}", id),
+                    _ => println!("No watchpoint {}", id)
+                }
+            }
+            ["wl"] =>
+            {
+                if self.watchpoints.list().is_empty() { println!("No watchpoints") }
+                for wp in self.watchpoints.list() { println!("{}", wp) }
+            }
+            _ => return false
+        }
 
-                //ALU result without decimal adjustments
-                lo_nibble &= 0xf;
-                hi_nibble &= 0xf;
-                let alu_result: u8 = (hi_nibble << 4) + lo_nibble;
+        return true
+   }
 
-                self.set_zero(alu_result == 0);
-                self.set_negative(alu_result > 0x7f);
-                self.set_overflow((byte & 0x80 == self.a & 0x80) && (alu_result & 0x80 != byte & 0x80));
 
-                //Final A result with adjustment
-                lo_nibble = (lo_nibble + lo_adjust) & 0xf;
-                hi_nibble = (hi_nibble + hi_adjust) & 0xf;
-                result = u16::from((hi_nibble << 4) + lo_nibble);
+   fn assemble_lines(&mut self, memory: &mut dyn Bus, start: u16) //read assembly a line at a time and write each instruction as it's assembled
+   {
+        let mut addr: u16 = start;
+
+        loop
+        {
+            print!("{:04X}  ", addr);
+            let _ = stdout().flush();
+
+            let line: String = read!("{}\n");
+            if line.trim().is_empty() { break }
+
+            let result: Result<Assembled, String> = self.assembler.assemble_line(self.variant, addr, &line);
+            match result
+            {
+                Err(e) => println!("{}", e),
+                Ok(out) =>
+                {
+                    for (i, byte) in out.bytes.iter().enumerate() { bus::write(memory, self, addr.wrapping_add(i as u16), *byte) }
+                    for (at, byte) in out.patches { bus::write(memory, self, at, byte) }
+                    if let Some(e) = self.fault.take() { println!("{}", e) }
+
+                    if !out.bytes.is_empty() { println!("      {}", disasm::disassemble_one(memory, self.variant, addr)) }
+                    addr = addr.wrapping_add(out.bytes.len() as u16);
+                }
             }
-            false => //Normal binary add
+        }
+
+        let missing: Vec<String> = self.assembler.unresolved();
+        if !missing.is_empty() { println!("Still undefined: {}", missing.join(", ")) }
+   }
+
+
+   fn parse_assemble(cmd: &str) -> Option<Option<u16>> //"a" or "a <addr>"
+   {
+        let mut words = cmd.split_whitespace();
+        if words.next() != Some("a") { return None }
+
+        let start: Option<u16> = match words.next()
+        {
+            Some(word) => Some(CpuStatus::parse_number(word)?),
+            None => None
+        };
+
+        if words.next().is_some() { return None }
+        return Some(start)
+   }
+
+
+   fn parse_trace(cmd: &str) -> Option<usize> //"trace" or "trace <count>", 20 unless told otherwise
+   {
+        match cmd.split_whitespace().collect::<Vec<&str>>().as_slice()
+        {
+            ["trace"] => Some(20),
+            ["trace", count] => count.parse().ok(),
+            _ => None
+        }
+   }
+
+
+   fn parse_listing(cmd: &str) -> Option<(Option<u16>, usize)> //"d", "d <addr>" or "d <addr> <count>", 16 instructions unless told otherwise
+   {
+        let mut words = cmd.split_whitespace();
+        if words.next() != Some("d") { return None }
+
+        let start: Option<u16> = match words.next()
+        {
+            Some(word) => Some(CpuStatus::parse_number(word)?),
+            None => None
+        };
+        let count: usize = match words.next()
+        {
+            Some(word) => word.parse().ok()?,
+            None => 16
+        };
+
+        if words.next().is_some() { return None }
+        return Some((start, count))
+   }
+
+
+   pub(crate) fn parse_number(word: &str) -> Option<u16> //decimal like the peek and poke commands, or hex with a $ or 0x in front
+   {
+        if let Some(hex) = word.strip_prefix('$').or_else(|| word.strip_prefix("0x"))
+        {
+            return u16::from_str_radix(hex, 16).ok()
+        }
+
+        return word.parse().ok()
+   }
+
+
+   fn parse_poke(cmd: &str) -> Result<(u16, u8), Box<dyn std::error::Error>>
+   {
+        let addr: u16;
+        let byte: u8;
+
+        try_scan!(cmd.trim().bytes() => "{}:{}", addr, byte);
+
+        Ok((addr, byte))
+   }
+
+
+   fn adc(&mut self, memory: &mut dyn Bus, i_addr: u16) 
+   {
+        let byte: u8 = bus::read(memory, self, i_addr);
+        self.add(byte);
+        self.decimal_dummy(memory, i_addr);
+   }
+
+
+   fn add(&mut self, byte: u8) //the arithmetic behind ADC, shared with RRA
+   {
+        let carry: u16 = self.carry_flag() as u16;
+        let binary: u16 = self.a as u16 + byte as u16 + carry;
+
+        match self.decimal_flag() //is this a BCD operation?
+        {
+            true => //BCD add, following Bruce Clark's description of what the chips do with any input, invalid BCD included
             {
-                result = self.a as u16 + byte as u16 + self.carry_flag() as u16; // A + Byte + Carry
+                let mut lo_nibble: u16 = (self.a & 0xf) as u16 + (byte & 0xf) as u16 + carry;
+                if lo_nibble >= 0xa { lo_nibble = ((lo_nibble + 6) & 0xf) + 0x10 }
+
+                let mut result: u16 = (self.a & 0xf0) as u16 + (byte & 0xf0) as u16 + lo_nibble;
+                let signed: i16 = (self.a & 0xf0) as i8 as i16 + (byte & 0xf0) as i8 as i16 + lo_nibble as i16;
+
+                //the NMOS part takes N and V from the sum before the high nibble is adjusted, and Z from the binary sum
+                self.set_negative(result & 0x80 != 0);
+                self.set_overflow(!(-128..=127).contains(&signed));
+                self.set_zero(binary & 0xff == 0);
 
+                if result >= 0xa0 { result += 0x60 }
                 self.set_carry(result > 0xff);
+                self.a = result as u8;
 
-                if self.carry_flag()
+                if self.variant != Variant::Nmos //the 65C02 spends an extra cycle getting N and Z right
                 {
-                    result &= 0xff;
+                    self.set_negative(self.a > 0x7f);
+                    self.set_zero(self.a == 0);
+                    self.cycles_used += 1;
                 }
+            }
+            false => //Normal binary add
+            {
+                self.set_carry(binary > 0xff);
+                self.set_overflow((self.a ^ binary as u8) & (byte ^ binary as u8) & 0x80 != 0);
 
-                self.set_overflow((byte & 0x80 == self.a & 0x80) && (result as u8 & 0x80 != byte & 0x80));
-                self.set_zero(result == 0);
-                self.set_negative(result > 0x7f);
+                self.a = binary as u8;
+                self.set_zero(self.a == 0);
+                self.set_negative(self.a > 0x7f);
             }
         }
-
-        self.a = result as u8;
-
-        self.cycles_used += cycles
-    }
+   }
 
 
-    fn and(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn and(&mut self, memory: &mut dyn Bus, i_addr: u16) 
     {
-        let byte: u8 = bus::read(memory, i_addr);
+        let byte: u8 = bus::read(memory, self, i_addr);
 
         self.a &= byte;
 
         self.set_negative(self.a > 0x7f);
         self.set_zero(self.a == 0);
+    }
+
+
+    fn arr(&mut self, memory: &mut dyn Bus, i_addr: u16) //AND, then ROR A, with flags from the adder that's wired in behind the shifter
+    {
+        let and_result: u8 = self.a & bus::read(memory, self, i_addr);
+        let carry_in: bool = self.carry_flag();
+        let mut result: u8 = (and_result >> 1) | ((carry_in as u8) << 7);
 
-        self.cycles_used += cycles
+        if self.decimal_flag()      //NMOS decimal mode runs the BCD fixup on the shifted value
+        {
+            self.set_negative(carry_in);
+            self.set_zero(result == 0);
+            self.set_overflow((result ^ and_result) & 0x40 != 0);
+
+            if (and_result & 0x0f) + (and_result & 0x01) > 5
+            {
+                result = (result & 0xf0) | (result.wrapping_add(6) & 0x0f);
+            }
+
+            let carry: bool = (and_result & 0xf0) as u16 + (and_result & 0x10) as u16 > 0x50;
+            if carry { result = result.wrapping_add(0x60) }
+            self.set_carry(carry);
+        }
+        else
+        {
+            self.set_negative(result > 0x7f);
+            self.set_zero(result == 0);
+            self.set_carry(result & 0x40 != 0);
+            self.set_overflow(((result >> 6) ^ (result >> 5)) & 1 != 0);
+        }
+
+        self.a = result;
     }
 
 
-    fn asl(&mut self, memory: &mut [Segment], cycles: u8, i_addr: Option<u16>) 
+    fn asl(&mut self, memory: &mut dyn Bus, i_addr: Option<u16>) -> u8 
     {
         let mut byte: u8;
 
         match i_addr {
-            Some(v) => byte = bus::read(memory, v),
+            Some(v) => byte = bus::read(memory, self, v),
             None => byte = self.a,
         };
 
+        if let Some(v) = i_addr { self.modify_dummy(memory, v, byte) }
+
         self.set_carry(0 != byte & 0b10000000);
 
         byte <<= 1;
@@ -525,250 +990,249 @@ impl CpuStatus
         self.set_zero(byte == 0);
 
         match i_addr {
-            Some(v) => bus::write(memory, v, byte),
+            Some(v) => bus::write(memory, self, v, byte),
             None => self.a = byte,
         };
 
-        self.cycles_used += cycles
+        return byte
     }
 
 
-    fn bit(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn bit(&mut self, memory: &mut dyn Bus, i_addr: u16) 
     {
-        let byte: u8 = bus::read(memory, i_addr);
+        let byte: u8 = bus::read(memory, self, i_addr);
 
         self.set_negative(0 != byte & 0b10000000);
         self.set_overflow(0 != byte & 0b1000000);
         self.set_zero(0 == byte & self.a);
-
-        self.cycles_used += cycles
     }
 
 
-    fn branch(&mut self, memory: &mut [Segment], flag: bool)
-    //basis for all branch instructions
+    fn branch(&mut self, memory: &mut dyn Bus, flag: bool, target: u16)
+    //basis for all branch instructions, the target has already been worked out by relative addressing
     {
-        self.cycles_used += 2; //use two cycles no matter what
-
         if flag
         //if the flag we tested is true and we should branch:
         {
             self.cycles_used += 1; //use another cycle
+            bus::dummy_read(memory, self, self.pc);
 
-            let old_pc: u16 = self.pc; //store the old program counter to compare against later
-            let offset: u8 = bus::read(memory, self.pc); //read the next byte to get the offset
-            self.pc += 1;
-
-            if offset < 127 //if the byte is positive, move PC forward that many bytes
-            {
-                self.pc += offset as u16;
-            } 
-            else //if the byte is negative, invert all the bits of the offset to convert it to positive again and then subtract from the PC
-            {
-                self.pc -= !offset as u16 + 1;
-            }
-
-            if old_pc & 0x100 != self.pc & 0x100
+            if self.pc & 0xff00 != target & 0xff00
             //use another cycle if we crossed a page boundary
             {
                 self.cycles_used += 1;
+                bus::dummy_read(memory, self, (self.pc & 0xff00) | (target & 0x00ff));
             }
 
-            if self.debug_text {
-                println!(
-                    "Branching from address {:#06x} to {:#06x}...",
-                    old_pc, self.pc
-                )
-            }
-        } else
-        //if the flag is false then just increment the program counter and do nothing else
-        {
-            self.pc += 1;
-
-            if self.debug_text {
-                println!("Branch condition evaluated but not taken.")
-            }
+            self.pc = target;
         }
     }
 
 
-    fn brk(&mut self, memory: &mut [Segment], cycles: u8)
+    fn brk(&mut self, memory: &mut dyn Bus)
+    {
+        self.pc = self.pc.wrapping_add(1); //skip the signature byte after BRK, which was read as the dummy operand
+        self.interrupt(memory, FrameKind::Break);
+    }
+
+
+    fn interrupt(&mut self, memory: &mut dyn Bus, kind: FrameKind) //push PC and P and jump through the vector, for BRK, IRQ and NMI
     {
-        self.pc += 1;
-        self.set_break(true);
+        let from: u16 = if kind == FrameKind::Break { self.pc.wrapping_sub(2) } else { self.pc };
+        let status: u8 = if kind == FrameKind::Break { self.sr | 0x30 } else { (self.sr & !0x10) | 0x20 }; //B is only set in the copy BRK pushes
+        let mut vector: u16 = if kind == FrameKind::Nmi { 0xfffa } else { 0xfffe };
+        let mut frame: FrameKind = kind;
+
+        if kind == FrameKind::Irq { self.irq_request = false }
 
         bus::push_stack(memory, self, ((self.pc & 0xff00) >> 8) as u8);
         bus::push_stack(memory, self, (self.pc & 0x00ff) as u8);
-        bus::push_stack(memory, self, self.sr);
 
-        self.pc = 0xfffe;
-        self.pc = bus::absolute(memory, self);
+        memory.update_inputs(self);
+        if self.nmi_pending     //an NMI that comes in while the return address is pushed takes over the vector fetch, even from BRK
+        {
+            self.nmi_pending = false;
+            vector = 0xfffa;
+            if kind == FrameKind::Irq { frame = FrameKind::Nmi; self.watch_pc = vector }
+        }
 
-        self.cycles_used += cycles;
+        bus::push_stack(memory, self, status);
+
+        self.set_interrupt(true);
+        if self.variant != Variant::Nmos { self.set_decimal(false) }  //the 65C02 clears D on every interrupt
+
+        self.pc = vector;
+        self.pc = bus::absolute(memory, self);
+        self.push_frame(frame, from);
     }
 
 
-    fn cmp(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn compare(&mut self, register: u8, byte: u8) //flags for CMP, CPX, CPY and DCP
     {
-        let byte: u8 = bus::read(memory, i_addr);
+        self.set_carry(register >= byte);
+        self.set_zero(register == byte);
+        self.set_negative(register.wrapping_sub(byte) > 0x7f);
+    }
 
-        self.set_carry(self.a >= byte);
-        self.set_zero(self.a == byte);
-        self.set_negative(self.a.wrapping_sub(byte) > 0x7f);
 
-        self.cycles_used += cycles
+    fn cmp(&mut self, memory: &mut dyn Bus, i_addr: u16) 
+    {
+        let byte: u8 = bus::read(memory, self, i_addr);
+
+        self.compare(self.a, byte);
     }
 
 
-    fn cpx(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn cpx(&mut self, memory: &mut dyn Bus, i_addr: u16) 
     {
-        let byte: u8 = bus::read(memory, i_addr);
+        let byte: u8 = bus::read(memory, self, i_addr);
 
-        self.set_carry(self.x >= byte);
-        self.set_zero(self.x == byte);
-        self.set_negative(self.x.wrapping_sub(byte) > 0x7f);
-
-        self.cycles_used += cycles
+        self.compare(self.x, byte);
     }
 
 
-    fn cpy(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn cpy(&mut self, memory: &mut dyn Bus, i_addr: u16) 
     {
-        let byte: u8 = bus::read(memory, i_addr);
-
-        self.set_carry(self.y >= byte);
-        self.set_zero(self.y == byte);
-        self.set_negative(self.y.wrapping_sub(byte) > 0x7f);
+        let byte: u8 = bus::read(memory, self, i_addr);
 
-        self.cycles_used += cycles
+        self.compare(self.y, byte);
     }
 
 
-    fn dec(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn dec(&mut self, memory: &mut dyn Bus, i_addr: Option<u16>) -> u8 
     {
-        let mut byte: u8 = bus::read(memory, i_addr);
+        let mut byte: u8;
+
+        match i_addr {
+            Some(v) => byte = bus::read(memory, self, v),
+            None => byte = self.a,
+        };
+
+        if let Some(v) = i_addr { self.modify_dummy(memory, v, byte) }
 
         byte = byte.wrapping_sub(1);
 
         self.set_negative(byte > 0x7f);
         self.set_zero(byte == 0);
 
-        bus::write(memory, i_addr, byte);
+        match i_addr {
+            Some(v) => bus::write(memory, self, v, byte),
+            None => self.a = byte,
+        };
 
-        self.cycles_used += cycles
+        return byte
     }
 
 
-    fn eor(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn eor(&mut self, memory: &mut dyn Bus, i_addr: u16) 
     {
-        let byte: u8 = bus::read(memory, i_addr);
+        let byte: u8 = bus::read(memory, self, i_addr);
 
         self.a ^= byte;
 
         self.set_negative(self.a > 0x7f);
         self.set_zero(self.a == 0);
-
-        self.cycles_used += cycles
     }
 
 
-    fn inc(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn inc(&mut self, memory: &mut dyn Bus, i_addr: Option<u16>) -> u8 
     {
-        let mut byte: u8 = bus::read(memory, i_addr);
+        let mut byte: u8;
+
+        match i_addr {
+            Some(v) => byte = bus::read(memory, self, v),
+            None => byte = self.a,
+        };
+
+        if let Some(v) = i_addr { self.modify_dummy(memory, v, byte) }
 
         byte = byte.wrapping_add(1);
 
         self.set_negative(byte > 0x7f);
         self.set_zero(byte == 0);
 
-        bus::write(memory, i_addr, byte);
+        match i_addr {
+            Some(v) => bus::write(memory, self, v, byte),
+            None => self.a = byte,
+        };
 
-        self.cycles_used += cycles
+        return byte
     }
 
 
-    fn jmp(&mut self, cycles: u8, i_addr: u16) 
+    fn jmp(&mut self, i_addr: u16) 
     {
         self.pc = i_addr;
-
-        self.cycles_used += cycles;
-
-        if self.debug_text {
-            println!("JMP to new address {:#06x}...", self.pc)
-        }
     }
 
 
-    fn jsr(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn jsr(&mut self, memory: &mut dyn Bus) //the high byte of the address is only fetched after the return address is pushed
     {
-        let return_addr: u16 = self.pc - 1;
+        let from: u16 = self.pc.wrapping_sub(1);
+        let target_lo: u8 = bus::read(memory, self, self.pc);
+        self.pc = self.pc.wrapping_add(1);
+        self.stack_dummy(memory);
+
+        let return_addr: u16 = self.pc;
         let return_byte_lo: u8 = (return_addr & 0xff) as u8;
         let return_byte_hi: u8 = ((return_addr & 0xff00) >> 8) as u8;
 
         bus::push_stack(memory, self, return_byte_hi);
         bus::push_stack(memory, self, return_byte_lo);
 
-        self.pc = i_addr;
-
-        self.cycles_used += cycles;
-
-        if self.debug_text {
-            println!("JSR to new address {:#06x}...", self.pc)
-        }
+        let target_hi: u8 = bus::read(memory, self, self.pc);
+        self.pc = ((target_hi as u16) << 8) + target_lo as u16;
+        self.push_frame(FrameKind::Subroutine, from);
     }
 
 
-    fn lda(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn lda(&mut self, memory: &mut dyn Bus, i_addr: u16) 
     {
         let byte: u8;
-        byte = bus::read(memory, i_addr);
+        byte = bus::read(memory, self, i_addr);
 
         self.a = byte;
 
         self.set_negative(self.a > 0x7f);
         self.set_zero(self.a == 0);
-
-        self.cycles_used += cycles
     }
 
 
-    fn ldx(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn ldx(&mut self, memory: &mut dyn Bus, i_addr: u16) 
     {
         let byte: u8;
-        byte = bus::read(memory, i_addr);
+        byte = bus::read(memory, self, i_addr);
 
         self.x = byte;
 
         self.set_negative(self.x > 0x7f);
         self.set_zero(self.x == 0);
-
-        self.cycles_used += cycles
     }
 
 
-    fn ldy(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn ldy(&mut self, memory: &mut dyn Bus, i_addr: u16) 
     {
         let byte: u8;
-        byte = bus::read(memory, i_addr);
+        byte = bus::read(memory, self, i_addr);
 
         self.y = byte;
 
         self.set_negative(self.y > 0x7f);
         self.set_zero(self.y == 0);
-
-        self.cycles_used += cycles
     }
 
 
-    fn lsr(&mut self, memory: &mut [Segment], cycles: u8, i_addr: Option<u16>) 
+    fn lsr(&mut self, memory: &mut dyn Bus, i_addr: Option<u16>) -> u8 
     {
         let mut byte: u8;
 
         match i_addr {
-            Some(v) => byte = bus::read(memory, v),
+            Some(v) => byte = bus::read(memory, self, v),
             None => byte = self.a,
         };
 
+        if let Some(v) = i_addr { self.modify_dummy(memory, v, byte) }
+
         self.set_carry(0 != byte & 0b1);
 
         byte >>= 1;
@@ -776,36 +1240,36 @@ impl CpuStatus
         self.set_zero(byte == 0);
 
         match i_addr {
-            Some(v) => bus::write(memory, v, byte),
+            Some(v) => bus::write(memory, self, v, byte),
             None => self.a = byte,
         };
 
-        self.cycles_used += cycles
+        return byte
     }
 
 
-    fn ora(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn ora(&mut self, memory: &mut dyn Bus, i_addr: u16) 
     {
-        let byte: u8 = bus::read(memory, i_addr);
+        let byte: u8 = bus::read(memory, self, i_addr);
 
         self.a |= byte;
 
         self.set_negative(self.a > 0x7f);
         self.set_zero(self.a == 0);
-
-        self.cycles_used += cycles
     }
 
 
-    fn rol(&mut self, memory: &mut [Segment], cycles: u8, i_addr: Option<u16>) 
+    fn rol(&mut self, memory: &mut dyn Bus, i_addr: Option<u16>) -> u8 
     {
         let mut byte: u8;
 
         match i_addr {
-            Some(v) => byte = bus::read(memory, v),
+            Some(v) => byte = bus::read(memory, self, v),
             None => byte = self.a,
         };
 
+        if let Some(v) = i_addr { self.modify_dummy(memory, v, byte) }
+
         let new_carry = 0 != byte & 0b10000000;
 
         byte <<= 1;
@@ -817,23 +1281,25 @@ impl CpuStatus
         self.set_zero(byte == 0);
 
         match i_addr {
-            Some(v) => bus::write(memory, v, byte),
+            Some(v) => bus::write(memory, self, v, byte),
             None => self.a = byte,
         };
 
-        self.cycles_used += cycles
+        return byte
     }
 
 
-    fn ror(&mut self, memory: &mut [Segment], cycles: u8, i_addr: Option<u16>) 
+    fn ror(&mut self, memory: &mut dyn Bus, i_addr: Option<u16>) -> u8 
     {
         let mut byte: u8;
 
         match i_addr {
-            Some(v) => byte = bus::read(memory, v),
+            Some(v) => byte = bus::read(memory, self, v),
             None => byte = self.a,
         };
 
+        if let Some(v) = i_addr { self.modify_dummy(memory, v, byte) }
+
         let new_carry = 0 != byte & 0b1;
 
         byte >>= 1;
@@ -845,127 +1311,201 @@ impl CpuStatus
         self.set_zero(byte == 0);
 
         match i_addr {
-            Some(v) => bus::write(memory, v, byte),
+            Some(v) => bus::write(memory, self, v, byte),
             None => self.a = byte,
         };
 
-        self.cycles_used += cycles
+        return byte
     }
 
 
-    fn rti(&mut self, memory: &mut [Segment], cycles: u8) 
+    fn rti(&mut self, memory: &mut dyn Bus) 
     {
-        self.sr = self.sr & 0x30 | (bus::pull_stack(memory, self) & 0xcf);
+        self.stack_dummy(memory);
+        self.sr = (bus::pull_stack(memory, self) & 0xcf) | 0x20;
 
         let return_byte_lo: u8 = bus::pull_stack(memory, self);
         let return_byte_hi: u8 = bus::pull_stack(memory, self);
 
         self.pc = ((return_byte_hi as u16) << 8) + return_byte_lo as u16;
-
-        self.cycles_used += cycles
     }
 
 
-    fn rts(&mut self, memory: &mut [Segment], cycles: u8) 
+    fn rts(&mut self, memory: &mut dyn Bus) 
     {
+        self.stack_dummy(memory);
+
         let return_byte_lo: u8 = bus::pull_stack(memory, self);
         let return_byte_hi: u8 = bus::pull_stack(memory, self);
 
-        self.pc = (((return_byte_hi as u16) << 8) + return_byte_lo as u16) + 1;
+        self.pc = ((return_byte_hi as u16) << 8) + return_byte_lo as u16;
+        bus::dummy_read(memory, self, self.pc);         //the CPU spends a cycle stepping past the last byte of the JSR
+        self.pc = self.pc.wrapping_add(1);
+    }
 
-        self.cycles_used += cycles
+
+    fn sbc(&mut self, memory: &mut dyn Bus, i_addr: u16) 
+    {
+        let byte: u8 = bus::read(memory, self, i_addr);
+        self.subtract(byte);
+        self.decimal_dummy(memory, i_addr);
     }
 
 
-    fn sbc(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn subtract(&mut self, byte: u8) //the arithmetic behind SBC, shared with ISC
     {
-        let byte: u8 = bus::read(memory, i_addr); //the only difference between add and subtract is using the inverse of the byte to be added!
-        let c_byte = !byte;
+        let borrow: i16 = 1 - self.carry_flag() as i16;
+        let binary: u16 = self.a as u16 + !byte as u16 + self.carry_flag() as u16; //subtracting is adding the inverse of the byte
 
-        let mut result: u16;
+        //C and V always come from the binary result, and so do N and Z on the NMOS part
+        self.set_carry(binary > 0xff);
+        self.set_overflow((self.a ^ binary as u8) & (!byte ^ binary as u8) & 0x80 != 0);
+        self.set_negative(binary & 0x80 != 0);
+        self.set_zero(binary & 0xff == 0);
 
-        match self.decimal_flag() 
+        match self.decimal_flag()
         {
-            true => //BCD
+            true => //BCD subtract, the two families adjust the result in different ways
             {
-                let mut half_carry: bool = false;
-                let mut hi_adjust: u8 = 0;
-                let mut lo_adjust: u8 = 0;
+                let mut lo_nibble: i16 = (self.a & 0xf) as i16 - (byte & 0xf) as i16 - borrow;
+                let mut result: i16;
 
-                let mut lo_nibble: u8 = (self.a & 0xf) + (c_byte & 0xf) + (self.carry_flag() as u8); 
-                
-                if lo_nibble > 9
+                if self.variant == Variant::Nmos
+                {
+                    if lo_nibble < 0 { lo_nibble = ((lo_nibble - 6) & 0xf) - 0x10 }
+                    result = (self.a & 0xf0) as i16 - (byte & 0xf0) as i16 + lo_nibble;
+                    if result < 0 { result -= 0x60 }
+                }
+                else
                 {
-                    lo_adjust = 6;
-                    half_carry = true;
+                    result = self.a as i16 - byte as i16 - borrow;
+                    if result < 0 { result -= 0x60 }
+                    if lo_nibble < 0 { result -= 6 }
                 }
 
-                let mut hi_nibble: u8 = ( (self.a >> 4) & 0xf ) + ( (c_byte>> 4) & 0xf ) + (half_carry as u8); 
+                self.a = result as u8;
 
-                self.set_carry(hi_nibble > 9);
-                if self.carry_flag()
+                if self.variant != Variant::Nmos
                 {
-                    hi_adjust = 6;
+                    self.set_negative(self.a > 0x7f);
+                    self.set_zero(self.a == 0);
+                    self.cycles_used += 1;
                 }
+            }
+            false => self.a = binary as u8
+        }
+    }
 
-                //ALU result without decimal adjustments
-                lo_nibble &= 0xf;
-                hi_nibble &= 0xf;
-                let alu_result: u8 = (hi_nibble << 4) + lo_nibble;
 
-                self.set_zero(alu_result == 0);
-                self.set_negative(alu_result > 0x7f);
-                self.set_overflow((c_byte & 0x80 == self.a & 0x80) && (alu_result & 0x80 != c_byte & 0x80));
+    fn sbx(&mut self, memory: &mut dyn Bus, i_addr: u16) //X = (A AND X) - operand, setting the flags like CMP and ignoring decimal mode
+    {
+        let byte: u8 = bus::read(memory, self, i_addr);
+        let value: u8 = self.a & self.x;
 
-                //Final A result with adjustment
-                lo_nibble = (lo_nibble + lo_adjust) & 0xf;
-                hi_nibble = (hi_nibble + hi_adjust) & 0xf;
-                result = u16::from((hi_nibble << 4) + lo_nibble);
-            }
-            false => //Normal binary
-            {
-                result = self.a as u16 + c_byte as u16 + self.carry_flag() as u16; // A + Byte + Carry
+        self.compare(value, byte);
+        self.x = value.wrapping_sub(byte);
+    }
 
-                self.set_carry(result > 0xff);
 
-                if self.carry_flag()
-                {
-                    result &= 0xff;
-                }
+    fn sta(&mut self, memory: &mut dyn Bus, i_addr: u16) 
+    {
+        bus::write(memory, self, i_addr, self.a);
+    }
 
-                self.set_overflow((c_byte & 0x80 == self.a & 0x80) && (result as u8 & 0x80 != c_byte & 0x80));
-                self.set_zero(result == 0);
-                self.set_negative(result > 0x7f);
-            }
-        }
 
-        self.a = result as u8;
+    fn stx(&mut self, memory: &mut dyn Bus, i_addr: u16) 
+    {
+        bus::write(memory, self, i_addr, self.x);
+    }
 
-        self.cycles_used += cycles
+
+    fn sty(&mut self, memory: &mut dyn Bus, i_addr: u16) 
+    {
+        bus::write(memory, self, i_addr, self.y);
     }
 
 
-    fn sta(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn trb(&mut self, memory: &mut dyn Bus, i_addr: u16)
     {
-        bus::write(memory, i_addr, self.a);
+        let byte: u8 = bus::read(memory, self, i_addr);
+        self.modify_dummy(memory, i_addr, byte);
+
+        self.set_zero(byte & self.a == 0);
 
-        self.cycles_used += cycles
+        bus::write(memory, self, i_addr, byte & !self.a);
     }
 
 
-    fn stx(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn tsb(&mut self, memory: &mut dyn Bus, i_addr: u16)
     {
-        bus::write(memory, i_addr, self.x);
+        let byte: u8 = bus::read(memory, self, i_addr);
+        self.modify_dummy(memory, i_addr, byte);
 
-        self.cycles_used += cycles
+        self.set_zero(byte & self.a == 0);
+
+        bus::write(memory, self, i_addr, byte | self.a);
+    }
+
+
+    fn set_bit(&mut self, memory: &mut dyn Bus, i_addr: u16, bit: u8, value: bool) //RMB and SMB
+    {
+        let mut byte: u8 = bus::read(memory, self, i_addr);
+        self.modify_dummy(memory, i_addr, byte);
+
+        if value { byte |= 1 << bit } else { byte &= !(1 << bit) }
+
+        bus::write(memory, self, i_addr, byte);
     }
 
 
-    fn sty(&mut self, memory: &mut [Segment], cycles: u8, i_addr: u16) 
+    fn bit_branch(&mut self, memory: &mut dyn Bus, i_addr: u16, bit: u8, value: bool) //BBR and BBS: test a zero page bit, then branch on it
     {
-        bus::write(memory, i_addr, self.y);
+        let byte: u8 = bus::read(memory, self, i_addr);
+        bus::dummy_read(memory, self, i_addr);
+        let offset: u8 = bus::read(memory, self, self.pc);
+        self.pc = self.pc.wrapping_add(1);
 
-        self.cycles_used += cycles
+        let target: u16 = self.pc.wrapping_add(offset as i8 as u16);
+        self.branch(memory, (byte >> bit) & 1 == value as u8, target);
+    }
+
+
+    fn nop(&mut self, memory: &mut dyn Bus, i_addr: Option<u16>, cycles: u8)
+    {
+        if let Some(v) = i_addr
+        {
+            bus::read(memory, self, v);
+
+            for _ in 0..cycles.saturating_sub(4) { bus::dummy_read(memory, self, v) }  //the 65C02's eight cycle NOP ($5C) keeps reading for four more
+        }
+    }
+
+
+    fn modify_dummy(&mut self, memory: &mut dyn Bus, i_addr: u16, byte: u8) //the spare cycle of a read-modify-write instruction
+    {
+        if self.variant == Variant::Nmos
+        {
+            bus::dummy_write(memory, self, i_addr, byte);   //the NMOS part writes the unmodified value back
+        }
+        else
+        {
+            bus::dummy_read(memory, self, i_addr);          //the 65C02 reads it again instead
+        }
+    }
+
+
+    fn decimal_dummy(&mut self, memory: &mut dyn Bus, i_addr: u16) //the extra cycle the 65C02 takes to fix up N and Z in decimal mode
+    {
+        if self.variant != Variant::Nmos && self.decimal_flag()
+        {
+            bus::dummy_read(memory, self, i_addr);
+        }
+    }
+
+
+    fn stack_dummy(&mut self, memory: &mut dyn Bus) //pulls spend a cycle reading the stack before the pointer moves
+    {
+        bus::dummy_read(memory, self, 0x100 | self.sp as u16);
     }
 
 
@@ -989,8 +1529,6 @@ impl CpuStatus
             _ => panic!("Invalid destination argument to self.transfer \n")
         };
 
-        self.cycles_used += 2;
-
         if destination != 's'
         {
             self.set_negative(val > 0x7f);
@@ -999,6 +1537,23 @@ impl CpuStatus
     }
     
 
+    fn unstable_store(&mut self, memory: &mut dyn Bus, i_addr: u16, index: u8, value: u8)
+    //SHA, SHX, SHY and TAS store the value ANDed with the high byte of the base address plus one.
+    //When the index crosses a page, that same value replaces the high byte of the address written to.
+    {
+        let base: u16 = i_addr.wrapping_sub(index as u16);
+        let byte: u8 = value & ((base >> 8) as u8).wrapping_add(1);
+        let mut target: u16 = i_addr;
+
+        if base & 0xff00 != i_addr & 0xff00
+        {
+            target = ((byte as u16) << 8) | (i_addr & 0xff);
+        }
+
+        bus::write(memory, self, target, byte);
+    }
+
+
     pub fn carry_flag(&mut self) -> bool
     {
         return 0 != (self.sr & 0b1)
@@ -1076,16 +1631,65 @@ impl CpuStatus
     }
 
 
-    pub fn irq(&mut self)
+    pub fn irq(&mut self) //a one-shot interrupt request, as from the monitor. It waits until I is clear instead of getting lost.
     {
-        if !self.interrupt_flag()
-        {
-            self.external_irq = true;
-        }
+        self.irq_request = true;
+    }
+
+    pub fn nmi(&mut self) //a single NMI edge
+    {
+        self.nmi_pending = true;
+    }
+
+
+    pub fn set_irq(&mut self, source: usize, asserted: bool) //drive one of the inputs wired-ORed onto the level-triggered IRQ line
+    {
+        CpuStatus::drive(&mut self.irq_sources, source, asserted);
+    }
+
+    pub fn set_nmi(&mut self, source: usize, asserted: bool) //the NMI line is wired-OR too, but the CPU only sees it go from high to low
+    {
+        let before: bool = self.nmi_sources != 0;
+        CpuStatus::drive(&mut self.nmi_sources, source, asserted);
+
+        if !before && self.nmi_sources != 0 { self.nmi_pending = true }
+    }
+
+    pub fn irq_line(&self) -> bool //true while something is holding IRQ low
+    {
+        return self.irq_sources != 0 || self.irq_request
+    }
+
+    pub fn set_rdy(&mut self, source: usize, asserted: bool) //hold RDY low to stall the CPU, as DMA does
+    //In cycle accurate mode the CPU stops on the next read cycle, or any cycle on the 65C02, since the NMOS part ignores
+    //RDY on writes. Otherwise RDY is only sampled between instructions, and the CPU stalls there a cycle at a time.
+    {
+        CpuStatus::drive(&mut self.rdy_sources, source, asserted);
+    }
+
+    pub fn rdy_held(&self) -> bool //true while something is holding RDY low
+    {
+        return self.rdy_sources != 0
+    }
+
+    pub fn set_so(&mut self, source: usize, asserted: bool) //the Set Overflow pin: V is set each time the line goes from high to low
+    {
+        let before: bool = self.so_sources != 0;
+        CpuStatus::drive(&mut self.so_sources, source, asserted);
+
+        if !before && self.so_sources != 0 { self.set_overflow(true) }
+    }
+
+    fn drive(sources: &mut u32, source: usize, asserted: bool) //one source's bit in a wired-OR input, nothing for an id past INPUT_SOURCES
+    {
+        if source >= INPUT_SOURCES { return }
+
+        if asserted { *sources |= 1 << source } else { *sources &= !(1 << source) }
     }
 
-    pub fn nmi(&mut self)
+    pub fn set_reset(&mut self, asserted: bool) //hold RESET low to keep the CPU idle, it runs the reset sequence once released
     {
-        self.external_nmi = true;
+        if !asserted && self.reset_line { self.reset = true }
+        self.reset_line = asserted;
     }
 }
@@ -0,0 +1,276 @@
+use crate::devices::Device;
+
+use std::fs::File;
+use std::io::{self, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const SAMPLE_RATE: u32 = 44100;                 //for recordings
+const MAX_GAP_SAMPLES: u64 = 2 * SAMPLE_RATE as u64; //long pauses between saves are cut down to 2 seconds of steady level
+
+
+pub struct Aci //Apple Cassette Interface. Attach at 0xc000-0xc1ff: C000-C0FF is the tape I/O page, C100-C1FF the ROM.
+{
+    rom: [u8; 0x100],
+    clock_hz: u64,              //CPU clock, to turn cycles into tape time
+    cycle: u64,                 //cycles since power on
+
+    out_level: bool,            //the tape output flip-flop
+    recording: Option<WavRecorder>,
+
+    tape: Option<Tape>,
+}
+
+impl Aci
+{
+    pub fn new(rom_data: &[u8], clock_hz: u64) -> Aci
+    {
+        let mut rom: [u8; 0x100] = [0; 0x100];
+        let length = rom_data.len().min(rom.len());
+        rom[..length].copy_from_slice(&rom_data[..length]);
+
+        Aci { rom, clock_hz, cycle: 0, out_level: false, recording: None, tape: None }
+    }
+
+    pub fn insert_tape(&mut self, path: &Path) -> io::Result<()> //load a WAV file to play into tape in. It starts playing on the first read.
+    {
+        self.tape = Some(Tape::load(path)?);
+        Ok(())
+    }
+
+    pub fn eject_tape(&mut self)
+    {
+        self.tape = None;
+    }
+
+    pub fn record_to(&mut self, path: &Path) -> io::Result<()> //write everything sent to tape out into a WAV file
+    {
+        self.recording = Some(WavRecorder::create(path)?);
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) -> io::Result<()>
+    {
+        match self.recording.take()
+        {
+            Some(mut recorder) => recorder.finish(),
+            None => Ok(())
+        }
+    }
+
+    fn toggle_output(&mut self) //any access to C000-C07F flips the output
+    {
+        if let Some(recorder) = self.recording.as_mut()
+        {
+            let until: u64 = self.cycle * SAMPLE_RATE as u64 / self.clock_hz;
+            let _ = recorder.level_until(self.out_level, until);
+        }
+
+        self.out_level = !self.out_level;
+    }
+
+    fn tape_in(&mut self) -> bool
+    {
+        let cycle: u64 = self.cycle;
+        let clock_hz: u64 = self.clock_hz;
+
+        match self.tape.as_mut()
+        {
+            Some(tape) => tape.level_at(cycle, clock_hz),
+            None => false
+        }
+    }
+}
+
+impl Device for Aci
+{
+    fn read(&mut self, reg: u16) -> u8
+    {
+        let offset: usize = (reg & 0xff) as usize;
+
+        if reg & 0x100 == 0
+        {
+            if reg & 0x80 == 0
+            {
+                self.toggle_output();
+            }
+            else                                //C080-C0FF: the ROM, with A0 replaced by the tape input level
+            {
+                let level: bool = self.tape_in();
+                return self.rom[(offset & 0xfe) | level as usize];
+            }
+        }
+
+        return self.rom[offset]
+    }
+
+    fn write(&mut self, reg: u16, _data: u8)
+    {
+        if reg & 0x180 == 0 { self.toggle_output() }
+    }
+
+    fn peek(&self, reg: u16) -> u8
+    {
+        return self.rom[(reg & 0xff) as usize]
+    }
+
+    fn tick(&mut self)
+    {
+        self.cycle += 1;
+    }
+}
+
+
+struct Tape //a WAV file, squared up through the comparator on the ACI's input
+{
+    levels: Vec<bool>,
+    sample_rate: u64,
+    start_cycle: Option<u64>,
+}
+
+impl Tape
+{
+    fn load(path: &Path) -> io::Result<Tape>
+    {
+        let mut bytes: Vec<u8> = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+
+        let bad = |what: &str| io::Error::new(ErrorKind::InvalidData, format!("{}: {}", path.display(), what));
+
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" { return Err(bad("not a WAV file")) }
+
+        let mut channels: usize = 0;
+        let mut bits: usize = 0;
+        let mut sample_rate: u64 = 0;
+        let mut data: &[u8] = &[];
+
+        let mut pos: usize = 12;
+        while pos + 8 <= bytes.len()    //walk the RIFF chunks
+        {
+            let id = &bytes[pos..pos + 4];
+            let size = u32::from_le_bytes([bytes[pos + 4], bytes[pos + 5], bytes[pos + 6], bytes[pos + 7]]) as usize;
+            let body = &bytes[pos + 8..(pos + 8 + size).min(bytes.len())];
+
+            if id == b"fmt " && body.len() >= 16
+            {
+                if u16::from_le_bytes([body[0], body[1]]) != 1 { return Err(bad("only PCM WAV files are supported")) }
+                channels = u16::from_le_bytes([body[2], body[3]]) as usize;
+                sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]) as u64;
+                bits = u16::from_le_bytes([body[14], body[15]]) as usize;
+            }
+            else if id == b"data"
+            {
+                data = body;
+            }
+
+            pos += 8 + size + (size & 1);
+        }
+
+        if channels == 0 || sample_rate == 0 || (bits != 8 && bits != 16) { return Err(bad("needs 8 or 16 bit PCM audio")) }
+
+        let frame: usize = channels * bits / 8;
+        let mut levels: Vec<bool> = Vec::with_capacity(data.len() / frame);
+        let mut level: bool = false;
+
+        for sample in data.chunks_exact(frame)   //first channel only, with a little hysteresis around zero
+        {
+            let value: i32 = if bits == 8 { (sample[0] as i32 - 128) << 8 } else { i16::from_le_bytes([sample[0], sample[1]]) as i32 };
+
+            if value > 512 { level = true } else if value < -512 { level = false }
+            levels.push(level);
+        }
+
+        Ok(Tape { levels, sample_rate, start_cycle: None })
+    }
+
+    fn level_at(&mut self, cycle: u64, clock_hz: u64) -> bool
+    {
+        let start: u64 = *self.start_cycle.get_or_insert(cycle);
+        let index: usize = ((cycle - start) * self.sample_rate / clock_hz) as usize;
+
+        match self.levels.get(index)
+        {
+            Some(level) => *level,
+            None => self.levels.last().copied().unwrap_or(false)     //end of the tape
+        }
+    }
+}
+
+
+struct WavRecorder //8 bit mono PCM, the header is filled in as the recording grows
+{
+    file: BufWriter<File>,
+    samples: u64,                   //samples written so far
+    first_sample: u64,              //sample clock when the recording started
+    unpatched: u64,                 //samples written since the header sizes were last brought up to date
+}
+
+impl WavRecorder
+{
+    fn create(path: &Path) -> io::Result<WavRecorder>
+    {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        file.write_all(b"RIFF")?;
+        file.write_all(&36u32.to_le_bytes())?;
+        file.write_all(b"WAVEfmt ")?;
+        file.write_all(&16u32.to_le_bytes())?;
+        file.write_all(&1u16.to_le_bytes())?;              //PCM
+        file.write_all(&1u16.to_le_bytes())?;              //mono
+        file.write_all(&SAMPLE_RATE.to_le_bytes())?;
+        file.write_all(&SAMPLE_RATE.to_le_bytes())?;       //bytes per second
+        file.write_all(&1u16.to_le_bytes())?;              //bytes per frame
+        file.write_all(&8u16.to_le_bytes())?;              //bits per sample
+        file.write_all(b"data")?;
+        file.write_all(&0u32.to_le_bytes())?;
+
+        Ok(WavRecorder { file, samples: 0, first_sample: u64::MAX, unpatched: 0 })
+    }
+
+    fn level_until(&mut self, level: bool, sample_clock: u64) -> io::Result<()> //hold the given level up to the sample clock time
+    {
+        if self.first_sample == u64::MAX { self.first_sample = sample_clock }  //the recording starts with the first transition
+
+        let target: u64 = sample_clock - self.first_sample;
+        let mut count: u64 = target.saturating_sub(self.samples);
+        if count > MAX_GAP_SAMPLES
+        {
+            self.first_sample += count - MAX_GAP_SAMPLES;
+            count = MAX_GAP_SAMPLES;
+        }
+
+        let value: u8 = if level { 0xe0 } else { 0x20 };
+        for _ in 0..count { self.file.write_all(&[value])? }
+
+        self.samples += count;
+        self.unpatched += count;
+
+        if self.unpatched >= SAMPLE_RATE as u64 { self.patch_header()? }
+        Ok(())
+    }
+
+    fn patch_header(&mut self) -> io::Result<()>
+    {
+        self.unpatched = 0;
+
+        let data_size: u32 = self.samples as u32;
+        self.file.seek(SeekFrom::Start(4))?;
+        self.file.write_all(&(36 + data_size).to_le_bytes())?;
+        self.file.seek(SeekFrom::Start(40))?;
+        self.file.write_all(&data_size.to_le_bytes())?;
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.flush()
+    }
+
+    fn finish(&mut self) -> io::Result<()>
+    {
+        self.patch_header()
+    }
+}
+
+impl Drop for WavRecorder
+{
+    fn drop(&mut self)
+    {
+        let _ = self.patch_header();
+    }
+}
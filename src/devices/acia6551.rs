@@ -0,0 +1,177 @@
+use crate::devices::Device;
+use crate::devices::serial::SerialBackend;
+
+const DATA: u16 = 0;            //register select lines RS1 and RS0
+const STATUS: u16 = 1;          //writing here is a programmed reset
+const COMMAND: u16 = 2;
+const CONTROL: u16 = 3;
+
+const ST_OVERRUN: u8 = 0b00000100;
+const ST_RDRF: u8 = 0b00001000;  //receiver data register full
+const ST_TDRE: u8 = 0b00010000;  //transmitter data register empty
+const ST_IRQ: u8 = 0b10000000;
+
+const CMD_DTR: u8 = 0b00000001;         //0 disables the receiver and all interrupts
+const CMD_RX_IRQ_OFF: u8 = 0b00000010;
+const CMD_TIC: u8 = 0b00001100;         //transmitter control: 01 enables the TX interrupt, 00 turns the transmitter off
+const CMD_ECHO: u8 = 0b00010000;
+const CMD_PARITY: u8 = 0b00100000;
+
+const BAUD_RATES: [u32; 16] = [115200, 50, 75, 110, 135, 150, 300, 600, 1200, 1800, 2400, 3600, 4800, 7200, 9600, 19200]; //0 is the 16x external clock, assumed to be 1.8432 MHz
+
+
+pub struct Acia6551 //MOS 6551 Asynchronous Communications Interface Adapter
+{
+    pub status: u8,
+    pub command: u8,
+    pub control: u8,
+    rx_data: u8,
+    tx_data: u8,
+    tx_shifting: u32,           //cycles until the transmit shift register is free
+    rx_timer: u32,              //cycles until the receiver can take the next character
+    clock_hz: u64,              //CPU clock, used to turn the baud rate into cycles per character
+    host: Box<dyn SerialBackend>,
+}
+
+impl Acia6551
+{
+    pub fn new(host: Box<dyn SerialBackend>, clock_hz: u64) -> Acia6551
+    {
+        Acia6551 { status: ST_TDRE, command: CMD_RX_IRQ_OFF, control: 0, rx_data: 0, tx_data: 0, tx_shifting: 0, rx_timer: 0, clock_hz, host }
+    }
+
+    pub fn reset(&mut self) //hardware reset
+    {
+        self.status = ST_TDRE;
+        self.command = CMD_RX_IRQ_OFF;
+        self.control = 0;
+        self.tx_shifting = 0;
+    }
+
+    fn word_mask(&self) -> u8
+    {
+        return 0xff >> ((self.control >> 5) & 3)
+    }
+
+    pub fn character_cycles(&self) -> u32 //CPU cycles it takes to send one character at the programmed baud rate and framing
+    {
+        let data_bits: u32 = 8 - ((self.control as u32 >> 5) & 3);
+        let parity_bits: u32 = (self.command & CMD_PARITY != 0) as u32;
+        let stop_bits: u32 = if self.control & 0x80 != 0 { 2 } else { 1 };
+        let baud: u64 = BAUD_RATES[(self.control & 0xf) as usize] as u64;
+
+        return ((self.clock_hz * (1 + data_bits + parity_bits + stop_bits) as u64) / baud).max(1) as u32
+    }
+
+    fn tx_irq_enabled(&self) -> bool
+    {
+        return self.command & CMD_TIC == 0b0100 && self.command & CMD_DTR != 0
+    }
+
+    fn rx_irq_enabled(&self) -> bool
+    {
+        return self.command & CMD_RX_IRQ_OFF == 0 && self.command & CMD_DTR != 0
+    }
+
+    fn receive(&mut self)
+    {
+        if let Some(byte) = self.host.receive()
+        {
+            if self.status & ST_RDRF != 0
+            {
+                self.status |= ST_OVERRUN;      //the CPU didn't read the last one in time, this one is lost
+            }
+            else
+            {
+                self.rx_data = byte & self.word_mask();
+                self.status |= ST_RDRF;
+            }
+
+            if self.command & (CMD_ECHO | CMD_TIC) == CMD_ECHO { self.host.transmit(byte) }
+
+            if self.rx_irq_enabled() { self.status |= ST_IRQ }
+        }
+    }
+}
+
+impl Device for Acia6551
+{
+    fn read(&mut self, reg: u16) -> u8
+    {
+        let value: u8 = self.peek(reg);
+
+        match reg & 3
+        {
+            DATA => self.status &= !(ST_RDRF | ST_OVERRUN),
+            STATUS => self.status &= !ST_IRQ,           //reading the status acknowledges the interrupt
+            _ => ()
+        }
+
+        return value
+    }
+
+    fn write(&mut self, reg: u16, data: u8)
+    {
+        match reg & 3
+        {
+            DATA =>
+            {
+                self.tx_data = data & self.word_mask();
+                self.status &= !ST_TDRE;
+            },
+            STATUS =>                                   //programmed reset
+            {
+                self.command &= 0b11100000;
+                self.status &= !ST_OVERRUN;
+            },
+            COMMAND => self.command = data,
+            CONTROL => self.control = data,
+            _ => ()
+        }
+    }
+
+    fn peek(&self, reg: u16) -> u8
+    {
+        match reg & 3
+        {
+            DATA => self.rx_data,
+            STATUS => self.status,                      //DCD and DSR read as 0, a connected modem
+            COMMAND => self.command,
+            CONTROL => self.control,
+            _ => 0
+        }
+    }
+
+    fn tick(&mut self)
+    {
+        self.host.tick();
+
+        if self.tx_shifting > 0
+        {
+            self.tx_shifting -= 1;
+        }
+        else if self.status & ST_TDRE == 0 && self.command & CMD_TIC != 0   //move the next byte into the shift register
+        {
+            self.host.transmit(self.tx_data);
+            self.tx_shifting = self.character_cycles();
+            self.status |= ST_TDRE;
+
+            if self.tx_irq_enabled() { self.status |= ST_IRQ }
+        }
+
+        if self.rx_timer > 0
+        {
+            self.rx_timer -= 1;
+        }
+        else if self.command & CMD_DTR != 0            //only poll the host once per character time
+        {
+            self.rx_timer = self.character_cycles();
+            self.receive();
+        }
+    }
+
+    fn irq(&self) -> bool
+    {
+        return self.status & ST_IRQ != 0
+    }
+}
@@ -0,0 +1,168 @@
+use crate::devices::Device;
+use crate::devices::serial::SerialBackend;
+
+const CONTROL: u16 = 0;         //register select line RS. Writes go to control, reads come from status
+const DATA: u16 = 1;
+
+const ST_RDRF: u8 = 0b00000001;  //receiver data register full
+const ST_TDRE: u8 = 0b00000010;  //transmitter data register empty
+const ST_OVRN: u8 = 0b00100000;
+const ST_IRQ: u8 = 0b10000000;
+
+const CR_DIVIDE: u8 = 0b00000011;        //11 is master reset
+const CR_WORD: u8 = 0b00011100;
+const CR_TX_CONTROL: u8 = 0b01100000;    //01 enables the transmit interrupt, 10 raises RTS
+const CR_RX_IRQ: u8 = 0b10000000;
+
+const WORD_BITS: [u32; 8] = [10, 10, 9, 9, 10, 9, 10, 10]; //data + parity + stop bits for each CR4-2 word select
+
+
+pub struct Acia6850 //Motorola 6850 Asynchronous Communications Interface Adapter
+{
+    pub status: u8,
+    pub control: u8,
+    rx_data: u8,
+    tx_data: u8,
+    tx_shifting: u32,           //cycles until the transmit shift register is free
+    rx_timer: u32,              //cycles until the receiver can take the next character
+    clock_hz: u64,              //CPU clock, used to turn the baud rate into cycles per character
+    serial_clock_hz: u64,       //the TX/RX clock input, divided by 1, 16 or 64 to get the baud rate
+    host: Box<dyn SerialBackend>,
+}
+
+impl Acia6850
+{
+    pub fn new(host: Box<dyn SerialBackend>, clock_hz: u64, serial_clock_hz: u64) -> Acia6850
+    {
+        let mut acia = Acia6850 { status: 0, control: CR_DIVIDE, rx_data: 0, tx_data: 0, tx_shifting: 0, rx_timer: 0, clock_hz, serial_clock_hz, host };
+        acia.master_reset();
+        return acia
+    }
+
+    pub fn master_reset(&mut self)
+    {
+        self.status = ST_TDRE;
+        self.tx_shifting = 0;
+    }
+
+    fn in_reset(&self) -> bool
+    {
+        return self.control & CR_DIVIDE == CR_DIVIDE
+    }
+
+    pub fn baud_rate(&self) -> u64
+    {
+        let divide: u64 = match self.control & CR_DIVIDE { 0 => 1, 1 => 16, _ => 64 };
+        return self.serial_clock_hz / divide
+    }
+
+    pub fn character_cycles(&self) -> u32 //CPU cycles it takes to send one character at the current divide ratio and word format
+    {
+        let bits: u64 = 1 + WORD_BITS[((self.control & CR_WORD) >> 2) as usize] as u64;
+        return ((self.clock_hz * bits) / self.baud_rate().max(1)).max(1) as u32
+    }
+
+    fn word_mask(&self) -> u8 //CR4 clear selects 7 bit words
+    {
+        if self.control & 0b00010000 == 0 { 0x7f } else { 0xff }
+    }
+
+    fn update_irq(&mut self)
+    {
+        let rx_irq: bool = self.control & CR_RX_IRQ != 0 && self.status & (ST_RDRF | ST_OVRN) != 0;
+        let tx_irq: bool = self.control & CR_TX_CONTROL == 0b00100000 && self.status & ST_TDRE != 0;
+
+        if rx_irq || tx_irq { self.status |= ST_IRQ } else { self.status &= !ST_IRQ }
+    }
+}
+
+impl Device for Acia6850
+{
+    fn read(&mut self, reg: u16) -> u8
+    {
+        let value: u8 = self.peek(reg);
+
+        if reg & 1 == DATA                      //reading the data clears the receiver flags and its interrupt
+        {
+            self.status &= !(ST_RDRF | ST_OVRN);
+            self.update_irq();
+        }
+
+        return value
+    }
+
+    fn write(&mut self, reg: u16, data: u8)
+    {
+        match reg & 1
+        {
+            CONTROL =>
+            {
+                self.control = data;
+                if self.in_reset() { self.master_reset() }
+            },
+            _ =>
+            {
+                self.tx_data = data & self.word_mask();
+                self.status &= !ST_TDRE;
+            }
+        }
+
+        self.update_irq();
+    }
+
+    fn peek(&self, reg: u16) -> u8
+    {
+        match reg & 1
+        {
+            CONTROL => self.status,             //DCD and CTS read as 0, carrier present and clear to send
+            _ => self.rx_data
+        }
+    }
+
+    fn tick(&mut self)
+    {
+        self.host.tick();
+        if self.in_reset() { return }
+
+        if self.tx_shifting > 0
+        {
+            self.tx_shifting -= 1;
+        }
+        else if self.status & ST_TDRE == 0      //move the next byte into the shift register
+        {
+            self.host.transmit(self.tx_data);
+            self.tx_shifting = self.character_cycles();
+            self.status |= ST_TDRE;
+            self.update_irq();
+        }
+
+        if self.rx_timer > 0
+        {
+            self.rx_timer -= 1;
+        }
+        else                                    //only poll the host once per character time
+        {
+            self.rx_timer = self.character_cycles();
+
+            if let Some(byte) = self.host.receive()
+            {
+                if self.status & ST_RDRF != 0
+                {
+                    self.status |= ST_OVRN;     //the CPU didn't read the last one in time, this one is lost
+                }
+                else
+                {
+                    self.rx_data = byte & self.word_mask();
+                    self.status |= ST_RDRF;
+                }
+
+                self.update_irq();
+            }
+        }
+    }
+
+    fn irq(&self) -> bool
+    {
+        return self.status & ST_IRQ != 0
+    }
+}
@@ -0,0 +1,310 @@
+use crate::devices::Device;
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const ROM_SIZE: usize = 0x2000;         //firmware at 9000-AFFF
+const IO_OFFSET: u16 = 0x1ff0;          //the task file is overlaid on the top 16 bytes of the ROM, AFF0-AFFF
+const SECTOR: usize = 512;
+
+const DATA_HIGH: u16 = 0x0;             //latch for the upper half of the 16 bit data register
+const SET_CS_MASK: u16 = 0x1;           //the CS mask keeps 65C02 false reads off the bus, there's nothing to mask here
+const CLEAR_CS_MASK: u16 = 0x2;
+const DEVICE_CONTROL: u16 = 0x6;        //alternate status when read
+const DATA: u16 = 0x8;
+const ERROR: u16 = 0x9;                 //features when written
+const SECTOR_COUNT: u16 = 0xa;
+const LBA_0: u16 = 0xb;                 //sector number
+const LBA_8: u16 = 0xc;                 //cylinder low
+const LBA_16: u16 = 0xd;                //cylinder high
+const HEAD: u16 = 0xe;                  //drive/head, LBA bits 24-27
+const COMMAND: u16 = 0xf;               //status when read
+
+const ST_BSY: u8 = 0x80;
+const ST_DRDY: u8 = 0x40;
+const ST_DSC: u8 = 0x10;
+const ST_DRQ: u8 = 0x08;
+const ST_ERR: u8 = 0x01;
+
+const ERR_ABRT: u8 = 0x04;
+const ERR_IDNF: u8 = 0x10;
+
+const CMD_READ_SECTORS: u8 = 0x20;
+const CMD_READ_SECTORS_NR: u8 = 0x21;
+const CMD_WRITE_SECTORS: u8 = 0x30;
+const CMD_WRITE_SECTORS_NR: u8 = 0x31;
+const CMD_INIT_PARAMETERS: u8 = 0x91;
+const CMD_IDENTIFY: u8 = 0xec;
+const CMD_SET_FEATURES: u8 = 0xef;
+
+const HEADS: u64 = 16;                  //geometry reported to CHS software
+const SECTORS_PER_TRACK: u64 = 63;
+
+
+#[derive(PartialEq)]
+enum Transfer { Idle, Read, Write }
+
+
+pub struct Cffa1 //CFFA1 CompactFlash card for the Apple I, attach at 0x9000-0xafff. The media is a raw image file.
+{
+    rom: Vec<u8>,
+    image: File,
+    total_sectors: u64,
+
+    pub status: u8,
+    pub error: u8,
+    pub sector_count: u8,
+    pub lba: [u8; 3],
+    pub head: u8,
+    data_high: u8,
+
+    buffer: [u8; SECTOR],
+    buffer_pos: usize,
+    transfer: Transfer,
+    sectors_left: u32,
+    current_lba: u64,
+}
+
+impl Cffa1
+{
+    pub fn new(rom_data: &[u8], image_path: &Path) -> io::Result<Cffa1>
+    {
+        let mut rom: Vec<u8> = vec![0; ROM_SIZE];
+        let length = rom_data.len().min(ROM_SIZE);
+        rom[..length].copy_from_slice(&rom_data[..length]);
+
+        let image = OpenOptions::new().read(true).write(true).open(image_path)?;
+        let total_sectors: u64 = image.metadata()?.len() / SECTOR as u64;
+
+        Ok(Cffa1 {
+            rom, image, total_sectors,
+            status: ST_DRDY | ST_DSC, error: 0, sector_count: 1, lba: [1, 0, 0], head: 0xa0, data_high: 0,
+            buffer: [0; SECTOR], buffer_pos: 0, transfer: Transfer::Idle, sectors_left: 0, current_lba: 0
+        })
+    }
+
+    fn target_lba(&self) -> u64
+    {
+        if self.head & 0x40 != 0        //LBA addressing
+        {
+            return ((self.head as u64 & 0xf) << 24) | ((self.lba[2] as u64) << 16) | ((self.lba[1] as u64) << 8) | self.lba[0] as u64
+        }
+
+        let cylinder: u64 = ((self.lba[2] as u64) << 8) | self.lba[1] as u64;
+        return (cylinder * HEADS + (self.head as u64 & 0xf)) * SECTORS_PER_TRACK + (self.lba[0] as u64).saturating_sub(1)
+    }
+
+    fn fail(&mut self, error: u8)
+    {
+        self.error = error;
+        self.status = ST_DRDY | ST_DSC | ST_ERR;
+        self.transfer = Transfer::Idle;
+    }
+
+    fn load_sector(&mut self) -> io::Result<()>
+    {
+        self.image.seek(SeekFrom::Start(self.current_lba * SECTOR as u64))?;
+        self.image.read_exact(&mut self.buffer)
+    }
+
+    fn store_sector(&mut self) -> io::Result<()>
+    {
+        self.image.seek(SeekFrom::Start(self.current_lba * SECTOR as u64))?;
+        self.image.write_all(&self.buffer)?;
+        self.image.flush()
+    }
+
+    fn start_read(&mut self)
+    {
+        if self.current_lba >= self.total_sectors { return self.fail(ERR_IDNF | ERR_ABRT) }
+
+        match self.load_sector()
+        {
+            Ok(()) =>
+            {
+                self.buffer_pos = 0;
+                self.transfer = Transfer::Read;
+                self.status = ST_DRDY | ST_DSC | ST_DRQ;
+            },
+            Err(_) => self.fail(ERR_ABRT)
+        }
+    }
+
+    fn execute_command(&mut self, command: u8)
+    {
+        self.error = 0;
+        self.current_lba = self.target_lba();
+        self.sectors_left = if self.sector_count == 0 { 256 } else { self.sector_count as u32 };
+
+        if self.head & 0x10 != 0 { return self.fail(ERR_ABRT) }    //there's no slave device on the card
+
+        match command
+        {
+            CMD_READ_SECTORS | CMD_READ_SECTORS_NR => self.start_read(),
+            CMD_WRITE_SECTORS | CMD_WRITE_SECTORS_NR =>
+            {
+                if self.current_lba >= self.total_sectors { return self.fail(ERR_IDNF | ERR_ABRT) }
+
+                self.buffer_pos = 0;
+                self.transfer = Transfer::Write;
+                self.status = ST_DRDY | ST_DSC | ST_DRQ;
+            },
+            CMD_IDENTIFY =>
+            {
+                self.buffer = self.identify();
+                self.buffer_pos = 0;
+                self.sectors_left = 1;
+                self.transfer = Transfer::Read;
+                self.current_lba = u64::MAX;
+                self.status = ST_DRDY | ST_DSC | ST_DRQ;
+            },
+            CMD_SET_FEATURES | CMD_INIT_PARAMETERS => self.status = ST_DRDY | ST_DSC,
+            _ => self.fail(ERR_ABRT)
+        }
+    }
+
+    fn identify(&self) -> [u8; SECTOR] //IDENTIFY DEVICE data for a CompactFlash card the size of the image
+    {
+        let mut words: [u16; 256] = [0; 256];
+        let cylinders: u64 = (self.total_sectors / (HEADS * SECTORS_PER_TRACK)).min(0xffff);
+
+        words[0] = 0x848a;                                  //CompactFlash signature
+        words[1] = cylinders as u16;
+        words[3] = HEADS as u16;
+        words[6] = SECTORS_PER_TRACK as u16;
+        words[49] = 0x0200;                                 //LBA supported
+        words[60] = self.total_sectors as u16;
+        words[61] = (self.total_sectors >> 16) as u16;
+
+        let strings: [(usize, usize, &str); 3] = [(10, 20, "RUST65"), (23, 8, "0.5"), (27, 40, "RUST65 CFFA1 DISK IMAGE")];
+        for (start, length, text) in strings     //ATA strings are space padded, two characters per word, big endian
+        {
+            let padded: Vec<u8> = text.bytes().chain(std::iter::repeat(b' ')).take(length).collect();
+            for (i, pair) in padded.chunks(2).enumerate()
+            {
+                words[start + i] = ((pair[0] as u16) << 8) | pair[1] as u16;
+            }
+        }
+
+        let mut sector: [u8; SECTOR] = [0; SECTOR];
+        for (i, word) in words.iter().enumerate()
+        {
+            sector[i * 2] = *word as u8;
+            sector[i * 2 + 1] = (*word >> 8) as u8;
+        }
+        return sector
+    }
+
+    fn next_word_read(&mut self) -> u8 //low byte of the next data word, the high byte goes into the latch
+    {
+        if self.transfer != Transfer::Read { return 0xff }
+
+        let low: u8 = self.buffer[self.buffer_pos];
+        self.data_high = self.buffer[self.buffer_pos + 1];
+        self.buffer_pos += 2;
+
+        if self.buffer_pos >= SECTOR                        //end of this sector
+        {
+            self.sectors_left -= 1;
+            if self.sectors_left == 0 || self.current_lba == u64::MAX
+            {
+                self.transfer = Transfer::Idle;
+                self.status = ST_DRDY | ST_DSC;
+            }
+            else
+            {
+                self.current_lba += 1;
+                self.start_read();
+            }
+        }
+
+        return low
+    }
+
+    fn next_word_write(&mut self, low: u8)
+    {
+        if self.transfer != Transfer::Write { return }
+
+        self.buffer[self.buffer_pos] = low;
+        self.buffer[self.buffer_pos + 1] = self.data_high;
+        self.buffer_pos += 2;
+
+        if self.buffer_pos >= SECTOR
+        {
+            if self.store_sector().is_err() { return self.fail(ERR_ABRT) }
+
+            self.buffer_pos = 0;
+            self.sectors_left -= 1;
+            self.current_lba += 1;
+
+            if self.sectors_left == 0
+            {
+                self.transfer = Transfer::Idle;
+                self.status = ST_DRDY | ST_DSC;
+            }
+            else if self.current_lba >= self.total_sectors
+            {
+                self.fail(ERR_IDNF | ERR_ABRT)
+            }
+        }
+    }
+}
+
+impl Device for Cffa1
+{
+    fn read(&mut self, reg: u16) -> u8
+    {
+        if reg < IO_OFFSET { return self.rom[reg as usize % ROM_SIZE] }
+
+        match reg - IO_OFFSET
+        {
+            DATA => self.next_word_read(),
+            other => self.peek(IO_OFFSET + other)
+        }
+    }
+
+    fn write(&mut self, reg: u16, data: u8)
+    {
+        if reg < IO_OFFSET { return }
+
+        match reg - IO_OFFSET
+        {
+            DATA_HIGH => self.data_high = data,
+            DEVICE_CONTROL if data & 0x04 != 0 =>              //software reset
+            {
+                self.transfer = Transfer::Idle;
+                self.error = 0;
+                self.status = ST_DRDY | ST_DSC;
+            },
+            DATA => self.next_word_write(data),
+            SECTOR_COUNT => self.sector_count = data,
+            LBA_0 => self.lba[0] = data,
+            LBA_8 => self.lba[1] = data,
+            LBA_16 => self.lba[2] = data,
+            HEAD => self.head = data,
+            COMMAND => self.execute_command(data),
+            _ => ()                                             //features, CS mask, device control without SRST
+        }
+    }
+
+    fn peek(&self, reg: u16) -> u8
+    {
+        if reg < IO_OFFSET { return self.rom[reg as usize % ROM_SIZE] }
+
+        match reg - IO_OFFSET
+        {
+            DATA_HIGH => self.data_high,
+            SET_CS_MASK | CLEAR_CS_MASK => 0,
+            DEVICE_CONTROL | COMMAND => self.status & !ST_BSY,  //commands finish instantly, the card is never busy
+            DATA if self.transfer == Transfer::Read => self.buffer[self.buffer_pos],
+            ERROR => self.error,
+            SECTOR_COUNT => self.sector_count,
+            LBA_0 => self.lba[0],
+            LBA_8 => self.lba[1],
+            LBA_16 => self.lba[2],
+            HEAD => self.head,
+            _ => 0xff                                           //including the data port with no transfer running
+        }
+    }
+}
@@ -0,0 +1,37 @@
+pub mod aci;
+pub mod acia6551;
+pub mod acia6850;
+pub mod cffa1;
+pub mod pia;
+pub mod serial;
+pub mod via;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub trait Device //a memory-mapped peripheral. Register numbers are offsets from the address the device is attached at.
+{
+    fn read(&mut self, reg: u16) -> u8;             //CPU read of a register, with whatever side effects that has on the chip
+    fn write(&mut self, reg: u16, data: u8);        //CPU write to a register
+    fn peek(&self, reg: u16) -> u8;                 //register contents without side effects, for the monitor
+
+    fn tick(&mut self) {}                           //called once for every CPU clock cycle
+
+    fn irq(&self) -> bool { false }                 //state of the device's IRQ output, true when asserted (pulling the line low)
+    fn nmi(&self) -> bool { false }                 //state of the device's NMI output
+    fn rdy(&self) -> bool { false }                 //true while the device holds RDY low to stall the CPU, as DMA does
+    fn so(&self) -> bool { false }                  //state of the device's SO output, the CPU sets V when it goes low
+}
+
+
+impl<T: Device> Device for Rc<RefCell<T>> //lets the front end keep a handle on a device after attaching it to a bus
+{
+    fn read(&mut self, reg: u16) -> u8 { self.borrow_mut().read(reg) }
+    fn write(&mut self, reg: u16, data: u8) { self.borrow_mut().write(reg, data) }
+    fn peek(&self, reg: u16) -> u8 { self.borrow().peek(reg) }
+    fn tick(&mut self) { self.borrow_mut().tick() }
+    fn irq(&self) -> bool { self.borrow().irq() }
+    fn nmi(&self) -> bool { self.borrow().nmi() }
+    fn rdy(&self) -> bool { self.borrow().rdy() }
+    fn so(&self) -> bool { self.borrow().so() }
+}
@@ -0,0 +1,239 @@
+use crate::devices::Device;
+
+const PORT_A: u16 = 0;          //register select lines RS1 and RS0
+const CRA: u16 = 1;
+const PORT_B: u16 = 2;
+const CRB: u16 = 3;
+
+const CR_C1_IRQ_ENABLE: u8 = 0b00000001;    //control register bits, the same layout for CRA and CRB
+const CR_C1_RISING: u8 = 0b00000010;
+const CR_OUTPUT_REG: u8 = 0b00000100;       //0 selects the data direction register, 1 the output register
+const CR_C2_BIT3: u8 = 0b00001000;          //C2 input: interrupt enable. C2 output: pulse/handshake select or manual level
+const CR_C2_BIT4: u8 = 0b00010000;          //C2 input: active edge. C2 output: manual mode
+const CR_C2_OUTPUT: u8 = 0b00100000;
+const CR_IRQ2_FLAG: u8 = 0b01000000;
+const CR_IRQ1_FLAG: u8 = 0b10000000;
+
+
+pub struct PiaPort //one side (A or B) of the PIA
+{
+    pub output: u8,         //output register
+    pub ddr: u8,            //data direction register, 1 bits are outputs
+    pub cr: u8,             //control register
+    pub input: u8,          //levels driven onto the port pins by the outside world
+    pub c1: bool,           //level of the C1 control input
+    pub c2: bool,           //level of the C2 control line, whichever side is driving it
+    c2_pulse: u8,           //cycles left before a pulse mode C2 output returns high
+}
+
+impl PiaPort
+{
+    fn new() -> PiaPort
+    {
+        PiaPort { output: 0, ddr: 0, cr: 0, input: 0xff, c1: false, c2: true, c2_pulse: 0 }
+    }
+
+    fn c2_is_output(&self) -> bool
+    {
+        return self.cr & CR_C2_OUTPUT != 0
+    }
+
+    fn c2_handshake(&self) -> bool      //CR bits 5-3 = 100
+    {
+        return self.cr & (CR_C2_OUTPUT | CR_C2_BIT4 | CR_C2_BIT3) == CR_C2_OUTPUT
+    }
+
+    fn c2_pulse_mode(&self) -> bool     //CR bits 5-3 = 101
+    {
+        return self.cr & (CR_C2_OUTPUT | CR_C2_BIT4 | CR_C2_BIT3) == CR_C2_OUTPUT | CR_C2_BIT3
+    }
+
+    fn write_control(&mut self, data: u8)
+    {
+        self.cr = (self.cr & (CR_IRQ1_FLAG | CR_IRQ2_FLAG)) | (data & 0x3f);   //the two flags are read only
+
+        if self.c2_is_output()
+        {
+            self.cr &= !CR_IRQ2_FLAG;                                                //IRQ2 always reads 0 while C2 is an output
+
+            if self.cr & CR_C2_BIT4 != 0 { self.c2 = self.cr & CR_C2_BIT3 != 0 }   //manual output mode, CR bit 3 is the level
+            else { self.c2 = true; self.c2_pulse = 0 }                               //handshake and pulse modes idle high
+        }
+    }
+
+    fn data_access(&mut self)           //a read of port A or a write of port B starts a handshake or a pulse on C2
+    {
+        if self.c2_handshake()
+        {
+            self.c2 = false;
+        }
+        else if self.c2_pulse_mode()
+        {
+            self.c2 = false;
+            self.c2_pulse = 1;
+        }
+    }
+
+    fn set_c1(&mut self, level: bool)
+    {
+        if active_edge(self.c1, level, self.cr & CR_C1_RISING != 0)
+        {
+            self.cr |= CR_IRQ1_FLAG;
+            if self.c2_handshake() { self.c2 = true }   //the active C1 transition completes a handshake
+        }
+        self.c1 = level;
+    }
+
+    fn set_c2(&mut self, level: bool)
+    {
+        if self.c2_is_output() { return }               //we're driving the line, nothing to detect
+
+        if active_edge(self.c2, level, self.cr & CR_C2_BIT4 != 0)
+        {
+            self.cr |= CR_IRQ2_FLAG;
+        }
+        self.c2 = level;
+    }
+
+    fn irq(&self) -> bool
+    {
+        let irq1: bool = self.cr & CR_IRQ1_FLAG != 0 && self.cr & CR_C1_IRQ_ENABLE != 0;
+        let irq2: bool = self.cr & CR_IRQ2_FLAG != 0 && self.cr & CR_C2_BIT3 != 0 && !self.c2_is_output();
+        return irq1 || irq2
+    }
+
+    fn tick(&mut self)
+    {
+        if self.c2_pulse > 0
+        {
+            self.c2_pulse -= 1;
+            if self.c2_pulse == 0 { self.c2 = true }
+        }
+    }
+}
+
+
+fn active_edge(old: bool, new: bool, rising: bool) -> bool
+{
+    if rising { !old && new } else { old && !new }
+}
+
+
+pub struct Pia6821 //Motorola 6821 Peripheral Interface Adapter
+{
+    pub a: PiaPort,
+    pub b: PiaPort,
+}
+
+impl Default for Pia6821
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl Pia6821
+{
+    pub fn new() -> Pia6821
+    {
+        Pia6821 { a: PiaPort::new(), b: PiaPort::new() }
+    }
+
+    pub fn reset(&mut self)
+    {
+        self.a = PiaPort::new();
+        self.b = PiaPort::new();
+    }
+
+    pub fn set_port_a_input(&mut self, data: u8) { self.a.input = data }
+    pub fn set_port_b_input(&mut self, data: u8) { self.b.input = data }
+
+    pub fn port_a_output(&self) -> u8 //what the pins show to the outside world, inputs float high
+    {
+        return (self.a.output & self.a.ddr) | !self.a.ddr
+    }
+
+    pub fn port_b_output(&self) -> u8
+    {
+        return (self.b.output & self.b.ddr) | !self.b.ddr
+    }
+
+    pub fn set_ca1(&mut self, level: bool) { self.a.set_c1(level) }
+    pub fn set_ca2(&mut self, level: bool) { self.a.set_c2(level) }
+    pub fn set_cb1(&mut self, level: bool) { self.b.set_c1(level) }
+    pub fn set_cb2(&mut self, level: bool) { self.b.set_c2(level) }
+
+    pub fn ca2(&self) -> bool { self.a.c2 }
+    pub fn cb2(&self) -> bool { self.b.c2 }
+
+    pub fn irqa(&self) -> bool { self.a.irq() }
+    pub fn irqb(&self) -> bool { self.b.irq() }
+}
+
+impl Device for Pia6821
+{
+    fn read(&mut self, reg: u16) -> u8
+    {
+        let value: u8 = self.peek(reg);
+
+        match reg & 3
+        {
+            PORT_A if self.a.cr & CR_OUTPUT_REG != 0 =>     //reading the port A data clears the A interrupt flags
+            {
+                self.a.cr &= !(CR_IRQ1_FLAG | CR_IRQ2_FLAG);
+                self.a.data_access();
+            },
+            PORT_B if self.b.cr & CR_OUTPUT_REG != 0 =>
+            {
+                self.b.cr &= !(CR_IRQ1_FLAG | CR_IRQ2_FLAG);
+            },
+            _ => ()
+        }
+
+        return value
+    }
+
+    fn write(&mut self, reg: u16, data: u8)
+    {
+        match reg & 3
+        {
+            PORT_A => if self.a.cr & CR_OUTPUT_REG != 0 { self.a.output = data } else { self.a.ddr = data },
+            CRA => self.a.write_control(data),
+            PORT_B =>
+                if self.b.cr & CR_OUTPUT_REG != 0
+                {
+                    self.b.output = data;
+                    self.b.data_access();                   //writing the port B data starts the CB2 handshake
+                }
+                else { self.b.ddr = data },
+            CRB => self.b.write_control(data),
+            _ => ()
+        }
+    }
+
+    fn peek(&self, reg: u16) -> u8
+    {
+        match reg & 3
+        {
+            PORT_A =>   //port A reads the pins, output bits included
+                if self.a.cr & CR_OUTPUT_REG != 0 { (self.a.output & self.a.ddr) | (self.a.input & !self.a.ddr) } else { self.a.ddr },
+            CRA => self.a.cr,
+            PORT_B =>   //port B reads its output register for output bits
+                if self.b.cr & CR_OUTPUT_REG != 0 { (self.b.output & self.b.ddr) | (self.b.input & !self.b.ddr) } else { self.b.ddr },
+            CRB => self.b.cr,
+            _ => 0
+        }
+    }
+
+    fn tick(&mut self)
+    {
+        self.a.tick();
+        self.b.tick();
+    }
+
+    fn irq(&self) -> bool   //IRQA and IRQB are open collector outputs, usually both tied to the CPU's IRQ line
+    {
+        return self.irqa() || self.irqb()
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Write, ErrorKind};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::sync::{Mutex, mpsc::{self, Receiver}};
+#[cfg(unix)]
+use std::thread;
+#[cfg(unix)]
+use std::time::Duration;
+
+pub trait SerialBackend //the host side of an emulated serial port. Neither call may block the emulator.
+{
+    fn receive(&mut self) -> Option<u8>;        //next byte from the host, if one has arrived
+    fn transmit(&mut self, data: u8);           //send a byte to the host
+    fn tick(&mut self) {}                       //called every cycle, for anything the backend has to retry
+}
+
+
+pub fn open(spec: &str) -> io::Result<Box<dyn SerialBackend>> //"stdio", "pty" or "tcp:<port>"
+{
+    match spec.trim()
+    {
+        #[cfg(unix)]
+        "stdio" => Ok(Box::new(StdioSerial::new())),
+        #[cfg(unix)]
+        "pty" => Ok(Box::new(PtySerial::new()?)),
+        other => match other.strip_prefix("tcp:")
+        {
+            Some(port) =>
+            {
+                let port: u16 = port.parse().map_err(|_| io::Error::new(ErrorKind::InvalidInput, format!("bad TCP port {}", port)))?;
+                Ok(Box::new(TcpSerial::new(port)?))
+            },
+            None => Err(io::Error::new(ErrorKind::InvalidInput, format!("unknown serial backend {}", other)))
+        }
+    }
+}
+
+
+#[cfg(unix)]
+static STDIN_PAUSED: Mutex<bool> = Mutex::new(false);
+
+#[cfg(unix)]
+pub fn pause_stdin(paused: bool) //hand the console to the monitor and back. Once this returns, StdioSerial won't read stdin until it's unpaused.
+{
+    *STDIN_PAUSED.lock().unwrap_or_else(|e| e.into_inner()) = paused;
+}
+
+
+#[cfg(unix)]
+pub struct StdioSerial //the emulator's own console. A thread feeds stdin into a channel so reads never block, and stops reading while paused.
+{
+    input: Receiver<u8>,
+}
+
+#[cfg(unix)]
+impl Default for StdioSerial
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+#[cfg(unix)]
+impl StdioSerial
+{
+    pub fn new() -> StdioSerial
+    {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move ||
+        {
+            let mut buffer: [u8; 64] = [0; 64];
+
+            loop
+            {
+                let mut stdin = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+                if unsafe { libc::poll(&mut stdin, 1, 50) } <= 0 { continue }     //wait for input without holding the lock
+
+                let paused = STDIN_PAUSED.lock().unwrap_or_else(|e| e.into_inner());
+                if *paused                                                          //the monitor reads it instead
+                {
+                    drop(paused);
+                    thread::sleep(Duration::from_millis(50));
+                    continue
+                }
+
+                let count: isize = unsafe { libc::read(libc::STDIN_FILENO, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len()) };
+                drop(paused);
+
+                if count <= 0 { return }                                            //end of input
+                for &b in &buffer[..count as usize]
+                {
+                    if sender.send(b).is_err() { return }
+                }
+            }
+        });
+
+        StdioSerial { input: receiver }
+    }
+}
+
+#[cfg(unix)]
+impl SerialBackend for StdioSerial
+{
+    fn receive(&mut self) -> Option<u8>
+    {
+        return self.input.try_recv().ok()
+    }
+
+    fn transmit(&mut self, data: u8)
+    {
+        let mut out = io::stdout();
+        let _ = out.write_all(&[data]);
+        let _ = out.flush();
+    }
+}
+
+
+pub struct TcpSerial //a listener on localhost, so telnet or nc can be the terminal. One client at a time.
+{
+    listener: TcpListener,
+    client: Option<TcpStream>,
+    unsent: VecDeque<u8>,   //bytes the socket wasn't ready for, sent ahead of anything new
+}
+
+impl TcpSerial
+{
+    pub fn new(port: u16) -> io::Result<TcpSerial>
+    {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+
+        println!("Serial port listening on {}", listener.local_addr()?);
+
+        Ok(TcpSerial { listener, client: None, unsent: VecDeque::new() })
+    }
+
+    pub fn port(&self) -> io::Result<u16> //the port actually listened on, for when new was asked for port 0
+    {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    fn poll_client(&mut self)
+    {
+        if self.client.is_none()
+        {
+            if let Ok((stream, addr)) = self.listener.accept()
+            {
+                if stream.set_nonblocking(true).is_ok()
+                {
+                    println!("Serial port connected to {}", addr);
+                    self.client = Some(stream);
+                }
+            }
+        }
+    }
+
+    fn send_unsent(&mut self) //write out as much of the queue as the socket will take
+    {
+        while !self.unsent.is_empty()
+        {
+            let client: &mut TcpStream = match self.client.as_mut() { Some(client) => client, None => return };
+            match client.write(self.unsent.as_slices().0)
+            {
+                Ok(0) => return,
+                Ok(sent) => { self.unsent.drain(..sent); },
+                Err(e) if e.kind() != ErrorKind::WouldBlock => self.hang_up(),
+                Err(_) => return            //full, try again on the next tick
+            }
+        }
+    }
+
+    fn hang_up(&mut self)
+    {
+        self.client = None;
+        self.unsent.clear();
+    }
+}
+
+impl SerialBackend for TcpSerial
+{
+    fn receive(&mut self) -> Option<u8>
+    {
+        self.poll_client();
+
+        let mut byte: [u8; 1] = [0];
+        match self.client.as_mut()?.read(&mut byte)
+        {
+            Ok(1) => Some(byte[0]),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => None,
+            _ => { self.hang_up(); None }       //the client hung up
+        }
+    }
+
+    fn transmit(&mut self, data: u8)
+    {
+        self.poll_client();
+
+        if self.client.is_some()
+        {
+            self.unsent.push_back(data);
+            if self.unsent.len() == 1 { self.send_unsent() }    //otherwise the socket was full last time, leave it to tick
+        }
+    }
+
+    fn tick(&mut self)
+    {
+        if !self.unsent.is_empty() { self.send_unsent() }
+    }
+}
+
+
+#[cfg(unix)]
+pub struct PtySerial //a pseudo terminal, for screen, minicom or picocom to open
+{
+    master: std::fs::File,
+    pub slave_path: String,
+}
+
+#[cfg(unix)]
+impl PtySerial
+{
+    pub fn new() -> io::Result<PtySerial>
+    {
+        use std::ffi::CStr;
+        use std::os::unix::io::FromRawFd;
+
+        unsafe
+        {
+            let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            if fd < 0 { return Err(io::Error::last_os_error()) }
+
+            let master = std::fs::File::from_raw_fd(fd);    //closes the fd on any error below
+
+            if libc::grantpt(fd) != 0 || libc::unlockpt(fd) != 0 { return Err(io::Error::last_os_error()) }
+
+            let name = libc::ptsname(fd);
+            if name.is_null() { return Err(io::Error::last_os_error()) }
+            let slave_path: String = CStr::from_ptr(name).to_string_lossy().into_owned();
+
+            let flags = libc::fcntl(fd, libc::F_GETFL);
+            if flags < 0 || libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) < 0 { return Err(io::Error::last_os_error()) }
+
+            println!("Serial port available at {}", slave_path);
+
+            Ok(PtySerial { master, slave_path })
+        }
+    }
+}
+
+#[cfg(unix)]
+impl SerialBackend for PtySerial
+{
+    fn receive(&mut self) -> Option<u8>
+    {
+        let mut byte: [u8; 1] = [0];
+        match self.master.read(&mut byte)
+        {
+            Ok(1) => Some(byte[0]),
+            _ => None               //nothing waiting, or nobody has the other end open yet
+        }
+    }
+
+    fn transmit(&mut self, data: u8)
+    {
+        let _ = self.master.write(&[data]);
+    }
+}
@@ -0,0 +1,468 @@
+use crate::devices::Device;
+
+const ORB: u16 = 0x0;           //register select lines RS3-RS0
+const ORA: u16 = 0x1;
+const DDRB: u16 = 0x2;
+const DDRA: u16 = 0x3;
+const T1C_L: u16 = 0x4;
+const T1C_H: u16 = 0x5;
+const T1L_L: u16 = 0x6;
+const T1L_H: u16 = 0x7;
+const T2C_L: u16 = 0x8;
+const T2C_H: u16 = 0x9;
+const SR: u16 = 0xa;
+const ACR: u16 = 0xb;
+const PCR: u16 = 0xc;
+const IFR: u16 = 0xd;
+const IER: u16 = 0xe;
+const ORA_NH: u16 = 0xf;        //port A without handshake
+
+const IRQ_CA2: u8 = 0b00000001; //interrupt flag and enable bits
+const IRQ_CA1: u8 = 0b00000010;
+const IRQ_SR: u8 = 0b00000100;
+const IRQ_CB2: u8 = 0b00001000;
+const IRQ_CB1: u8 = 0b00010000;
+const IRQ_T2: u8 = 0b00100000;
+const IRQ_T1: u8 = 0b01000000;
+const IRQ_ANY: u8 = 0b10000000;
+
+const ACR_PA_LATCH: u8 = 0b00000001;
+const ACR_PB_LATCH: u8 = 0b00000010;
+const ACR_T2_COUNT_PB6: u8 = 0b00100000;
+const ACR_T1_CONTINUOUS: u8 = 0b01000000;
+const ACR_T1_PB7: u8 = 0b10000000;
+
+
+fn active_edge(old: bool, new: bool, rising: bool) -> bool
+{
+    if rising { !old && new } else { old && !new }
+}
+
+
+pub struct Via6522 //MOS 6522 Versatile Interface Adapter
+{
+    pub ora: u8,
+    pub orb: u8,
+    pub ddra: u8,
+    pub ddrb: u8,
+    pub acr: u8,
+    pub pcr: u8,
+    pub ifr: u8,
+    pub ier: u8,
+    pub sr: u8,
+
+    pub t1_counter: u16,
+    pub t1_latch: u16,
+    t1_armed: bool,             //one-shot mode only interrupts once per write to T1C-H
+    t1_reload: bool,            //the counter reloads from the latch one cycle after it passes zero
+    pb7: bool,                  //PB7 level when T1 is driving it
+
+    pub t2_counter: u16,
+    t2_latch_lo: u8,
+    t2_armed: bool,
+
+    sr_count: u8,               //bits left to shift
+    sr_divider: u16,            //cycles left until the next shift clock edge
+    sr_clock: bool,             //CB1 level when the shift register drives it
+
+    port_a_input: u8,           //levels driven onto the pins by the outside world
+    port_b_input: u8,
+    ira_latch: u8,              //inputs latched on an active C1 edge, when latching is enabled in the ACR
+    irb_latch: u8,
+
+    ca1: bool,
+    ca2: bool,
+    cb1: bool,
+    cb2: bool,
+    c2_pulse_a: bool,           //a pulse mode CA2/CB2 output returns high on the next cycle
+    c2_pulse_b: bool,
+}
+
+impl Default for Via6522
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl Via6522
+{
+    pub fn new() -> Via6522
+    {
+        Via6522 {
+            ora: 0, orb: 0, ddra: 0, ddrb: 0, acr: 0, pcr: 0, ifr: 0, ier: 0, sr: 0,
+            t1_counter: 0xffff, t1_latch: 0xffff, t1_armed: false, t1_reload: false, pb7: true,
+            t2_counter: 0xffff, t2_latch_lo: 0xff, t2_armed: false,
+            sr_count: 0, sr_divider: 0, sr_clock: true,
+            port_a_input: 0xff, port_b_input: 0xff, ira_latch: 0xff, irb_latch: 0xff,
+            ca1: true, ca2: true, cb1: true, cb2: true, c2_pulse_a: false, c2_pulse_b: false
+        }
+    }
+
+    pub fn reset(&mut self) //RES clears every register except the timers, latches and shift register
+    {
+        self.ora = 0; self.orb = 0; self.ddra = 0; self.ddrb = 0;
+        self.acr = 0; self.pcr = 0; self.ifr = 0; self.ier = 0;
+        self.t1_armed = false; self.t2_armed = false; self.sr_count = 0;
+        self.ca2 = true; self.cb2 = true; self.sr_clock = true;
+    }
+
+
+    //pins seen from outside the chip
+
+    pub fn set_port_a_input(&mut self, data: u8) { self.port_a_input = data }
+
+    pub fn set_port_b_input(&mut self, data: u8)
+    {
+        let pb6_falling: bool = self.port_b_input & 0x40 != 0 && data & 0x40 == 0;
+        self.port_b_input = data;
+
+        if pb6_falling && self.acr & ACR_T2_COUNT_PB6 != 0     //T2 counting pulses on PB6
+        {
+            self.t2_counter = self.t2_counter.wrapping_sub(1);
+            if self.t2_counter == 0 && self.t2_armed
+            {
+                self.ifr |= IRQ_T2;
+                self.t2_armed = false;
+            }
+        }
+    }
+
+    pub fn port_a_output(&self) -> u8 //inputs float high
+    {
+        return (self.ora & self.ddra) | !self.ddra
+    }
+
+    pub fn port_b_output(&self) -> u8
+    {
+        let mut pins: u8 = (self.orb & self.ddrb) | !self.ddrb;
+
+        if self.acr & ACR_T1_PB7 != 0   //T1 owns PB7 in its output modes
+        {
+            pins = (pins & 0x7f) | if self.pb7 { 0x80 } else { 0 };
+        }
+
+        return pins
+    }
+
+    pub fn set_ca1(&mut self, level: bool)
+    {
+        if active_edge(self.ca1, level, self.pcr & 0b00000001 != 0)
+        {
+            self.ifr |= IRQ_CA1;
+            if self.acr & ACR_PA_LATCH != 0 { self.ira_latch = self.port_a_input }
+            if self.ca2_mode() == 0b100 { self.ca2 = true }        //the active CA1 edge completes a handshake
+        }
+        self.ca1 = level;
+    }
+
+    pub fn set_ca2(&mut self, level: bool)
+    {
+        let mode: u8 = self.ca2_mode();
+        if mode & 0b100 != 0 { return }                             //CA2 is an output
+
+        if active_edge(self.ca2, level, mode & 0b010 != 0)
+        {
+            self.ifr |= IRQ_CA2;
+        }
+        self.ca2 = level;
+    }
+
+    pub fn set_cb1(&mut self, level: bool)
+    {
+        let old: bool = self.cb1;
+        self.cb1 = level;
+
+        if self.sr_mode() == 0b011 && !old && level { self.shift_in() }         //external clock, shift in on the rising edge
+        if self.sr_mode() == 0b111 && old && !level { self.shift_out() }        //external clock, shift out on the falling edge
+
+        if active_edge(old, level, self.pcr & 0b00010000 != 0)
+        {
+            self.ifr |= IRQ_CB1;
+            if self.acr & ACR_PB_LATCH != 0 { self.irb_latch = self.port_b_input }
+            if self.cb2_mode() == 0b100 { self.cb2 = true }
+        }
+    }
+
+    pub fn set_cb2(&mut self, level: bool)
+    {
+        let mode: u8 = self.cb2_mode();
+        if mode & 0b100 != 0 || self.sr_mode() & 0b100 != 0 { return }           //CB2 is being driven by the VIA
+
+        if active_edge(self.cb2, level, mode & 0b010 != 0)
+        {
+            self.ifr |= IRQ_CB2;
+        }
+        self.cb2 = level;
+    }
+
+    pub fn ca2(&self) -> bool { self.ca2 }
+    pub fn cb2(&self) -> bool { self.cb2 }
+
+    pub fn cb1(&self) -> bool //CB1 is the shift clock output when the shift register runs from T2 or the system clock
+    {
+        match self.sr_mode() { 0b000 | 0b011 | 0b111 => self.cb1, _ => self.sr_clock }
+    }
+
+
+    fn ca2_mode(&self) -> u8 { (self.pcr >> 1) & 0b111 }
+    fn cb2_mode(&self) -> u8 { (self.pcr >> 5) & 0b111 }
+    fn sr_mode(&self) -> u8 { (self.acr >> 2) & 0b111 }
+
+    fn write_pcr(&mut self, data: u8)
+    {
+        self.pcr = data;
+
+        match self.ca2_mode() { 0b110 => self.ca2 = false, 0b111 | 0b100 | 0b101 => self.ca2 = true, _ => () }
+        match self.cb2_mode() { 0b110 => self.cb2 = false, 0b111 | 0b100 | 0b101 => self.cb2 = true, _ => () }
+    }
+
+    fn port_a_access(&mut self) //reads and writes of ORA clear the CA flags and run the CA2 handshake
+    {
+        self.ifr &= !IRQ_CA1;
+        if self.ca2_mode() & 0b101 != 0b001 { self.ifr &= !IRQ_CA2 }   //independent interrupt modes leave CA2 alone
+
+        match self.ca2_mode()
+        {
+            0b100 => self.ca2 = false,
+            0b101 => { self.ca2 = false; self.c2_pulse_a = true },
+            _ => ()
+        }
+    }
+
+    fn port_b_access(&mut self, write: bool)
+    {
+        self.ifr &= !IRQ_CB1;
+        if self.cb2_mode() & 0b101 != 0b001 { self.ifr &= !IRQ_CB2 }
+
+        if write                        //CB2 handshakes only on writes to ORB
+        {
+            match self.cb2_mode()
+            {
+                0b100 => self.cb2 = false,
+                0b101 => { self.cb2 = false; self.c2_pulse_b = true },
+                _ => ()
+            }
+        }
+    }
+
+    fn read_port_a(&self) -> u8
+    {
+        let pins: u8 = if self.acr & ACR_PA_LATCH != 0 { self.ira_latch } else { self.port_a_input };
+        return (self.ora & self.ddra) | (pins & !self.ddra)     //port A reads the pins, which the outputs drive
+    }
+
+    fn read_port_b(&self) -> u8
+    {
+        let pins: u8 = if self.acr & ACR_PB_LATCH != 0 { self.irb_latch } else { self.port_b_input };
+        let mut value: u8 = (self.orb & self.ddrb) | (pins & !self.ddrb);
+
+        if self.acr & ACR_T1_PB7 != 0
+        {
+            value = (value & 0x7f) | if self.pb7 { 0x80 } else { 0 };
+        }
+
+        return value
+    }
+
+    fn start_shift(&mut self) //any access to the shift register starts a new 8 bit transfer
+    {
+        self.ifr &= !IRQ_SR;
+        if self.sr_mode() != 0
+        {
+            self.sr_count = 8;
+            self.sr_divider = self.shift_period();
+        }
+    }
+
+    fn shift_period(&self) -> u16 //cycles between shift clock edges
+    {
+        match self.sr_mode()
+        {
+            0b001 | 0b100 | 0b101 => self.t2_latch_lo as u16 + 2,
+            _ => 1
+        }
+    }
+
+    fn shift_in(&mut self)
+    {
+        if self.sr_count == 0 { return }
+
+        self.sr = (self.sr << 1) | self.cb2 as u8;
+        self.shift_done();
+    }
+
+    fn shift_out(&mut self)
+    {
+        if self.sr_count == 0 && self.sr_mode() != 0b100 { return }
+
+        self.cb2 = self.sr & 0x80 != 0;
+        self.sr = self.sr.rotate_left(1);      //the shift register recirculates as it shifts out
+        self.shift_done();
+    }
+
+    fn shift_done(&mut self)
+    {
+        if self.sr_mode() == 0b100 { return }  //free running output never stops or interrupts
+
+        self.sr_count -= 1;
+        if self.sr_count == 0
+        {
+            self.ifr |= IRQ_SR;
+        }
+    }
+
+    fn tick_shift_register(&mut self)
+    {
+        let mode: u8 = self.sr_mode();
+        if mode == 0 || mode == 0b011 || mode == 0b111 { return }       //disabled or clocked from outside
+        if self.sr_count == 0 && mode != 0b100 { return }
+
+        self.sr_divider = self.sr_divider.saturating_sub(1);
+        if self.sr_divider > 0 { return }
+        self.sr_divider = self.shift_period();
+
+        self.sr_clock = !self.sr_clock;                                 //one bit per full cycle of the CB1 clock
+        if self.sr_clock
+        {
+            if mode & 0b100 != 0 { self.shift_out() } else { self.shift_in() }
+        }
+    }
+
+    fn tick_timers(&mut self)
+    {
+        if self.t1_reload                                               //free-run mode reloads one cycle after passing zero
+        {
+            self.t1_counter = self.t1_latch;
+            self.t1_reload = false;
+        }
+        else
+        {
+            self.t1_counter = self.t1_counter.wrapping_sub(1);
+
+            if self.t1_counter == 0xffff
+            {
+                if self.t1_armed
+                {
+                    self.ifr |= IRQ_T1;
+                    if self.acr & ACR_T1_CONTINUOUS != 0 { self.pb7 = !self.pb7 } else { self.pb7 = true; self.t1_armed = false }
+                }
+
+                if self.acr & ACR_T1_CONTINUOUS != 0 { self.t1_reload = true }
+            }
+        }
+
+        if self.acr & ACR_T2_COUNT_PB6 == 0                             //T2 as a one-shot interval timer
+        {
+            self.t2_counter = self.t2_counter.wrapping_sub(1);
+
+            if self.t2_counter == 0xffff && self.t2_armed
+            {
+                self.ifr |= IRQ_T2;
+                self.t2_armed = false;
+            }
+        }
+    }
+}
+
+impl Device for Via6522
+{
+    fn read(&mut self, reg: u16) -> u8
+    {
+        let value: u8 = self.peek(reg);
+
+        match reg & 0xf
+        {
+            ORB => self.port_b_access(false),
+            ORA => self.port_a_access(),
+            T1C_L => self.ifr &= !IRQ_T1,
+            T2C_L => self.ifr &= !IRQ_T2,
+            SR => self.start_shift(),
+            _ => ()
+        }
+
+        return value
+    }
+
+    fn write(&mut self, reg: u16, data: u8)
+    {
+        match reg & 0xf
+        {
+            ORB => { self.orb = data; self.port_b_access(true) },
+            ORA => { self.ora = data; self.port_a_access() },
+            DDRB => self.ddrb = data,
+            DDRA => self.ddra = data,
+            T1C_L | T1L_L => self.t1_latch = (self.t1_latch & 0xff00) | data as u16,
+            T1C_H =>                                                    //loads and starts T1
+            {
+                self.t1_latch = (self.t1_latch & 0x00ff) | ((data as u16) << 8);
+                self.t1_counter = self.t1_latch;
+                self.t1_reload = false;
+                self.t1_armed = true;
+                self.ifr &= !IRQ_T1;
+                if self.acr & ACR_T1_PB7 != 0 { self.pb7 = false }      //PB7 goes low for the length of the count
+            },
+            T1L_H =>
+            {
+                self.t1_latch = (self.t1_latch & 0x00ff) | ((data as u16) << 8);
+                self.ifr &= !IRQ_T1;
+            },
+            T2C_L => self.t2_latch_lo = data,
+            T2C_H =>                                                    //loads and starts T2
+            {
+                self.t2_counter = ((data as u16) << 8) | self.t2_latch_lo as u16;
+                self.t2_armed = true;
+                self.ifr &= !IRQ_T2;
+            },
+            SR => { self.sr = data; self.start_shift() },
+            ACR =>
+            {
+                if data & ACR_T1_PB7 != 0 && self.acr & ACR_T1_PB7 == 0 { self.pb7 = true }
+                self.acr = data;
+            },
+            PCR => self.write_pcr(data),
+            IFR => self.ifr &= !(data & 0x7f),                          //writing a 1 clears that flag
+            IER => if data & 0x80 != 0 { self.ier |= data & 0x7f } else { self.ier &= !(data & 0x7f) },
+            ORA_NH => self.ora = data,
+            _ => ()
+        }
+    }
+
+    fn peek(&self, reg: u16) -> u8
+    {
+        match reg & 0xf
+        {
+            ORB => self.read_port_b(),
+            ORA | ORA_NH => self.read_port_a(),
+            DDRB => self.ddrb,
+            DDRA => self.ddra,
+            T1C_L => self.t1_counter as u8,
+            T1C_H => (self.t1_counter >> 8) as u8,
+            T1L_L => self.t1_latch as u8,
+            T1L_H => (self.t1_latch >> 8) as u8,
+            T2C_L => self.t2_counter as u8,
+            T2C_H => (self.t2_counter >> 8) as u8,
+            SR => self.sr,
+            ACR => self.acr,
+            PCR => self.pcr,
+            IFR => if self.irq() { self.ifr | IRQ_ANY } else { self.ifr },
+            IER => self.ier | 0x80,
+            _ => 0
+        }
+    }
+
+    fn tick(&mut self)
+    {
+        if self.c2_pulse_a { self.ca2 = true; self.c2_pulse_a = false }
+        if self.c2_pulse_b { self.cb2 = true; self.c2_pulse_b = false }
+
+        self.tick_timers();
+        self.tick_shift_register();
+    }
+
+    fn irq(&self) -> bool
+    {
+        return self.ifr & self.ier & 0x7f != 0
+    }
+}
@@ -0,0 +1,116 @@
+//Disassembler: turns the bytes on a bus back into assembly, with operands written the usual way for each addressing mode
+//and branch targets worked out. It only peeks at memory, so it never sets off a device's side effects.
+
+use crate::bus::Bus;
+use crate::cpu::Variant;
+use crate::opcodes::{Opcode, Mode};
+use crate::opcodes::Mnemonic::*;
+
+use std::fmt;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line //one disassembled instruction
+{
+    pub addr: u16,
+    pub bytes: Vec<u8>,         //the opcode and its operand bytes
+    pub text: String,           //mnemonic and operand, e.g. "LDA ($20),Y"
+    pub target: Option<u16>,    //where a branch, JMP or JSR goes, when that can be known without running it
+}
+
+impl Line
+{
+    pub fn next(&self) -> u16 //address of the instruction after this one
+    {
+        return self.addr.wrapping_add(self.bytes.len() as u16)
+    }
+}
+
+impl fmt::Display for Line
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result //"E000  A9 00     LDA #$00"
+    {
+        let hex: Vec<String> = self.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        write!(f, "{:04X}  {:<8}  {}", self.addr, hex.join(" "), self.text)
+    }
+}
+
+
+pub fn mnemonic_name(opcode: u8, op: &Opcode) -> String //the bit instructions carry their bit number in the name, as in RMB3 and BBS7
+{
+    match op.mnemonic
+    {
+        RMB | SMB | BBR | BBS => format!("{:?}{}", op.mnemonic, (opcode >> 4) & 7),
+        _ => format!("{:?}", op.mnemonic)
+    }
+}
+
+fn relative(from: u16, offset: u8) -> u16 //a branch target, counted from the end of the instruction
+{
+    return from.wrapping_add(offset as i8 as u16)
+}
+
+
+pub fn disassemble_one(memory: &dyn Bus, variant: Variant, addr: u16) -> Line //disassemble the instruction at addr
+{
+    let opcode: u8 = memory.peek(addr);
+    let op: &Opcode = &variant.opcodes()[opcode as usize];
+
+    let bytes: Vec<u8> = (0..op.length as u16).map(|i| memory.peek(addr.wrapping_add(i))).collect();
+    let byte: u8 = bytes.get(1).copied().unwrap_or(0);
+    let word: u16 = byte as u16 | (bytes.get(2).copied().unwrap_or(0) as u16) << 8;
+    let next: u16 = addr.wrapping_add(op.length as u16);
+
+    let mut target: Option<u16> = None;
+    let operand: String = match op.mode
+    {
+        Mode::Implied => String::new(),
+        Mode::Accumulator => "A".to_string(),
+        Mode::Immediate => format!("#${:02X}", byte),
+        Mode::ZeroPage => format!("${:02X}", byte),
+        Mode::ZeroPageX => format!("${:02X},X", byte),
+        Mode::ZeroPageY => format!("${:02X},Y", byte),
+        Mode::Absolute =>
+        {
+            if matches!(op.mnemonic, JMP | JSR) { target = Some(word) }
+            format!("${:04X}", word)
+        },
+        Mode::AbsoluteX => format!("${:04X},X", word),
+        Mode::AbsoluteY => format!("${:04X},Y", word),
+        Mode::Indirect => format!("(${:04X})", word),
+        Mode::IndirectX => format!("(${:02X},X)", byte),
+        Mode::IndirectY => format!("(${:02X}),Y", byte),
+        Mode::Relative =>
+        {
+            target = Some(relative(next, byte));
+            format!("${:04X}", relative(next, byte))
+        },
+        Mode::ZeroPageIndirect => format!("(${:02X})", byte),
+        Mode::AbsoluteIndexedIndirect => format!("(${:04X},X)", word),
+        Mode::ZeroPageRelative =>
+        {
+            target = Some(relative(next, bytes[2]));
+            format!("${:02X},${:04X}", byte, relative(next, bytes[2]))
+        },
+    };
+
+    let name: String = mnemonic_name(opcode, op);
+    let text: String = if operand.is_empty() { name } else { format!("{} {}", name, operand) };
+
+    return Line { addr, bytes, text, target }
+}
+
+pub fn disassemble(memory: &dyn Bus, variant: Variant, start: u16, count: usize) -> Vec<Line> //disassemble count instructions from start
+{
+    let mut lines: Vec<Line> = Vec::with_capacity(count);
+    let mut addr: u16 = start;
+
+    for _ in 0..count
+    {
+        let line: Line = disassemble_one(memory, variant, addr);
+        addr = line.next();
+        lines.push(line);
+    }
+
+    return lines
+}
@@ -0,0 +1,14 @@
+/* Rust65: an example 6502 system emulator in Rust
+Written by Peter Worthington, 2023
+
+The emulator core (CPU and memory bus) as a library, so other tools can drive it.
+The SDL2 Apple I front end lives in main.rs and is built with the "sdl" feature. */
+
+pub mod asm;
+pub mod breakpoints;
+pub mod bus;
+pub mod cpu;
+pub mod devices;
+pub mod disasm;
+pub mod opcodes;
+pub mod trace;
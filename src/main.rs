@@ -1,8 +1,9 @@
 /* Rust65: an example 6502 system emulator in Rust
-Written by Peter Worthington, 2023 */
+Written by Peter Worthington, 2023
 
-mod bus;
-mod cpu;
+SDL2 Apple I front end, built on top of the rust65 library. */
+
+mod apple1;
 mod terminal;
 
 extern crate sdl2;
@@ -10,10 +11,15 @@ extern crate spin_sleep;
 
 use config::Config;
 
-use crate::bus::Segment;
-use crate::cpu::CpuStatus;
+use rust65::bus::OpenBus;
+use rust65::cpu::{CpuError, CpuStatus, StackCheck, Variant};
+#[cfg(unix)]
+use rust65::devices::serial;
+use rust65::trace::TraceFormat;
+
+use crate::apple1::AppleOne;
 
-use std::io::{Read, Error, ErrorKind, Write, stdout};
+use std::io::{Read, Write, stdout};
 use std::fs::File;
 use std::path::Path;
 use std::str::Chars;
@@ -39,7 +45,10 @@ fn main() {
         Ok(file) => file
     };
     let mut rom_array: [u8; 0x2000] = [0; 0x2000];
-    rom_file.read(&mut rom_array);
+    let mut rom_contents: Vec<u8> = Vec::new();
+    if let Err(why) = rom_file.read_to_end(&mut rom_contents) { panic!("couldn't read {}: {}", rom_path.display(), why) }
+    let rom_length = rom_contents.len().min(rom_array.len());
+    rom_array[..rom_length].copy_from_slice(&rom_contents[..rom_length]);
     let rom: &mut[u8] = &mut rom_array[..];
 
 
@@ -47,25 +56,28 @@ fn main() {
     let dram: &mut[u8] = &mut dram_array[..];
 
 
-    let mut pia_in_array: [u8; 3] = [0; 3];         //set up Peripheral Interface Adapter registers
-    let mut pia_out_array: [u8; 3] = [0; 3];
-    let pia_in: &mut[u8] = &mut pia_in_array[..];
-    let pia_out: &mut[u8] = &mut pia_out_array[..];
-
-
-    let memory: &mut[Segment] =                     //define memory map
-    &mut[
-        Segment::new(dram, 0, true, true),
-        Segment::new(rom, 0xe000, false, true),
-        Segment::new(pia_in, 0xd010, false, true),
-        Segment::new(pia_out, 0xd010, true, false)
-    ];
+    let mut apple = AppleOne::new(dram, rom);       //define memory map and attach the peripherals
+    apple.memory.open_bus = OpenBus::from_name(unpacked_settings.get("open_bus").map_or("float", |o| o.as_str())).expect("open_bus should be float, trap, error or a fill byte in hex");
 
     let clock: u64 = unpacked_settings.get("cpu_speed").unwrap().parse().unwrap();
+    apple.attach_serial(&unpacked_settings, clock);
+    apple.attach_aci(&unpacked_settings, clock);
+    apple.attach_cffa1(&unpacked_settings);
     let pia_refresh: u64 = clock / unpacked_settings.get("terminal_speed").unwrap().parse::<u64>().unwrap();                     //The real Apple 1 terminal updated every 16.7 milliseconds. clock / 60 provides a close approximate to the original, diving clock by higher values provides faster print speeds
     let video_refresh: u64 = 1000000000 / 120;
 
     let mut nm65 = CpuStatus::new(clock); //create and initialize registers and other cpu state
+    nm65.variant = Variant::from_name(unpacked_settings.get("cpu_variant").map_or("6502", |v| v.as_str())).expect("cpu_variant should be 6502, 65c02, r65c02 or w65c02s");
+    nm65.strict = unpacked_settings.get("cpu_strict").is_some_and(|s| s == "true");                          //optional: halt on undocumented opcodes
+    nm65.magic = u8::from_str_radix(unpacked_settings.get("cpu_magic").map_or("ee", |m| m.as_str()), 16).unwrap(); //optional: ANE/LXA constant in hex
+    nm65.cycle_accurate = unpacked_settings.get("cpu_cycle_accurate").is_some_and(|s| s == "true");          //optional: every bus cycle, dummy accesses included
+    nm65.stack_check = StackCheck::from_name(unpacked_settings.get("stack_check").map_or("off", |c| c.as_str())).expect("stack_check should be off, warn or halt");
+    nm65.tracer.set_capacity(unpacked_settings.get("trace_buffer").map_or(256, |n| n.parse().expect("trace_buffer should be a number of instructions")));
+    nm65.tracer.format = TraceFormat::from_name(unpacked_settings.get("trace_format").map_or("nestest", |f| f.as_str())).expect("trace_format should be nestest or csv");
+    if let Some(trace_file) = unpacked_settings.get("trace_file")                                           //optional: log every instruction to a file
+    {
+        if let Err(why) = nm65.tracer.log_to(Path::new(trace_file)) { panic!("couldn't create {}: {}", trace_file, why) }
+    }
 
     let mut cycle_total: u64 = 0;
     let mut frame_time: time::Duration = time::Duration::ZERO;
@@ -121,88 +133,113 @@ fn main() {
                 Event::KeyDown { keycode: Some(Keycode::Escape), .. } => 
                 {
                     nm65.running = false; 
+                    nm65.tracer.flush();
+                    let stats = apple.memory.stats;
+                    println!("Bus: {} unmapped reads, {} unmapped writes, {} writes to ROM", stats.unmapped_reads, stats.unmapped_writes, stats.read_only_writes);
                     print!("Emulation paused, dropping into monitor \n>");
-                    stdout().flush();
+                    let _ = stdout().flush();
                 },
                 Event::Window { win_event: WindowEvent::FocusGained, .. } => video_subsystem.text_input().start(),
                 Event::Window { win_event: WindowEvent::FocusLost, .. } => video_subsystem.text_input().stop(),
-                Event::KeyDown { keycode: Some(Keycode::Return), .. } => if !pasting { i_char = Some(0xd as char) },
-                Event::KeyDown { keycode: Some(Keycode::Insert), .. } => 
-                    if video_subsystem.clipboard().has_clipboard_text() 
-                    {
-                        pasted_text = video_subsystem.clipboard().clipboard_text().unwrap();
-                        pasted_chars = pasted_text.chars();
-                        pasting = true;
-                    },
-                Event::TextInput { text: t, .. } => if !pasting { i_char = t.chars().next() },
+                Event::KeyDown { keycode: Some(Keycode::Return), .. } if !pasting => i_char = Some(0xd as char),
+                Event::KeyDown { keycode: Some(Keycode::Insert), .. } if video_subsystem.clipboard().has_clipboard_text() =>
+                {
+                    pasted_text = video_subsystem.clipboard().clipboard_text().unwrap();
+                    pasted_chars = pasted_text.chars();
+                    pasting = true;
+                },
+                Event::TextInput { text: t, .. } if !pasting => i_char = t.chars().next(),
                 _ => ()
             }
         }
 
-        if nm65.running                                           //if true, let's run 6502 code
+        let stop: Option<CpuError> = if nm65.running { nm65.breakpoint() } else { None }; //check for a breakpoint before each instruction
+
+        if let Some(stop) = stop
         {
-            let instruction_time = time::Instant::now();
-            let check: Result<u8, String> = nm65.execute(memory); //execute an instruction, check for errors
+            println!("{}", stop);
+            nm65.status_report();
+            nm65.running = false;
+            nm65.tracer.flush();                                   //so the log is complete while we sit in the monitor
 
-            if check.is_err()
-            {
-                println!("{}",check.unwrap_err());
-                nm65.status_report();
-                nm65.running = false;                                        //stop running if something goes wrong
+            print!(">");
+            let _ = stdout().flush();
+        }
+        else if nm65.running                                      //if true, let's run 6502 code
+        {
+            let instruction_time = time::Instant::now();
+            let check: Result<u32, CpuError> = nm65.execute(&mut apple.memory); //execute an instruction, check for errors
+            for warning in nm65.take_warnings() { println!("{}", warning) }
 
-                print!(">");
-                stdout().flush();
-            }
-            else                                                            //if the instruction executed OK...
+            match check
             {
-                let cycles_just_used: u8 = check.unwrap();                                          //count cycles used by the completed
-                if nm65.debug_text {println!("Instruction used {} cycles...", cycles_just_used)};   //instruction, add them to a running total
-                cycle_total += u64::from(cycles_just_used);
+                Err(e) =>
+                {
+                    println!("{}", e);
+                    nm65.status_report();
+                    nm65.running = false;                                        //stop running if something goes wrong
+                    nm65.tracer.flush();
 
-                if cycle_total > pia_refresh                                                    //should we update peripherals this frame?
+                    print!(">");
+                    let _ = stdout().flush();
+                }
+                Ok(cycles_just_used) =>                                         //if the instruction executed OK...
                 {
+                    cycle_total += u64::from(cycles_just_used);                                         //count cycles used by the completed instruction, add them to a running total
 
-                    if pasting && !printing
+                    if !nm65.cycle_accurate { apple.memory.tick(cycles_just_used) }          //run the peripherals' clocks alongside the CPU, cycle accurate mode already has
+
+                    if cycle_total > pia_refresh                                                    //should we update peripherals this frame?
                     {
-                        let p_next: Option<char> = pasted_chars.next();
-                        match p_next
+
+                        if pasting && !printing
                         {
-                            Some(c) => i_char = Some(c),
-                            None => pasting = false
+                            let p_next: Option<char> = pasted_chars.next();
+                            match p_next
+                            {
+                                Some(c) => i_char = Some(c),
+                                None => pasting = false
+                            }
                         }
-                    }
 
-                    cycle_total = 0;                                                                //reset count
-                    printing = terminal::pia(memory, &mut terminal_buf, &mut i_char);        //update the peripherals (keyboard, display)
-                }
+                        cycle_total = 0;                                                                //reset count
+                        printing = terminal::pia(&mut apple.pia.borrow_mut(), &mut terminal_buf, &mut i_char);        //update the peripherals (keyboard, display)
+                    }
 
-                //sleep for the amount of time dictated by cycles taken and the CPU speed
+                    //sleep for the amount of time dictated by cycles taken and the CPU speed
 
-                let mut wait_time = time::Duration::from_nanos(cycles_just_used as u64 * nm65.clock_time);
-                let spent_time = instruction_time.elapsed();
+                    let mut wait_time = time::Duration::from_nanos(cycles_just_used as u64 * nm65.clock_time);
+                    let spent_time = instruction_time.elapsed();
                 
-                if wait_time > spent_time
-                {
-                    wait_time -= spent_time; 
-                    spin_sleep::sleep(wait_time); 
-                }
+                    if wait_time > spent_time
+                    {
+                        wait_time -= spent_time; 
+                        spin_sleep::sleep(wait_time); 
+                    }
 
-                frame_time += spent_time + wait_time;
+                    frame_time += spent_time + wait_time;
 
-                if frame_time >= time::Duration::from_nanos(video_refresh)
-                {
-                    terminal::render_screen(&mut screen, &texture_creator, &mut terminal_buf, &font);
-                    frame_time = time::Duration::ZERO;
+                    if frame_time >= time::Duration::from_nanos(video_refresh)
+                    {
+                        terminal::render_screen(&mut screen, &texture_creator, &mut terminal_buf, &font);
+                        frame_time = time::Duration::ZERO;
+                    }
                 }
             }
         }
 
         else        //CPU is paused, drop into interactive monitor
         {   
-            let continue_loop: bool = nm65.debug_mode(memory);
+            #[cfg(unix)]
+            serial::pause_stdin(true);                              //the monitor has the console, not a stdio serial port
+
+            let continue_loop: bool = nm65.debug_mode(&mut apple.memory);
             if !continue_loop { return }
 
-            printing = terminal::pia(memory, &mut terminal_buf, &mut i_char);
+            #[cfg(unix)]
+            if nm65.running { serial::pause_stdin(false) }
+
+            printing = terminal::pia(&mut apple.pia.borrow_mut(), &mut terminal_buf, &mut i_char);
             terminal::render_screen(&mut screen, &texture_creator, &mut terminal_buf, &font);
         }
     }
@@ -0,0 +1,634 @@
+//Opcode table for the 6502. Everything the CPU, the cycle counting and the debugger need to know about an opcode lives here,
+//so the instruction set is described in one place instead of being spread across the executor.
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mnemonic
+{
+    ADC, AND, ASL, BCC, BCS, BEQ, BIT, BMI, BNE, BPL, BRK, BVC, BVS, CLC,
+    CLD, CLI, CLV, CMP, CPX, CPY, DEC, DEX, DEY, EOR, INC, INX, INY, JMP,
+    JSR, LDA, LDX, LDY, LSR, NOP, ORA, PHA, PHP, PLA, PLP, ROL, ROR, RTI,
+    RTS, SBC, SEC, SED, SEI, STA, STX, STY, TAX, TAY, TSX, TXA, TXS, TYA,
+
+    //undocumented NMOS instructions
+    ALR, ANC, ANE, ARR, DCP, ISC, JAM, LAS, LAX, LXA, RLA, RRA, SAX, SBX,
+    SHA, SHX, SHY, SLO, SRE, TAS,
+
+    //65C02 additions
+    BRA, PHX, PHY, PLX, PLY, STZ, TRB, TSB,
+    BBR, BBS, RMB, SMB,     //Rockwell and WDC bit instructions, the bit number is bits 4-6 of the opcode
+    STP, WAI,               //WDC only
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode
+{
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Indirect,       //JMP ($xxxx)
+    IndirectX,      //($xx,X)
+    IndirectY,      //($xx),Y
+    Relative,       //branches
+    ZeroPageIndirect,           //($xx), 65C02 only
+    AbsoluteIndexedIndirect,    //JMP ($xxxx,X), 65C02 only
+    ZeroPageRelative,           //BBR and BBS: a zero page address, then a branch offset
+}
+
+impl Mode
+{
+    pub const fn length(self) -> u8 //bytes taken up by an instruction in this mode, including the opcode
+    {
+        match self
+        {
+            Mode::Implied | Mode::Accumulator => 1,
+            Mode::Absolute | Mode::AbsoluteX | Mode::AbsoluteY | Mode::Indirect => 3,
+            Mode::AbsoluteIndexedIndirect | Mode::ZeroPageRelative => 3,
+            _ => 2
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy)]
+pub struct Opcode
+{
+    pub mnemonic: Mnemonic,
+    pub mode: Mode,
+    pub length: u8,
+    pub cycles: u8,             //base cycle count. Branches add one when taken and another when they land on a new page.
+    pub page_cross: bool,       //takes an extra cycle when the indexed address lands on a new page
+    pub undocumented: bool,
+}
+
+const fn op(mnemonic: Mnemonic, mode: Mode, cycles: u8, page_cross: bool) -> Opcode
+{
+    Opcode { mnemonic, mode, length: mode.length(), cycles, page_cross, undocumented: false }
+}
+
+const fn undoc(mnemonic: Mnemonic, mode: Mode, cycles: u8, page_cross: bool) -> Opcode
+{
+    Opcode { mnemonic, mode, length: mode.length(), cycles, page_cross, undocumented: true }
+}
+
+
+use Mnemonic::*;
+use Mode::*;
+
+pub static NMOS: [Opcode; 256] = //the NMOS 6502, indexed by opcode. JAM locks up the CPU, so it has no cycle count.
+[
+    op(BRK, Implied, 7, false),                     //00
+    op(ORA, IndirectX, 6, false),                   //01
+    undoc(JAM, Implied, 0, false),                  //02
+    undoc(SLO, IndirectX, 8, false),                //03
+    undoc(NOP, ZeroPage, 3, false),                 //04
+    op(ORA, ZeroPage, 3, false),                    //05
+    op(ASL, ZeroPage, 5, false),                    //06
+    undoc(SLO, ZeroPage, 5, false),                 //07
+    op(PHP, Implied, 3, false),                     //08
+    op(ORA, Immediate, 2, false),                   //09
+    op(ASL, Accumulator, 2, false),                 //0a
+    undoc(ANC, Immediate, 2, false),                //0b
+    undoc(NOP, Absolute, 4, false),                 //0c
+    op(ORA, Absolute, 4, false),                    //0d
+    op(ASL, Absolute, 6, false),                    //0e
+    undoc(SLO, Absolute, 6, false),                 //0f
+    op(BPL, Relative, 2, false),                    //10
+    op(ORA, IndirectY, 5, true),                    //11
+    undoc(JAM, Implied, 0, false),                  //12
+    undoc(SLO, IndirectY, 8, false),                //13
+    undoc(NOP, ZeroPageX, 4, false),                //14
+    op(ORA, ZeroPageX, 4, false),                   //15
+    op(ASL, ZeroPageX, 6, false),                   //16
+    undoc(SLO, ZeroPageX, 6, false),                //17
+    op(CLC, Implied, 2, false),                     //18
+    op(ORA, AbsoluteY, 4, true),                    //19
+    undoc(NOP, Implied, 2, false),                  //1a
+    undoc(SLO, AbsoluteY, 7, false),                //1b
+    undoc(NOP, AbsoluteX, 4, true),                 //1c
+    op(ORA, AbsoluteX, 4, true),                    //1d
+    op(ASL, AbsoluteX, 7, false),                   //1e
+    undoc(SLO, AbsoluteX, 7, false),                //1f
+    op(JSR, Absolute, 6, false),                    //20
+    op(AND, IndirectX, 6, false),                   //21
+    undoc(JAM, Implied, 0, false),                  //22
+    undoc(RLA, IndirectX, 8, false),                //23
+    op(BIT, ZeroPage, 3, false),                    //24
+    op(AND, ZeroPage, 3, false),                    //25
+    op(ROL, ZeroPage, 5, false),                    //26
+    undoc(RLA, ZeroPage, 5, false),                 //27
+    op(PLP, Implied, 4, false),                     //28
+    op(AND, Immediate, 2, false),                   //29
+    op(ROL, Accumulator, 2, false),                 //2a
+    undoc(ANC, Immediate, 2, false),                //2b
+    op(BIT, Absolute, 4, false),                    //2c
+    op(AND, Absolute, 4, false),                    //2d
+    op(ROL, Absolute, 6, false),                    //2e
+    undoc(RLA, Absolute, 6, false),                 //2f
+    op(BMI, Relative, 2, false),                    //30
+    op(AND, IndirectY, 5, true),                    //31
+    undoc(JAM, Implied, 0, false),                  //32
+    undoc(RLA, IndirectY, 8, false),                //33
+    undoc(NOP, ZeroPageX, 4, false),                //34
+    op(AND, ZeroPageX, 4, false),                   //35
+    op(ROL, ZeroPageX, 6, false),                   //36
+    undoc(RLA, ZeroPageX, 6, false),                //37
+    op(SEC, Implied, 2, false),                     //38
+    op(AND, AbsoluteY, 4, true),                    //39
+    undoc(NOP, Implied, 2, false),                  //3a
+    undoc(RLA, AbsoluteY, 7, false),                //3b
+    undoc(NOP, AbsoluteX, 4, true),                 //3c
+    op(AND, AbsoluteX, 4, true),                    //3d
+    op(ROL, AbsoluteX, 7, false),                   //3e
+    undoc(RLA, AbsoluteX, 7, false),                //3f
+    op(RTI, Implied, 6, false),                     //40
+    op(EOR, IndirectX, 6, false),                   //41
+    undoc(JAM, Implied, 0, false),                  //42
+    undoc(SRE, IndirectX, 8, false),                //43
+    undoc(NOP, ZeroPage, 3, false),                 //44
+    op(EOR, ZeroPage, 3, false),                    //45
+    op(LSR, ZeroPage, 5, false),                    //46
+    undoc(SRE, ZeroPage, 5, false),                 //47
+    op(PHA, Implied, 3, false),                     //48
+    op(EOR, Immediate, 2, false),                   //49
+    op(LSR, Accumulator, 2, false),                 //4a
+    undoc(ALR, Immediate, 2, false),                //4b
+    op(JMP, Absolute, 3, false),                    //4c
+    op(EOR, Absolute, 4, false),                    //4d
+    op(LSR, Absolute, 6, false),                    //4e
+    undoc(SRE, Absolute, 6, false),                 //4f
+    op(BVC, Relative, 2, false),                    //50
+    op(EOR, IndirectY, 5, true),                    //51
+    undoc(JAM, Implied, 0, false),                  //52
+    undoc(SRE, IndirectY, 8, false),                //53
+    undoc(NOP, ZeroPageX, 4, false),                //54
+    op(EOR, ZeroPageX, 4, false),                   //55
+    op(LSR, ZeroPageX, 6, false),                   //56
+    undoc(SRE, ZeroPageX, 6, false),                //57
+    op(CLI, Implied, 2, false),                     //58
+    op(EOR, AbsoluteY, 4, true),                    //59
+    undoc(NOP, Implied, 2, false),                  //5a
+    undoc(SRE, AbsoluteY, 7, false),                //5b
+    undoc(NOP, AbsoluteX, 4, true),                 //5c
+    op(EOR, AbsoluteX, 4, true),                    //5d
+    op(LSR, AbsoluteX, 7, false),                   //5e
+    undoc(SRE, AbsoluteX, 7, false),                //5f
+    op(RTS, Implied, 6, false),                     //60
+    op(ADC, IndirectX, 6, false),                   //61
+    undoc(JAM, Implied, 0, false),                  //62
+    undoc(RRA, IndirectX, 8, false),                //63
+    undoc(NOP, ZeroPage, 3, false),                 //64
+    op(ADC, ZeroPage, 3, false),                    //65
+    op(ROR, ZeroPage, 5, false),                    //66
+    undoc(RRA, ZeroPage, 5, false),                 //67
+    op(PLA, Implied, 4, false),                     //68
+    op(ADC, Immediate, 2, false),                   //69
+    op(ROR, Accumulator, 2, false),                 //6a
+    undoc(ARR, Immediate, 2, false),                //6b
+    op(JMP, Indirect, 5, false),                    //6c
+    op(ADC, Absolute, 4, false),                    //6d
+    op(ROR, Absolute, 6, false),                    //6e
+    undoc(RRA, Absolute, 6, false),                 //6f
+    op(BVS, Relative, 2, false),                    //70
+    op(ADC, IndirectY, 5, true),                    //71
+    undoc(JAM, Implied, 0, false),                  //72
+    undoc(RRA, IndirectY, 8, false),                //73
+    undoc(NOP, ZeroPageX, 4, false),                //74
+    op(ADC, ZeroPageX, 4, false),                   //75
+    op(ROR, ZeroPageX, 6, false),                   //76
+    undoc(RRA, ZeroPageX, 6, false),                //77
+    op(SEI, Implied, 2, false),                     //78
+    op(ADC, AbsoluteY, 4, true),                    //79
+    undoc(NOP, Implied, 2, false),                  //7a
+    undoc(RRA, AbsoluteY, 7, false),                //7b
+    undoc(NOP, AbsoluteX, 4, true),                 //7c
+    op(ADC, AbsoluteX, 4, true),                    //7d
+    op(ROR, AbsoluteX, 7, false),                   //7e
+    undoc(RRA, AbsoluteX, 7, false),                //7f
+    undoc(NOP, Immediate, 2, false),                //80
+    op(STA, IndirectX, 6, false),                   //81
+    undoc(NOP, Immediate, 2, false),                //82
+    undoc(SAX, IndirectX, 6, false),                //83
+    op(STY, ZeroPage, 3, false),                    //84
+    op(STA, ZeroPage, 3, false),                    //85
+    op(STX, ZeroPage, 3, false),                    //86
+    undoc(SAX, ZeroPage, 3, false),                 //87
+    op(DEY, Implied, 2, false),                     //88
+    undoc(NOP, Immediate, 2, false),                //89
+    op(TXA, Implied, 2, false),                     //8a
+    undoc(ANE, Immediate, 2, false),                //8b
+    op(STY, Absolute, 4, false),                    //8c
+    op(STA, Absolute, 4, false),                    //8d
+    op(STX, Absolute, 4, false),                    //8e
+    undoc(SAX, Absolute, 4, false),                 //8f
+    op(BCC, Relative, 2, false),                    //90
+    op(STA, IndirectY, 6, false),                   //91
+    undoc(JAM, Implied, 0, false),                  //92
+    undoc(SHA, IndirectY, 6, false),                //93
+    op(STY, ZeroPageX, 4, false),                   //94
+    op(STA, ZeroPageX, 4, false),                   //95
+    op(STX, ZeroPageY, 4, false),                   //96
+    undoc(SAX, ZeroPageY, 4, false),                //97
+    op(TYA, Implied, 2, false),                     //98
+    op(STA, AbsoluteY, 5, false),                   //99
+    op(TXS, Implied, 2, false),                     //9a
+    undoc(TAS, AbsoluteY, 5, false),                //9b
+    undoc(SHY, AbsoluteX, 5, false),                //9c
+    op(STA, AbsoluteX, 5, false),                   //9d
+    undoc(SHX, AbsoluteY, 5, false),                //9e
+    undoc(SHA, AbsoluteY, 5, false),                //9f
+    op(LDY, Immediate, 2, false),                   //a0
+    op(LDA, IndirectX, 6, false),                   //a1
+    op(LDX, Immediate, 2, false),                   //a2
+    undoc(LAX, IndirectX, 6, false),                //a3
+    op(LDY, ZeroPage, 3, false),                    //a4
+    op(LDA, ZeroPage, 3, false),                    //a5
+    op(LDX, ZeroPage, 3, false),                    //a6
+    undoc(LAX, ZeroPage, 3, false),                 //a7
+    op(TAY, Implied, 2, false),                     //a8
+    op(LDA, Immediate, 2, false),                   //a9
+    op(TAX, Implied, 2, false),                     //aa
+    undoc(LXA, Immediate, 2, false),                //ab
+    op(LDY, Absolute, 4, false),                    //ac
+    op(LDA, Absolute, 4, false),                    //ad
+    op(LDX, Absolute, 4, false),                    //ae
+    undoc(LAX, Absolute, 4, false),                 //af
+    op(BCS, Relative, 2, false),                    //b0
+    op(LDA, IndirectY, 5, true),                    //b1
+    undoc(JAM, Implied, 0, false),                  //b2
+    undoc(LAX, IndirectY, 5, true),                 //b3
+    op(LDY, ZeroPageX, 4, false),                   //b4
+    op(LDA, ZeroPageX, 4, false),                   //b5
+    op(LDX, ZeroPageY, 4, false),                   //b6
+    undoc(LAX, ZeroPageY, 4, false),                //b7
+    op(CLV, Implied, 2, false),                     //b8
+    op(LDA, AbsoluteY, 4, true),                    //b9
+    op(TSX, Implied, 2, false),                     //ba
+    undoc(LAS, AbsoluteY, 4, true),                 //bb
+    op(LDY, AbsoluteX, 4, true),                    //bc
+    op(LDA, AbsoluteX, 4, true),                    //bd
+    op(LDX, AbsoluteY, 4, true),                    //be
+    undoc(LAX, AbsoluteY, 4, true),                 //bf
+    op(CPY, Immediate, 2, false),                   //c0
+    op(CMP, IndirectX, 6, false),                   //c1
+    undoc(NOP, Immediate, 2, false),                //c2
+    undoc(DCP, IndirectX, 8, false),                //c3
+    op(CPY, ZeroPage, 3, false),                    //c4
+    op(CMP, ZeroPage, 3, false),                    //c5
+    op(DEC, ZeroPage, 5, false),                    //c6
+    undoc(DCP, ZeroPage, 5, false),                 //c7
+    op(INY, Implied, 2, false),                     //c8
+    op(CMP, Immediate, 2, false),                   //c9
+    op(DEX, Implied, 2, false),                     //ca
+    undoc(SBX, Immediate, 2, false),                //cb
+    op(CPY, Absolute, 4, false),                    //cc
+    op(CMP, Absolute, 4, false),                    //cd
+    op(DEC, Absolute, 6, false),                    //ce
+    undoc(DCP, Absolute, 6, false),                 //cf
+    op(BNE, Relative, 2, false),                    //d0
+    op(CMP, IndirectY, 5, true),                    //d1
+    undoc(JAM, Implied, 0, false),                  //d2
+    undoc(DCP, IndirectY, 8, false),                //d3
+    undoc(NOP, ZeroPageX, 4, false),                //d4
+    op(CMP, ZeroPageX, 4, false),                   //d5
+    op(DEC, ZeroPageX, 6, false),                   //d6
+    undoc(DCP, ZeroPageX, 6, false),                //d7
+    op(CLD, Implied, 2, false),                     //d8
+    op(CMP, AbsoluteY, 4, true),                    //d9
+    undoc(NOP, Implied, 2, false),                  //da
+    undoc(DCP, AbsoluteY, 7, false),                //db
+    undoc(NOP, AbsoluteX, 4, true),                 //dc
+    op(CMP, AbsoluteX, 4, true),                    //dd
+    op(DEC, AbsoluteX, 7, false),                   //de
+    undoc(DCP, AbsoluteX, 7, false),                //df
+    op(CPX, Immediate, 2, false),                   //e0
+    op(SBC, IndirectX, 6, false),                   //e1
+    undoc(NOP, Immediate, 2, false),                //e2
+    undoc(ISC, IndirectX, 8, false),                //e3
+    op(CPX, ZeroPage, 3, false),                    //e4
+    op(SBC, ZeroPage, 3, false),                    //e5
+    op(INC, ZeroPage, 5, false),                    //e6
+    undoc(ISC, ZeroPage, 5, false),                 //e7
+    op(INX, Implied, 2, false),                     //e8
+    op(SBC, Immediate, 2, false),                   //e9
+    op(NOP, Implied, 2, false),                     //ea
+    undoc(SBC, Immediate, 2, false),                //eb
+    op(CPX, Absolute, 4, false),                    //ec
+    op(SBC, Absolute, 4, false),                    //ed
+    op(INC, Absolute, 6, false),                    //ee
+    undoc(ISC, Absolute, 6, false),                 //ef
+    op(BEQ, Relative, 2, false),                    //f0
+    op(SBC, IndirectY, 5, true),                    //f1
+    undoc(JAM, Implied, 0, false),                  //f2
+    undoc(ISC, IndirectY, 8, false),                //f3
+    undoc(NOP, ZeroPageX, 4, false),                //f4
+    op(SBC, ZeroPageX, 4, false),                   //f5
+    op(INC, ZeroPageX, 6, false),                   //f6
+    undoc(ISC, ZeroPageX, 6, false),                //f7
+    op(SED, Implied, 2, false),                     //f8
+    op(SBC, AbsoluteY, 4, true),                    //f9
+    undoc(NOP, Implied, 2, false),                  //fa
+    undoc(ISC, AbsoluteY, 7, false),                //fb
+    undoc(NOP, AbsoluteX, 4, true),                 //fc
+    op(SBC, AbsoluteX, 4, true),                    //fd
+    op(INC, AbsoluteX, 7, false),                   //fe
+    undoc(ISC, AbsoluteX, 7, false),                //ff
+];
+
+
+pub static CMOS: [Opcode; 256] = //the original 65C02 without the bit instructions. Every unused opcode is a NOP of some length.
+[
+    op(BRK, Implied, 7, false),                     //00
+    op(ORA, IndirectX, 6, false),                   //01
+    undoc(NOP, Immediate, 2, false),                //02
+    undoc(NOP, Implied, 1, false),                  //03
+    op(TSB, ZeroPage, 5, false),                    //04
+    op(ORA, ZeroPage, 3, false),                    //05
+    op(ASL, ZeroPage, 5, false),                    //06
+    undoc(NOP, Implied, 1, false),                  //07
+    op(PHP, Implied, 3, false),                     //08
+    op(ORA, Immediate, 2, false),                   //09
+    op(ASL, Accumulator, 2, false),                 //0a
+    undoc(NOP, Implied, 1, false),                  //0b
+    op(TSB, Absolute, 6, false),                    //0c
+    op(ORA, Absolute, 4, false),                    //0d
+    op(ASL, Absolute, 6, false),                    //0e
+    undoc(NOP, Implied, 1, false),                  //0f
+    op(BPL, Relative, 2, false),                    //10
+    op(ORA, IndirectY, 5, true),                    //11
+    op(ORA, ZeroPageIndirect, 5, false),            //12
+    undoc(NOP, Implied, 1, false),                  //13
+    op(TRB, ZeroPage, 5, false),                    //14
+    op(ORA, ZeroPageX, 4, false),                   //15
+    op(ASL, ZeroPageX, 6, false),                   //16
+    undoc(NOP, Implied, 1, false),                  //17
+    op(CLC, Implied, 2, false),                     //18
+    op(ORA, AbsoluteY, 4, true),                    //19
+    op(INC, Accumulator, 2, false),                 //1a
+    undoc(NOP, Implied, 1, false),                  //1b
+    op(TRB, Absolute, 6, false),                    //1c
+    op(ORA, AbsoluteX, 4, true),                    //1d
+    op(ASL, AbsoluteX, 6, true),                    //1e
+    undoc(NOP, Implied, 1, false),                  //1f
+    op(JSR, Absolute, 6, false),                    //20
+    op(AND, IndirectX, 6, false),                   //21
+    undoc(NOP, Immediate, 2, false),                //22
+    undoc(NOP, Implied, 1, false),                  //23
+    op(BIT, ZeroPage, 3, false),                    //24
+    op(AND, ZeroPage, 3, false),                    //25
+    op(ROL, ZeroPage, 5, false),                    //26
+    undoc(NOP, Implied, 1, false),                  //27
+    op(PLP, Implied, 4, false),                     //28
+    op(AND, Immediate, 2, false),                   //29
+    op(ROL, Accumulator, 2, false),                 //2a
+    undoc(NOP, Implied, 1, false),                  //2b
+    op(BIT, Absolute, 4, false),                    //2c
+    op(AND, Absolute, 4, false),                    //2d
+    op(ROL, Absolute, 6, false),                    //2e
+    undoc(NOP, Implied, 1, false),                  //2f
+    op(BMI, Relative, 2, false),                    //30
+    op(AND, IndirectY, 5, true),                    //31
+    op(AND, ZeroPageIndirect, 5, false),            //32
+    undoc(NOP, Implied, 1, false),                  //33
+    op(BIT, ZeroPageX, 4, false),                   //34
+    op(AND, ZeroPageX, 4, false),                   //35
+    op(ROL, ZeroPageX, 6, false),                   //36
+    undoc(NOP, Implied, 1, false),                  //37
+    op(SEC, Implied, 2, false),                     //38
+    op(AND, AbsoluteY, 4, true),                    //39
+    op(DEC, Accumulator, 2, false),                 //3a
+    undoc(NOP, Implied, 1, false),                  //3b
+    op(BIT, AbsoluteX, 4, true),                    //3c
+    op(AND, AbsoluteX, 4, true),                    //3d
+    op(ROL, AbsoluteX, 6, true),                    //3e
+    undoc(NOP, Implied, 1, false),                  //3f
+    op(RTI, Implied, 6, false),                     //40
+    op(EOR, IndirectX, 6, false),                   //41
+    undoc(NOP, Immediate, 2, false),                //42
+    undoc(NOP, Implied, 1, false),                  //43
+    undoc(NOP, ZeroPage, 3, false),                 //44
+    op(EOR, ZeroPage, 3, false),                    //45
+    op(LSR, ZeroPage, 5, false),                    //46
+    undoc(NOP, Implied, 1, false),                  //47
+    op(PHA, Implied, 3, false),                     //48
+    op(EOR, Immediate, 2, false),                   //49
+    op(LSR, Accumulator, 2, false),                 //4a
+    undoc(NOP, Implied, 1, false),                  //4b
+    op(JMP, Absolute, 3, false),                    //4c
+    op(EOR, Absolute, 4, false),                    //4d
+    op(LSR, Absolute, 6, false),                    //4e
+    undoc(NOP, Implied, 1, false),                  //4f
+    op(BVC, Relative, 2, false),                    //50
+    op(EOR, IndirectY, 5, true),                    //51
+    op(EOR, ZeroPageIndirect, 5, false),            //52
+    undoc(NOP, Implied, 1, false),                  //53
+    undoc(NOP, ZeroPageX, 4, false),                //54
+    op(EOR, ZeroPageX, 4, false),                   //55
+    op(LSR, ZeroPageX, 6, false),                   //56
+    undoc(NOP, Implied, 1, false),                  //57
+    op(CLI, Implied, 2, false),                     //58
+    op(EOR, AbsoluteY, 4, true),                    //59
+    op(PHY, Implied, 3, false),                     //5a
+    undoc(NOP, Implied, 1, false),                  //5b
+    undoc(NOP, Absolute, 8, false),                 //5c
+    op(EOR, AbsoluteX, 4, true),                    //5d
+    op(LSR, AbsoluteX, 6, true),                    //5e
+    undoc(NOP, Implied, 1, false),                  //5f
+    op(RTS, Implied, 6, false),                     //60
+    op(ADC, IndirectX, 6, false),                   //61
+    undoc(NOP, Immediate, 2, false),                //62
+    undoc(NOP, Implied, 1, false),                  //63
+    op(STZ, ZeroPage, 3, false),                    //64
+    op(ADC, ZeroPage, 3, false),                    //65
+    op(ROR, ZeroPage, 5, false),                    //66
+    undoc(NOP, Implied, 1, false),                  //67
+    op(PLA, Implied, 4, false),                     //68
+    op(ADC, Immediate, 2, false),                   //69
+    op(ROR, Accumulator, 2, false),                 //6a
+    undoc(NOP, Implied, 1, false),                  //6b
+    op(JMP, Indirect, 6, false),                    //6c
+    op(ADC, Absolute, 4, false),                    //6d
+    op(ROR, Absolute, 6, false),                    //6e
+    undoc(NOP, Implied, 1, false),                  //6f
+    op(BVS, Relative, 2, false),                    //70
+    op(ADC, IndirectY, 5, true),                    //71
+    op(ADC, ZeroPageIndirect, 5, false),            //72
+    undoc(NOP, Implied, 1, false),                  //73
+    op(STZ, ZeroPageX, 4, false),                   //74
+    op(ADC, ZeroPageX, 4, false),                   //75
+    op(ROR, ZeroPageX, 6, false),                   //76
+    undoc(NOP, Implied, 1, false),                  //77
+    op(SEI, Implied, 2, false),                     //78
+    op(ADC, AbsoluteY, 4, true),                    //79
+    op(PLY, Implied, 4, false),                     //7a
+    undoc(NOP, Implied, 1, false),                  //7b
+    op(JMP, AbsoluteIndexedIndirect, 6, false),     //7c
+    op(ADC, AbsoluteX, 4, true),                    //7d
+    op(ROR, AbsoluteX, 6, true),                    //7e
+    undoc(NOP, Implied, 1, false),                  //7f
+    op(BRA, Relative, 2, false),                    //80
+    op(STA, IndirectX, 6, false),                   //81
+    undoc(NOP, Immediate, 2, false),                //82
+    undoc(NOP, Implied, 1, false),                  //83
+    op(STY, ZeroPage, 3, false),                    //84
+    op(STA, ZeroPage, 3, false),                    //85
+    op(STX, ZeroPage, 3, false),                    //86
+    undoc(NOP, Implied, 1, false),                  //87
+    op(DEY, Implied, 2, false),                     //88
+    op(BIT, Immediate, 2, false),                   //89
+    op(TXA, Implied, 2, false),                     //8a
+    undoc(NOP, Implied, 1, false),                  //8b
+    op(STY, Absolute, 4, false),                    //8c
+    op(STA, Absolute, 4, false),                    //8d
+    op(STX, Absolute, 4, false),                    //8e
+    undoc(NOP, Implied, 1, false),                  //8f
+    op(BCC, Relative, 2, false),                    //90
+    op(STA, IndirectY, 6, false),                   //91
+    op(STA, ZeroPageIndirect, 5, false),            //92
+    undoc(NOP, Implied, 1, false),                  //93
+    op(STY, ZeroPageX, 4, false),                   //94
+    op(STA, ZeroPageX, 4, false),                   //95
+    op(STX, ZeroPageY, 4, false),                   //96
+    undoc(NOP, Implied, 1, false),                  //97
+    op(TYA, Implied, 2, false),                     //98
+    op(STA, AbsoluteY, 5, false),                   //99
+    op(TXS, Implied, 2, false),                     //9a
+    undoc(NOP, Implied, 1, false),                  //9b
+    op(STZ, Absolute, 4, false),                    //9c
+    op(STA, AbsoluteX, 5, false),                   //9d
+    op(STZ, AbsoluteX, 5, false),                   //9e
+    undoc(NOP, Implied, 1, false),                  //9f
+    op(LDY, Immediate, 2, false),                   //a0
+    op(LDA, IndirectX, 6, false),                   //a1
+    op(LDX, Immediate, 2, false),                   //a2
+    undoc(NOP, Implied, 1, false),                  //a3
+    op(LDY, ZeroPage, 3, false),                    //a4
+    op(LDA, ZeroPage, 3, false),                    //a5
+    op(LDX, ZeroPage, 3, false),                    //a6
+    undoc(NOP, Implied, 1, false),                  //a7
+    op(TAY, Implied, 2, false),                     //a8
+    op(LDA, Immediate, 2, false),                   //a9
+    op(TAX, Implied, 2, false),                     //aa
+    undoc(NOP, Implied, 1, false),                  //ab
+    op(LDY, Absolute, 4, false),                    //ac
+    op(LDA, Absolute, 4, false),                    //ad
+    op(LDX, Absolute, 4, false),                    //ae
+    undoc(NOP, Implied, 1, false),                  //af
+    op(BCS, Relative, 2, false),                    //b0
+    op(LDA, IndirectY, 5, true),                    //b1
+    op(LDA, ZeroPageIndirect, 5, false),            //b2
+    undoc(NOP, Implied, 1, false),                  //b3
+    op(LDY, ZeroPageX, 4, false),                   //b4
+    op(LDA, ZeroPageX, 4, false),                   //b5
+    op(LDX, ZeroPageY, 4, false),                   //b6
+    undoc(NOP, Implied, 1, false),                  //b7
+    op(CLV, Implied, 2, false),                     //b8
+    op(LDA, AbsoluteY, 4, true),                    //b9
+    op(TSX, Implied, 2, false),                     //ba
+    undoc(NOP, Implied, 1, false),                  //bb
+    op(LDY, AbsoluteX, 4, true),                    //bc
+    op(LDA, AbsoluteX, 4, true),                    //bd
+    op(LDX, AbsoluteY, 4, true),                    //be
+    undoc(NOP, Implied, 1, false),                  //bf
+    op(CPY, Immediate, 2, false),                   //c0
+    op(CMP, IndirectX, 6, false),                   //c1
+    undoc(NOP, Immediate, 2, false),                //c2
+    undoc(NOP, Implied, 1, false),                  //c3
+    op(CPY, ZeroPage, 3, false),                    //c4
+    op(CMP, ZeroPage, 3, false),                    //c5
+    op(DEC, ZeroPage, 5, false),                    //c6
+    undoc(NOP, Implied, 1, false),                  //c7
+    op(INY, Implied, 2, false),                     //c8
+    op(CMP, Immediate, 2, false),                   //c9
+    op(DEX, Implied, 2, false),                     //ca
+    undoc(NOP, Implied, 1, false),                  //cb
+    op(CPY, Absolute, 4, false),                    //cc
+    op(CMP, Absolute, 4, false),                    //cd
+    op(DEC, Absolute, 6, false),                    //ce
+    undoc(NOP, Implied, 1, false),                  //cf
+    op(BNE, Relative, 2, false),                    //d0
+    op(CMP, IndirectY, 5, true),                    //d1
+    op(CMP, ZeroPageIndirect, 5, false),            //d2
+    undoc(NOP, Implied, 1, false),                  //d3
+    undoc(NOP, ZeroPageX, 4, false),                //d4
+    op(CMP, ZeroPageX, 4, false),                   //d5
+    op(DEC, ZeroPageX, 6, false),                   //d6
+    undoc(NOP, Implied, 1, false),                  //d7
+    op(CLD, Implied, 2, false),                     //d8
+    op(CMP, AbsoluteY, 4, true),                    //d9
+    op(PHX, Implied, 3, false),                     //da
+    undoc(NOP, Implied, 1, false),                  //db
+    undoc(NOP, Absolute, 4, false),                 //dc
+    op(CMP, AbsoluteX, 4, true),                    //dd
+    op(DEC, AbsoluteX, 7, false),                   //de
+    undoc(NOP, Implied, 1, false),                  //df
+    op(CPX, Immediate, 2, false),                   //e0
+    op(SBC, IndirectX, 6, false),                   //e1
+    undoc(NOP, Immediate, 2, false),                //e2
+    undoc(NOP, Implied, 1, false),                  //e3
+    op(CPX, ZeroPage, 3, false),                    //e4
+    op(SBC, ZeroPage, 3, false),                    //e5
+    op(INC, ZeroPage, 5, false),                    //e6
+    undoc(NOP, Implied, 1, false),                  //e7
+    op(INX, Implied, 2, false),                     //e8
+    op(SBC, Immediate, 2, false),                   //e9
+    op(NOP, Implied, 2, false),                     //ea
+    undoc(NOP, Implied, 1, false),                  //eb
+    op(CPX, Absolute, 4, false),                    //ec
+    op(SBC, Absolute, 4, false),                    //ed
+    op(INC, Absolute, 6, false),                    //ee
+    undoc(NOP, Implied, 1, false),                  //ef
+    op(BEQ, Relative, 2, false),                    //f0
+    op(SBC, IndirectY, 5, true),                    //f1
+    op(SBC, ZeroPageIndirect, 5, false),            //f2
+    undoc(NOP, Implied, 1, false),                  //f3
+    undoc(NOP, ZeroPageX, 4, false),                //f4
+    op(SBC, ZeroPageX, 4, false),                   //f5
+    op(INC, ZeroPageX, 6, false),                   //f6
+    undoc(NOP, Implied, 1, false),                  //f7
+    op(SED, Implied, 2, false),                     //f8
+    op(SBC, AbsoluteY, 4, true),                    //f9
+    op(PLX, Implied, 4, false),                     //fa
+    undoc(NOP, Implied, 1, false),                  //fb
+    undoc(NOP, Absolute, 4, false),                 //fc
+    op(SBC, AbsoluteX, 4, true),                    //fd
+    op(INC, AbsoluteX, 7, false),                   //fe
+    undoc(NOP, Implied, 1, false),                  //ff
+];
+
+pub static ROCKWELL: [Opcode; 256] = with_bit_instructions(CMOS);
+
+pub static WDC: [Opcode; 256] = with_wait_and_stop(ROCKWELL);
+
+
+const fn with_bit_instructions(base: [Opcode; 256]) -> [Opcode; 256] //the R65C02 fills columns 7 and F with RMB/SMB and BBR/BBS
+{
+    let mut table: [Opcode; 256] = base;
+    let mut bit: usize = 0;
+
+    while bit < 8
+    {
+        table[0x07 + (bit << 4)] = op(RMB, ZeroPage, 5, false);
+        table[0x87 + (bit << 4)] = op(SMB, ZeroPage, 5, false);
+        table[0x0f + (bit << 4)] = op(BBR, ZeroPageRelative, 5, false);
+        table[0x8f + (bit << 4)] = op(BBS, ZeroPageRelative, 5, false);
+        bit += 1;
+    }
+
+    return table
+}
+
+const fn with_wait_and_stop(base: [Opcode; 256]) -> [Opcode; 256] //the W65C02S adds WAI and STP on top of the Rockwell set
+{
+    let mut table: [Opcode; 256] = base;
+    table[0xcb] = op(WAI, Implied, 3, false);
+    table[0xdb] = op(STP, Implied, 3, false);
+    return table
+}
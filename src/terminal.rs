@@ -1,9 +1,8 @@
 extern crate sdl2;
 
-use crate::bus::Segment;
+use crate::apple1::ApplePia;
 
 use sdl2::pixels::Color;
-use sdl2::event::{Event, WindowEvent};
 use sdl2::rect::Rect;
 use sdl2::render::{Canvas, TextureCreator};
 use sdl2::ttf::Font;
@@ -11,21 +10,13 @@ use sdl2::video::{Window, WindowContext};
 
 use std::collections::VecDeque;
 
-const KBD: usize = 0;
-const KBDCR: usize = 1;
-const DSP: usize = 2;
-const DSPCR: usize = 3;
-
-const IN: usize = 2;
-const OUT: usize = 3;
-
-pub fn pia(memory: &mut [Segment], buf: &mut VecDeque<u8>, input: &mut Option<char>) -> bool
+pub fn pia(pia: &mut ApplePia, buf: &mut VecDeque<u8>, input: &mut Option<char>) -> bool
 {
     let mut printed: bool = false;
 
-    if memory[IN].data[DSP] > 0x7f   //is bit 7 of DSP set?
+    if let Some(dsp) = pia.display_pending()   //has the 6502 handed the display a character?
     {
-        let mut out_char: u8 = memory[OUT].data[DSP] & !0x80;     //get byte and convert to valid ASCII
+        let mut out_char: u8 = dsp & !0x80;     //get byte and convert to valid ASCII
 
         if out_char != 0x0                                      //make sure we're not passing a null character to the buffer
         {
@@ -38,18 +29,14 @@ pub fn pia(memory: &mut [Segment], buf: &mut VecDeque<u8>, input: &mut Option<ch
             scroll(buf);
         }
 
-        memory[IN].data[DSP] &= !0x80;          //clear bit 7 to let woz monitor know we got the byte
+        pia.display_done();          //strobe CB1 to let woz monitor know we got the byte
 
         printed = true;
     }
 
-    if input.is_some() {
-        memory[IN].data[KBD] = input.unwrap().to_ascii_uppercase() as u8 | 0x80;
+    if let Some(c) = input {
+        pia.key_pressed(c.to_ascii_uppercase() as u8);
         *input = None;
-
-        memory[IN].data[KBDCR] |= 0x80;
-
-        //println!("in: {} {}", memory[IN].data[KBD], memory[IN].data[KBD] as char);
     }
 
     return printed;
@@ -61,9 +48,9 @@ pub fn scroll(buf: &mut VecDeque<u8>) //handle scrolling the display if the buff
     let mut rows_used = 0;
     let mut characters_in_row = 0;
 
-    for i in 0 .. buf.len() //start by measuring how many lines of text have been used, by going through the buffer looking for wraps and newlines
+    for c in buf.iter() //start by measuring how many lines of text have been used, by going through the buffer looking for wraps and newlines
     {
-        if buf[i] == 0xa || characters_in_row >= 39
+        if *c == 0xa || characters_in_row >= 39
         {
             rows_used += 1;
             characters_in_row = 0
@@ -83,10 +70,7 @@ pub fn scroll(buf: &mut VecDeque<u8>) //handle scrolling the display if the buff
             let removed_char = buf.pop_front();
             characters_in_row += 1;
 
-            if removed_char.is_some()
-            {
-                if removed_char.unwrap() == 0xa { break; }
-            }
+            if removed_char == Some(0xa) { break; }
         }
     }
 }
@@ -100,11 +84,11 @@ pub fn render_screen(screen: &mut Canvas<Window>, texture_creator: &TextureCreat
     
     let text = font.render(str).blended_wrapped(Color::RGB(255, 255, 255), screen.viewport().width());
     
-    if text.is_ok()
+    if let Ok(rendered) = text
     {
-        let text_texture = text.unwrap().as_texture(&texture_creator).unwrap();
+        let text_texture = rendered.as_texture(texture_creator).unwrap();
         let text_dimensions = text_texture.query();
-        screen.copy(&text_texture, None, Some(Rect::new(0,0,text_dimensions.width,text_dimensions.height)));
+        let _ = screen.copy(&text_texture, None, Some(Rect::new(0,0,text_dimensions.width,text_dimensions.height)));
     }
 
     screen.present();
@@ -0,0 +1,150 @@
+//Instruction trace: one line per instruction with the registers as they were before it ran, laid out like the nestest
+//log most emulators can produce, so traces can be diffed against each other. The last few instructions are kept in a
+//ring buffer for the monitor to show after something goes wrong, and the whole trace can go to a file as it runs.
+
+use crate::bus::Bus;
+use crate::cpu::Variant;
+use crate::disasm;
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraceFormat
+{
+    Nestest,        //C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD CYC:7
+    Csv,            //pc,bytes,"instruction",a,x,y,p,sp,cycles, the instruction quoted since indexed operands have commas in
+}
+
+impl TraceFormat
+{
+    pub fn from_name(name: &str) -> Option<TraceFormat> //the names accepted by the trace_format setting
+    {
+        match name.trim().to_lowercase().as_str()
+        {
+            "nestest" => Some(TraceFormat::Nestest),
+            "csv" => Some(TraceFormat::Csv),
+            _ => None
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceEntry //the state of the CPU just before an instruction
+{
+    pub pc: u16,
+    pub bytes: [u8; 3],         //the opcode and up to two operand bytes, as they were when it ran
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub p: u8,
+    pub sp: u8,
+    pub cycles: u64,            //cycles run before this instruction
+    pub variant: Variant,
+}
+
+struct Snapshot<'a> //the bytes of a traced instruction, posing as a bus for the disassembler
+{
+    addr: u16,
+    bytes: &'a [u8; 3],
+}
+
+impl Bus for Snapshot<'_>
+{
+    fn read(&mut self, addr: u16) -> u8 { self.peek(addr) }
+    fn write(&mut self, _addr: u16, _data: u8) {}
+    fn peek(&self, addr: u16) -> u8 { self.bytes.get(addr.wrapping_sub(self.addr) as usize).copied().unwrap_or(0) }
+}
+
+impl TraceEntry
+{
+    pub fn format(&self, format: TraceFormat) -> String
+    {
+        let line: disasm::Line = disasm::disassemble_one(&Snapshot { addr: self.pc, bytes: &self.bytes }, self.variant, self.pc);
+        let hex: Vec<String> = line.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+
+        match format
+        {
+            TraceFormat::Nestest =>
+            {
+                let marker: char = if self.variant.opcodes()[self.bytes[0] as usize].undocumented { '*' } else { ' ' };   //nestest flags unofficial opcodes
+                return format!("{:04X}  {:<8} {}{:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{}",
+                               self.pc, hex.join(" "), marker, line.text, self.a, self.x, self.y, self.p, self.sp, self.cycles)
+            }
+            TraceFormat::Csv =>
+            {
+                return format!("{:04X},{},\"{}\",{:02X},{:02X},{:02X},{:02X},{:02X},{}",
+                               self.pc, hex.join(" "), line.text, self.a, self.x, self.y, self.p, self.sp, self.cycles)
+            }
+        }
+    }
+}
+
+
+pub struct Tracer
+{
+    pub format: TraceFormat,
+    pub cycles: u64,                        //cycles run so far, for the CYC column
+    capacity: usize,
+    ring: VecDeque<TraceEntry>,
+    output: Option<Box<dyn Write>>,
+}
+
+impl Tracer
+{
+    pub fn new(capacity: usize) -> Tracer //keeps the last capacity instructions, and writes nowhere until told to
+    {
+        Tracer { format: TraceFormat::Nestest, cycles: 0, capacity, ring: VecDeque::with_capacity(capacity), output: None }
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize)
+    {
+        self.capacity = capacity;
+        while self.ring.len() > capacity { self.ring.pop_front(); }
+    }
+
+    pub fn log_to(&mut self, path: &Path) -> io::Result<()> //write every instruction to a file from now on
+    {
+        let file: File = File::create(path)?;
+        self.output = Some(Box::new(BufWriter::new(file)));
+        return Ok(())
+    }
+
+    pub fn log_to_writer(&mut self, output: Box<dyn Write>)
+    {
+        self.output = Some(output);
+    }
+
+    pub fn active(&self) -> bool //is there anywhere for entries to go?
+    {
+        return self.capacity > 0 || self.output.is_some()
+    }
+
+    pub fn record(&mut self, entry: TraceEntry) //add an entry to the ring buffer and the log file
+    {
+        if let Some(output) = self.output.as_mut()
+        {
+            if writeln!(output, "{}", entry.format(self.format)).is_err() { self.output = None }   //a full disk stops the log, not the emulator
+        }
+
+        if self.capacity > 0
+        {
+            if self.ring.len() == self.capacity { self.ring.pop_front(); }
+            self.ring.push_back(entry);
+        }
+    }
+
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = &TraceEntry> //the last count entries, oldest first
+    {
+        return self.ring.iter().skip(self.ring.len().saturating_sub(count))
+    }
+
+    pub fn flush(&mut self)
+    {
+        if let Some(output) = self.output.as_mut() { let _ = output.flush(); }
+    }
+}
@@ -0,0 +1,105 @@
+//The Apple Cassette Interface: the output flip-flop, the input level through the ROM's A0, and WAV files going out and
+//coming back in.
+
+mod common;
+
+use common::scratch_file;
+use rust65::devices::Device;
+use rust65::devices::aci::Aci;
+
+use std::fs;
+
+const CLOCK: u64 = 1_000_000;
+const HALF_ZERO: u64 = 250;     //half a cycle of the 2 kHz tone for a 0 bit, in microseconds
+const HALF_ONE: u64 = 500;      //and of the 1 kHz tone for a 1
+
+
+fn aci() -> Aci //a ROM where C080 reads 0 and C081 reads 1, so a read of C080 gives the tape input level
+{
+    let mut rom: Vec<u8> = (0..=255).collect();
+    rom[0x80] = 0;
+    rom[0x81] = 1;
+
+    return Aci::new(&rom, CLOCK)
+}
+
+fn run(aci: &mut Aci, cycles: u64)
+{
+    for _ in 0..cycles { aci.tick() }
+}
+
+fn half_periods(bits: &[bool]) -> Vec<u64> //each bit is a full cycle of its tone, so two flips of the output
+{
+    return bits.iter().flat_map(|&bit| if bit { [HALF_ONE; 2] } else { [HALF_ZERO; 2] }).collect()
+}
+
+
+#[test]
+fn round_trip()
+{
+    let bits: Vec<bool> = vec![true, false, false, true, true, false, true, false, false, false, true];
+    let path = scratch_file("aci_round_trip.wav");
+
+    let mut writer = aci();
+    writer.record_to(&path).unwrap();
+    writer.read(0x00);                                          //the recording starts at the first flip
+    for half in half_periods(&bits)
+    {
+        run(&mut writer, half);
+        if half == HALF_ONE { writer.write(0x10, 0) } else { writer.read(0x7f); }  //reads and writes of C000-C07F both flip the output
+    }
+    run(&mut writer, HALF_ZERO);                                //a flip after the last bit, so its second half has an end on tape
+    writer.read(0x00);
+    writer.stop_recording().unwrap();
+
+    let wav: Vec<u8> = fs::read(&path).unwrap();
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(u32::from_le_bytes([wav[40], wav[41], wav[42], wav[43]]) as usize, wav.len() - 44, "the header has the data size");
+
+    let mut reader = aci();
+    reader.insert_tape(&path).unwrap();
+    let mut level: u8 = reader.read(0x80);                      //the tape starts playing on the first read
+    let mut since: u64 = 0;
+    let mut measured: Vec<u64> = Vec::new();
+
+    for _ in 0..(HALF_ONE * 2 * bits.len() as u64)              //sample the input every 10 microseconds, as the ROM's loop does
+    {
+        run(&mut reader, 10);
+        since += 10;
+
+        let now: u8 = reader.read(0x80);
+        if now != level
+        {
+            measured.push(since);
+            level = now;
+            since = 0;
+        }
+    }
+
+    let _ = fs::remove_file(&path);
+
+    let decoded: Vec<bool> = measured.chunks_exact(2).map(|pair| pair.iter().sum::<u64>() > HALF_ZERO + HALF_ONE).collect();
+    assert_eq!(decoded, bits, "half periods {:?}", measured);
+}
+
+#[test]
+fn input_level_and_rom()
+{
+    let mut aci = aci();
+    assert_eq!(aci.read(0x80), 0, "no tape reads low");
+    assert_eq!(aci.read(0x81), 0, "A0 comes from the tape, not the address");
+    assert_eq!(aci.read(0x1a5), 0xa5, "C100-C1FF is the ROM");
+    assert_eq!(aci.peek(0x81), 1, "peek sees the ROM as it is");
+}
+
+#[test]
+fn bad_tape()
+{
+    let path = scratch_file("aci_bad_tape.wav");
+    fs::write(&path, b"not a wave file at all").unwrap();
+
+    let result = aci().insert_tape(&path);
+    let _ = fs::remove_file(&path);
+
+    assert!(result.is_err());
+}
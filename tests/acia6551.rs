@@ -0,0 +1,156 @@
+//The 6551 ACIA against an in-memory serial host: the status register, RX full and TX empty, the receive interrupt,
+//and the command and control registers.
+
+mod common;
+
+use common::MemorySerial;
+use rust65::devices::Device;
+use rust65::devices::acia6551::Acia6551;
+
+const DATA: u16 = 0;
+const STATUS: u16 = 1;
+const COMMAND: u16 = 2;
+const CONTROL: u16 = 3;
+
+const OVERRUN: u8 = 0x04;
+const RDRF: u8 = 0x08;
+const TDRE: u8 = 0x10;
+const IRQ: u8 = 0x80;
+
+const CLOCK: u64 = 19_200;      //10 cycles per 8N1 character at 19200 baud
+
+
+fn setup() -> (Acia6551, MemorySerial)
+{
+    let host = MemorySerial::new();
+    let mut acia = Acia6551::new(Box::new(host.clone()), CLOCK);
+    acia.write(CONTROL, 0x1f);                                  //19200 baud, 8 data bits, 1 stop bit
+
+    return (acia, host)
+}
+
+fn run(acia: &mut Acia6551, cycles: u32)
+{
+    for _ in 0..cycles { acia.tick() }
+}
+
+
+#[test]
+fn after_reset()
+{
+    let (mut acia, _host) = setup();
+
+    assert_eq!(acia.read(STATUS), TDRE, "ready to transmit, nothing received");
+    assert_eq!(acia.read(COMMAND), 0x02, "receiver interrupt off, DTR off");
+    assert!(!acia.irq());
+}
+
+#[test]
+fn transmit()
+{
+    let (mut acia, host) = setup();
+    acia.write(COMMAND, 0x0b);                                  //DTR on, RX interrupt off, transmitter on with its interrupt off
+
+    acia.write(DATA, b'A');
+    assert_eq!(acia.read(STATUS) & TDRE, 0, "the byte is waiting in the data register");
+    acia.tick();
+    assert_eq!(acia.read(STATUS) & TDRE, TDRE, "moved to the shift register");
+
+    acia.write(DATA, b'B');
+    run(&mut acia, 5);
+    assert_eq!(*host.sent.borrow(), b"A", "the next byte waits for the first to finish");
+    run(&mut acia, 10);
+    assert_eq!(*host.sent.borrow(), b"AB");
+    assert!(!acia.irq());
+}
+
+#[test]
+fn transmit_interrupt_and_transmitter_off()
+{
+    let (mut acia, host) = setup();
+    acia.write(COMMAND, 0x03);                                  //transmitter off
+
+    acia.write(DATA, b'A');
+    run(&mut acia, 50);
+    assert!(host.sent.borrow().is_empty());
+
+    acia.write(COMMAND, 0x07);                                  //transmitter on, with its interrupt
+    acia.tick();
+    assert_eq!(*host.sent.borrow(), b"A");
+    assert!(acia.irq());
+    assert_eq!(acia.read(STATUS), IRQ | TDRE);
+    assert!(!acia.irq(), "reading the status acknowledges it");
+}
+
+#[test]
+fn receive_and_interrupt()
+{
+    let (mut acia, host) = setup();
+    host.type_in(b"x");
+
+    run(&mut acia, 20);
+    assert_eq!(acia.read(STATUS) & RDRF, 0, "DTR off disables the receiver");
+
+    acia.write(COMMAND, 0x09);                                  //DTR on, RX interrupt on
+    acia.tick();
+    assert!(acia.irq());
+    assert_eq!(acia.read(STATUS), IRQ | TDRE | RDRF);
+    assert!(!acia.irq(), "reading the status acknowledges the interrupt");
+
+    assert_eq!(acia.read(DATA), b'x');
+    assert_eq!(acia.read(STATUS) & RDRF, 0, "reading the data empties the receiver");
+
+    acia.write(COMMAND, 0x0b);                                  //RX interrupt off
+    host.type_in(b"y");
+    run(&mut acia, 20);
+    assert_eq!(acia.read(STATUS) & (RDRF | IRQ), RDRF);
+}
+
+#[test]
+fn overrun()
+{
+    let (mut acia, host) = setup();
+    acia.write(COMMAND, 0x0b);
+    host.type_in(b"12");
+
+    run(&mut acia, 20);
+    assert_eq!(acia.read(STATUS) & (RDRF | OVERRUN), RDRF | OVERRUN);
+    assert_eq!(acia.read(DATA), b'1', "the second byte is the one lost");
+    assert_eq!(acia.read(STATUS) & (RDRF | OVERRUN), 0);
+}
+
+#[test]
+fn command_and_control_registers()
+{
+    let (mut acia, host) = setup();
+
+    acia.write(CONTROL, 0x7f);                                  //5 data bits
+    acia.write(COMMAND, 0x0b);
+    host.type_in(&[0xff]);
+    run(&mut acia, 50);
+    assert_eq!(acia.read(DATA), 0x1f, "received words are masked to the word length");
+    assert_eq!(acia.read(CONTROL), 0x7f);
+
+    acia.write(CONTROL, 0x1e);                                  //9600 baud, 8N1
+    assert_eq!(acia.character_cycles(), 20);
+    acia.write(CONTROL, 0x9e);                                  //two stop bits
+    assert_eq!(acia.character_cycles(), 22);
+    acia.write(COMMAND, 0x2b);                                  //and parity
+    assert_eq!(acia.character_cycles(), 24);
+
+    acia.write(STATUS, 0x00);                                   //programmed reset
+    assert_eq!(acia.read(COMMAND), 0x20, "clears the command register except parity");
+    assert_eq!(acia.read(CONTROL), 0x9e, "and leaves the control register alone");
+}
+
+#[test]
+fn echo()
+{
+    let (mut acia, host) = setup();
+    acia.write(COMMAND, 0x13);                                  //echo, which needs the transmitter control bits clear
+    host.type_in(b"e");
+
+    acia.tick();
+    assert_eq!(*host.sent.borrow(), b"e");
+    assert_eq!(acia.read(DATA), b'e');
+}
@@ -0,0 +1,126 @@
+//The 6850 ACIA against an in-memory serial host: master reset, the status register, the transmit and receive
+//interrupts, and the word and divide selects in the control register.
+
+mod common;
+
+use common::MemorySerial;
+use rust65::devices::Device;
+use rust65::devices::acia6850::Acia6850;
+
+const CONTROL: u16 = 0;         //reads give the status register
+const DATA: u16 = 1;
+
+const RDRF: u8 = 0x01;
+const TDRE: u8 = 0x02;
+const OVRN: u8 = 0x20;
+const IRQ: u8 = 0x80;
+
+const CLOCK: u64 = 19_200;              //10 cycles per 8N1 character at 19200 baud
+const SERIAL_CLOCK: u64 = 307_200;      //19200 baud through the divide by 16
+
+const DIVIDE_16_8N1: u8 = 0x15;
+const TX_IRQ: u8 = 0x20;
+const RX_IRQ: u8 = 0x80;
+
+
+fn setup() -> (Acia6850, MemorySerial)
+{
+    let host = MemorySerial::new();
+    let acia = Acia6850::new(Box::new(host.clone()), CLOCK, SERIAL_CLOCK);
+
+    return (acia, host)
+}
+
+fn run(acia: &mut Acia6850, cycles: u32)
+{
+    for _ in 0..cycles { acia.tick() }
+}
+
+
+#[test]
+fn master_reset()
+{
+    let (mut acia, host) = setup();
+    assert_eq!(acia.read(CONTROL), TDRE);
+
+    host.type_in(b"x");
+    acia.write(DATA, b'A');
+    run(&mut acia, 50);
+    assert!(host.sent.borrow().is_empty(), "the chip does nothing until it's out of master reset");
+    assert_eq!(acia.read(CONTROL) & RDRF, 0);
+
+    acia.write(CONTROL, 0x03);
+    assert_eq!(acia.read(CONTROL), TDRE, "a master reset empties the transmitter again");
+}
+
+#[test]
+fn transmit_and_its_interrupt()
+{
+    let (mut acia, host) = setup();
+    acia.write(CONTROL, DIVIDE_16_8N1);
+
+    acia.write(DATA, b'A');
+    assert_eq!(acia.read(CONTROL), 0);
+    acia.tick();
+    assert_eq!(acia.read(CONTROL), TDRE);
+    assert_eq!(*host.sent.borrow(), b"A");
+    assert!(!acia.irq());
+
+    acia.write(CONTROL, DIVIDE_16_8N1 | TX_IRQ);
+    assert!(acia.irq(), "TDRE interrupts as soon as it's enabled");
+    acia.write(DATA, b'B');
+    assert!(!acia.irq(), "writing the data clears it");
+    run(&mut acia, 10);
+    assert_eq!(*host.sent.borrow(), b"A", "B waits for A's character time");
+    acia.tick();
+    assert_eq!(*host.sent.borrow(), b"AB");
+    assert_eq!(acia.read(CONTROL), IRQ | TDRE);
+}
+
+#[test]
+fn receive_and_its_interrupt()
+{
+    let (mut acia, host) = setup();
+    acia.write(CONTROL, DIVIDE_16_8N1 | RX_IRQ);
+    host.type_in(b"x");
+
+    acia.tick();
+    assert!(acia.irq());
+    assert_eq!(acia.read(CONTROL), IRQ | TDRE | RDRF);
+    assert!(acia.irq(), "reading the status doesn't clear it");
+
+    assert_eq!(acia.read(DATA), b'x');
+    assert_eq!(acia.read(CONTROL), TDRE, "reading the data does");
+    assert!(!acia.irq());
+}
+
+#[test]
+fn overrun()
+{
+    let (mut acia, host) = setup();
+    acia.write(CONTROL, DIVIDE_16_8N1 | RX_IRQ);
+    host.type_in(b"12");
+
+    run(&mut acia, 20);
+    assert_eq!(acia.read(CONTROL), IRQ | OVRN | TDRE | RDRF);
+    assert_eq!(acia.read(DATA), b'1', "the second byte is the one lost");
+    assert_eq!(acia.read(CONTROL), TDRE);
+}
+
+#[test]
+fn word_and_divide_selects()
+{
+    let (mut acia, host) = setup();
+
+    acia.write(CONTROL, 0x09);                                  //divide by 16, 7 data bits, even parity, 1 stop bit
+    assert_eq!(acia.character_cycles(), 10);
+    host.type_in(&[0xff]);
+    acia.tick();
+    assert_eq!(acia.read(DATA), 0x7f, "7 bit words lose the top bit");
+
+    acia.write(CONTROL, 0x14);
+    assert_eq!(acia.baud_rate(), 307_200, "divide by 1");
+    acia.write(CONTROL, 0x16);
+    assert_eq!(acia.baud_rate(), 4_800, "divide by 64");
+    assert_eq!(acia.character_cycles(), 40);
+}
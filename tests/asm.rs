@@ -0,0 +1,98 @@
+//The line assembler: every documented opcode round trips through the disassembler, and labels, forward references
+//and branch offsets come out right.
+
+mod common;
+
+use common::FlatBus;
+use rust65::asm::{assemble, Assembled, Assembler};
+use rust65::cpu::{CpuStatus, Variant};
+use rust65::disasm::disassemble_one;
+use rust65::opcodes::Mnemonic;
+
+
+#[test]
+fn every_documented_opcode_round_trips()
+{
+    for variant in [Variant::Nmos, Variant::Cmos, Variant::Rockwell, Variant::Wdc]
+    {
+        for (code, op) in variant.opcodes().iter().enumerate()
+        {
+            if op.undocumented || op.mnemonic == Mnemonic::JAM { continue }
+
+            let mut bus = FlatBus::new();
+            bus.load(0x1000, &[code as u8, 0x34, 0x12]);
+            let text: String = disassemble_one(&bus, variant, 0x1000).text;
+
+            let bytes: Vec<u8> = assemble(variant, 0x1000, &text).unwrap_or_else(|e| panic!("{:?} {:02x} {}: {}", variant, code, text, e));
+            assert_eq!(bytes[..], bus.ram[0x1000..0x1000 + op.length as usize], "{:?} {}", variant, text);
+        }
+    }
+}
+
+#[test]
+fn zero_page_or_absolute()
+{
+    assert_eq!(assemble(Variant::Nmos, 0, "LDA $20"), Ok(vec![0xa5, 0x20]));
+    assert_eq!(assemble(Variant::Nmos, 0, "LDA $0020"), Ok(vec![0xad, 0x20, 0x00]), "four hex digits mean absolute");
+    assert_eq!(assemble(Variant::Nmos, 0, "lda 300,y"), Ok(vec![0xb9, 0x2c, 0x01]));
+    assert_eq!(assemble(Variant::Nmos, 0, "LDA $20,Y"), Ok(vec![0xb9, 0x20, 0x00]), "there's no zero page,Y for LDA");
+    assert_eq!(assemble(Variant::Nmos, 0, "ROL"), Ok(vec![0x2a]));
+    assert_eq!(assemble(Variant::Nmos, 0, "LDX #%1010"), Ok(vec![0xa2, 0x0a]));
+}
+
+#[test]
+fn labels_and_branches()
+{
+    let source: &str = "count = $10
+                        start: LDX #0
+                        loop:  INX          ; count up
+                               CPX count
+                               BNE loop
+                               JMP done
+                               NOP
+                        done:  BEQ start";
+
+    assert_eq!(assemble(Variant::Nmos, 0x0200, source), Ok(vec![0xa2, 0x00, 0xe8, 0xe4, 0x10, 0xd0, 0xfb, 0x4c, 0x0b, 0x02, 0xea, 0xf0, 0xf3]));
+}
+
+#[test]
+fn errors()
+{
+    assert!(assemble(Variant::Nmos, 0, "FOO").is_err());
+    assert!(assemble(Variant::Nmos, 0, "STA #1").is_err());
+    assert!(assemble(Variant::Nmos, 0, "LDA #$100").is_err());
+    assert!(assemble(Variant::Nmos, 0, "STZ $20").is_err(), "STZ is 65C02 only");
+    assert!(assemble(Variant::Nmos, 0, "BNE nowhere").is_err(), "undefined label");
+    assert!(assemble(Variant::Nmos, 0x1000, "BNE $2000").is_err(), "out of range");
+}
+
+#[test]
+fn session_patches_forward_references()
+{
+    let mut assembler: Assembler = Assembler::new();
+
+    let first: Assembled = assembler.assemble_line(Variant::Nmos, 0x0300, "JSR print").unwrap();
+    assert_eq!(first.bytes, vec![0x20, 0x00, 0x00]);
+    assert_eq!(assembler.unresolved(), vec!["print".to_string()]);
+
+    let second: Assembled = assembler.assemble_line(Variant::Nmos, 0x0310, "print: RTS").unwrap();
+    assert_eq!(second.bytes, vec![0x60]);
+    assert_eq!(second.patches, vec![(0x0301, 0x10), (0x0302, 0x03)]);
+    assert_eq!(assembler.labels()["print"], 0x0310);
+}
+
+#[test]
+fn assembled_code_runs()
+{
+    let code: Vec<u8> = assemble(Variant::Nmos, 0x0200, "LDA #5\nloop: SEC\nSBC #1\nBNE loop\nSTA $30").unwrap();
+
+    let mut bus = FlatBus::new();
+    bus.load(0x0200, &code);
+    bus.ram[0x30] = 0xff;
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.reset = false;
+    cpu.pc = 0x0200;
+
+    while cpu.pc != 0x0200 + code.len() as u16 { cpu.execute(&mut bus).unwrap(); }
+    assert_eq!(bus.ram[0x30], 0);
+}
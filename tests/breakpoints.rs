@@ -0,0 +1,124 @@
+//Breakpoints checked before each instruction, the way the front end's run loop does it: plain, conditional,
+//after a number of hits, and the one-off stops behind "until" and "next".
+
+mod common;
+
+use common::FlatBus;
+use rust65::asm::assemble;
+use rust65::breakpoints::{Breakpoints, Condition};
+use rust65::cpu::{CpuError, CpuStatus, Variant};
+
+
+fn setup(source: &str) -> (CpuStatus, FlatBus) //assembled at $0200
+{
+    let mut bus = FlatBus::new();
+    bus.load(0x0200, &assemble(Variant::Nmos, 0x0200, source).unwrap());
+
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.reset = false;
+    cpu.pc = 0x0200;
+    cpu.sp = 0xff;
+
+    return (cpu, bus)
+}
+
+fn run(cpu: &mut CpuStatus, bus: &mut FlatBus) -> u16 //run until something stops it, returns where
+{
+    for _ in 0..10_000
+    {
+        if let Some(stop) = cpu.breakpoint()
+        {
+            assert_eq!(stop, CpuError::Breakpoint { addr: cpu.pc });
+            cpu.breakpoints.resume(cpu.pc);
+            return cpu.pc
+        }
+
+        cpu.execute(bus).unwrap();
+    }
+
+    panic!("never stopped, PC is {:#06x}", cpu.pc)
+}
+
+
+const COUNTER: &str = "loop: INX
+                             TXA
+                             JMP loop";
+
+#[test]
+fn stops_every_time_and_counts_hits()
+{
+    let (mut cpu, mut bus) = setup(COUNTER);
+    let id: usize = cpu.breakpoints.add(0x0201, None, 1);
+
+    assert_eq!(run(&mut cpu, &mut bus), 0x0201);
+    assert_eq!(cpu.x, 1);
+    assert_eq!(run(&mut cpu, &mut bus), 0x0201, "resuming runs the instruction at the breakpoint first");
+    assert_eq!(cpu.x, 2);
+    assert_eq!(cpu.breakpoints.list()[0].hits, 2);
+
+    assert!(cpu.breakpoints.remove(id));
+    assert!(!cpu.breakpoints.remove(id));
+}
+
+#[test]
+fn conditions_and_hit_counts()
+{
+    let (mut cpu, mut bus) = setup(COUNTER);
+    cpu.breakpoints.add(0x0201, Condition::parse("x == 0x10"), 1);
+    cpu.breakpoints.add(0x0202, Condition::parse("a>=$20"), 3);
+
+    assert_eq!(run(&mut cpu, &mut bus), 0x0201);
+    assert_eq!(cpu.x, 0x10);
+
+    assert_eq!(run(&mut cpu, &mut bus), 0x0202);
+    assert_eq!(cpu.a, 0x22, "the third time A is $20 or more");
+    assert_eq!(cpu.breakpoints.list()[0].hits, 1);
+    assert_eq!(cpu.breakpoints.list()[1].to_string(), "2: 0x0202 if A>=0x20 after 3, 3 hits");
+}
+
+#[test]
+fn condition_syntax()
+{
+    assert!(Condition::parse("a==0x8d").is_some());
+    assert!(Condition::parse("sp < 10").is_some());
+    assert!(Condition::parse("q==1").is_none());
+    assert!(Condition::parse("a=1").is_none());
+}
+
+#[test]
+fn until_stops_once()
+{
+    let (mut cpu, mut bus) = setup(COUNTER);
+    cpu.breakpoints.run_until(0x0202, None);
+
+    assert_eq!(run(&mut cpu, &mut bus), 0x0202);
+    cpu.breakpoints.add(0x0200, Condition::parse("x==3"), 1);
+    assert_eq!(run(&mut cpu, &mut bus), 0x0200);
+    assert_eq!(cpu.x, 3, "the until stop doesn't fire again");
+}
+
+#[test]
+fn next_steps_over_recursion()
+{
+    let (mut cpu, mut bus) = setup("       LDX #3
+                                    rec:   JSR down
+                                           RTS
+                                    down:  DEX
+                                           BNE rec
+                                           RTS");
+    cpu.execute(&mut bus).unwrap();                                     //LDX
+
+    cpu.breakpoints.run_until(cpu.pc + 3, Some(cpu.sp));                //what "next" does on a JSR
+    assert_eq!(run(&mut cpu, &mut bus), 0x0205);
+    assert_eq!((cpu.x, cpu.sp), (0, 0xff), "back from the outermost call, not the inner ones that also return to $0205");
+}
+
+#[test]
+fn ids_start_at_1()
+{
+    let mut breakpoints: Breakpoints = Breakpoints::default();
+    assert_eq!(breakpoints.add(0x0200, None, 0), 1);
+    assert_eq!(breakpoints.add(0x0300, None, 0), 2);
+    assert!(breakpoints.remove(1));
+    assert_eq!(breakpoints.add(0x0400, None, 0), 3, "ids aren't reused");
+}
@@ -0,0 +1,189 @@
+//The CFFA1 CompactFlash card against a scratch image file: the task file registers, reading and writing sectors
+//through the 16 bit data port, and the status and error bits.
+
+mod common;
+
+use common::scratch_file;
+use rust65::devices::Device;
+use rust65::devices::cffa1::Cffa1;
+
+use std::fs;
+use std::path::PathBuf;
+
+const IO: u16 = 0x1ff0;                 //the task file, offset from the card's $9000
+const DATA_HIGH: u16 = IO;
+const DEVICE_CONTROL: u16 = IO + 0x6;
+const DATA: u16 = IO + 0x8;
+const ERROR: u16 = IO + 0x9;
+const SECTOR_COUNT: u16 = IO + 0xa;
+const LBA_0: u16 = IO + 0xb;
+const LBA_8: u16 = IO + 0xc;
+const LBA_16: u16 = IO + 0xd;
+const HEAD: u16 = IO + 0xe;
+const COMMAND: u16 = IO + 0xf;
+
+const READY: u8 = 0x50;                 //DRDY and DSC
+const DRQ: u8 = 0x08;
+const ERR: u8 = 0x01;
+const ABRT: u8 = 0x04;
+const IDNF: u8 = 0x10;
+
+const SECTORS: usize = 4;
+
+
+struct Image //a scratch image file, removed again when the test is done with it
+{
+    path: PathBuf,
+}
+
+impl Drop for Image
+{
+    fn drop(&mut self)
+    {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn pattern(sector: usize) -> Vec<u8> //different in every sector and every byte of it
+{
+    return (0..512).map(|i| (i as u8).wrapping_mul(3) ^ (sector as u8 * 0x40)).collect()
+}
+
+fn setup(name: &str) -> (Cffa1, Image)
+{
+    let image = Image { path: scratch_file(name) };
+    fs::write(&image.path, (0..SECTORS).flat_map(pattern).collect::<Vec<u8>>()).unwrap();
+
+    let rom: Vec<u8> = (0..0x2000).map(|i| (i >> 8) as u8).collect();
+    let card = Cffa1::new(&rom, &image.path).unwrap();
+
+    return (card, image)
+}
+
+fn command(card: &mut Cffa1, lba: u32, count: u8, cmd: u8)
+{
+    card.write(SECTOR_COUNT, count);
+    card.write(LBA_0, lba as u8);
+    card.write(LBA_8, (lba >> 8) as u8);
+    card.write(LBA_16, (lba >> 16) as u8);
+    card.write(HEAD, 0xe0 | (lba >> 24) as u8 & 0xf);      //LBA mode, master
+    card.write(COMMAND, cmd);
+}
+
+fn read_sector(card: &mut Cffa1) -> Vec<u8>
+{
+    let mut sector: Vec<u8> = Vec::new();
+    for _ in 0..256
+    {
+        sector.push(card.read(DATA));                       //the low byte, which latches the high byte
+        sector.push(card.read(DATA_HIGH));
+    }
+    return sector
+}
+
+
+#[test]
+fn read_sectors()
+{
+    let (mut card, _image) = setup("cffa1_read.img");
+    assert_eq!(card.read(COMMAND), READY);
+
+    command(&mut card, 2, 1, 0x20);
+    assert_eq!(card.read(COMMAND), READY | DRQ);
+    assert_eq!(read_sector(&mut card), pattern(2));
+    assert_eq!(card.read(COMMAND), READY, "DRQ drops when the sector's been read");
+    assert_eq!(card.read(DATA), 0xff, "and the data port goes quiet");
+
+    command(&mut card, 1, 2, 0x20);
+    assert_eq!(read_sector(&mut card), pattern(1));
+    assert_eq!(card.read(COMMAND), READY | DRQ, "the next sector follows on");
+    assert_eq!(read_sector(&mut card), pattern(2));
+    assert_eq!(card.read(COMMAND), READY);
+}
+
+#[test]
+fn write_sectors()
+{
+    let (mut card, image) = setup("cffa1_write.img");
+    let data: Vec<u8> = (0..1024).map(|i| (i * 7 % 251) as u8).collect();
+
+    command(&mut card, 2, 2, 0x30);
+    assert_eq!(card.read(COMMAND), READY | DRQ);
+    for word in data.chunks(2)
+    {
+        card.write(DATA_HIGH, word[1]);
+        card.write(DATA, word[0]);                          //writing the low byte stores the word
+    }
+    assert_eq!(card.read(COMMAND), READY);
+
+    let contents: Vec<u8> = fs::read(&image.path).unwrap();
+    assert_eq!(contents[1024..], data[..]);
+    assert_eq!(contents[512..1024], pattern(1)[..], "the sectors either side are left alone");
+
+    command(&mut card, 3, 1, 0x20);
+    assert_eq!(read_sector(&mut card), data[512..]);
+}
+
+#[test]
+fn lba_registers()
+{
+    let (mut card, _image) = setup("cffa1_lba.img");
+
+    card.write(LBA_0, 0x12);
+    card.write(LBA_8, 0x34);
+    card.write(LBA_16, 0x56);
+    card.write(HEAD, 0xe7);
+    card.write(SECTOR_COUNT, 9);
+    assert_eq!([card.read(LBA_0), card.read(LBA_8), card.read(LBA_16), card.read(HEAD), card.read(SECTOR_COUNT)], [0x12, 0x34, 0x56, 0xe7, 9]);
+
+    card.write(SECTOR_COUNT, 1);
+    card.write(LBA_0, 2);                                   //sector 2 of cylinder 0, head 0 in CHS terms is LBA 1
+    card.write(LBA_8, 0);
+    card.write(LBA_16, 0);
+    card.write(HEAD, 0xa0);
+    card.write(COMMAND, 0x20);
+    assert_eq!(read_sector(&mut card), pattern(1));
+}
+
+#[test]
+fn errors()
+{
+    let (mut card, _image) = setup("cffa1_errors.img");
+
+    command(&mut card, SECTORS as u32, 1, 0x20);
+    assert_eq!(card.read(COMMAND), READY | ERR, "past the end of the image");
+    assert_eq!(card.read(ERROR), IDNF | ABRT);
+    assert_eq!(card.read(DATA), 0xff);
+
+    command(&mut card, 0x0100_0000, 1, 0x30);
+    assert_eq!(card.read(ERROR), IDNF | ABRT, "LBA bits 24-27 come from the head register");
+
+    command(&mut card, 0, 1, 0x20);
+    assert_eq!((card.read(COMMAND), card.read(ERROR)), (READY | DRQ, 0), "a good command clears the error");
+
+    command(&mut card, 0, 1, 0x55);
+    assert_eq!((card.read(COMMAND), card.read(ERROR)), (READY | ERR, ABRT), "unknown commands abort");
+
+    card.write(DEVICE_CONTROL, 0x04);
+    assert_eq!((card.read(DEVICE_CONTROL), card.read(ERROR)), (READY, 0), "a software reset clears it");
+
+    card.write(HEAD, 0xf0);                                 //the slave, which isn't there
+    card.write(COMMAND, 0x20);
+    assert_eq!((card.read(COMMAND), card.read(ERROR)), (READY | ERR, ABRT));
+}
+
+#[test]
+fn identify_and_rom()
+{
+    let (mut card, _image) = setup("cffa1_identify.img");
+
+    assert_eq!(card.read(0x0123), 0x01, "below the task file is the ROM");
+    assert_eq!(card.read(0x1fef), 0x1f);
+
+    card.write(HEAD, 0xa0);
+    card.write(COMMAND, 0xec);
+    let identify: Vec<u8> = read_sector(&mut card);
+    assert_eq!(&identify[0..2], &[0x8a, 0x84], "the CompactFlash signature");
+    assert_eq!(u16::from_le_bytes([identify[120], identify[121]]) as usize, SECTORS, "the size of the image in sectors");
+    assert_eq!(card.read(COMMAND), READY);
+}
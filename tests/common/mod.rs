@@ -0,0 +1,90 @@
+//Shared pieces for the integration tests: a flat 64K bus, an in-memory serial host and scratch files.
+
+#![allow(dead_code)] //not every test file uses every helper
+
+use rust65::bus::Bus;
+use rust65::devices::serial::SerialBackend;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+
+pub struct FlatBus //64K of RAM with nothing else on the bus
+{
+    pub ram: Vec<u8>,
+}
+
+impl FlatBus
+{
+    pub fn new() -> FlatBus
+    {
+        FlatBus { ram: vec![0; 0x10000] }
+    }
+
+    pub fn load(&mut self, addr: u16, data: &[u8])
+    {
+        let start: usize = addr as usize;
+        let end: usize = (start + data.len()).min(0x10000);
+        self.ram[start..end].copy_from_slice(&data[..end - start]);
+    }
+}
+
+impl Bus for FlatBus
+{
+    fn read(&mut self, addr: u16) -> u8
+    {
+        return self.ram[addr as usize]
+    }
+
+    fn write(&mut self, addr: u16, data: u8)
+    {
+        self.ram[addr as usize] = data;
+    }
+
+    fn peek(&self, addr: u16) -> u8
+    {
+        return self.ram[addr as usize]
+    }
+}
+
+
+#[derive(Clone, Default)]
+pub struct MemorySerial //a serial host the test can still type into and read from after handing a clone to a device
+{
+    pub incoming: Rc<RefCell<VecDeque<u8>>>,    //bytes waiting to be received by the device
+    pub sent: Rc<RefCell<Vec<u8>>>,             //bytes the device has transmitted
+}
+
+impl MemorySerial
+{
+    pub fn new() -> MemorySerial
+    {
+        MemorySerial::default()
+    }
+
+    pub fn type_in(&self, data: &[u8])
+    {
+        self.incoming.borrow_mut().extend(data);
+    }
+}
+
+impl SerialBackend for MemorySerial
+{
+    fn receive(&mut self) -> Option<u8>
+    {
+        return self.incoming.borrow_mut().pop_front()
+    }
+
+    fn transmit(&mut self, data: u8)
+    {
+        self.sent.borrow_mut().push(data);
+    }
+}
+
+
+pub fn scratch_file(name: &str) -> PathBuf //a path in the temp directory that no other test, or test run, uses
+{
+    return std::env::temp_dir().join(format!("rust65-{}-{}", std::process::id(), name))
+}
@@ -0,0 +1,109 @@
+//End to end conformance tests for CpuStatus::execute.
+//
+//Klaus Dormann's 6502 functional test, 65C02 extended opcode test and decimal test (Bruce Clark's) need their binaries
+//in tests/roms, built with the default options from https://github.com/Klaus2m5/6502_65C02_functional_tests. The
+//functional tests load at $0000 and start at $0400, the decimal test loads and starts at $0200 and stops with STP.
+//The binaries aren't in the tree yet, so these are ignored unless asked for with
+//cargo test --test conformance -- --ignored, and fail if a binary is missing.
+
+mod common;
+
+use common::FlatBus;
+use rust65::cpu::{CpuStatus, Variant};
+
+use std::fs;
+use std::path::Path;
+
+const INSTRUCTION_LIMIT: u64 = 200_000_000;
+
+
+fn run_until_trap(cpu: &mut CpuStatus, bus: &mut FlatBus, start: u16) -> u16 //run from start until an instruction jumps to itself, returns the trap address
+{
+    cpu.reset = false;
+    cpu.pc = start;
+
+    for _ in 0..INSTRUCTION_LIMIT
+    {
+        let pc: u16 = cpu.pc;
+
+        if let Err(e) = cpu.execute(bus) { panic!("CPU error at {:#06x}: {}", pc, e) }
+        if cpu.pc == pc { return pc }
+    }
+
+    panic!("no trap after {} instructions, PC is {:#06x}", INSTRUCTION_LIMIT, cpu.pc)
+}
+
+
+fn klaus_test(image: &str, variant: Variant, success: u16)
+{
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roms").join(image);
+    let binary: Vec<u8> = match fs::read(&path)
+    {
+        Ok(data) => data,
+        Err(e) => panic!("can't read {}: {} (see the top of tests/conformance.rs for where to get it)", path.display(), e)
+    };
+
+    let mut bus = FlatBus::new();
+    bus.load(0x0000, &binary);
+
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.variant = variant;
+
+    let trap: u16 = run_until_trap(&mut cpu, &mut bus, 0x0400);
+    assert_eq!(trap, success, "{} trapped at {:#06x} in test case {:#04x}", image, trap, bus.ram[0x0200]);
+}
+
+#[test]
+#[ignore = "needs tests/roms/6502_functional_test.bin"]
+fn functional_test()
+{
+    klaus_test("6502_functional_test.bin", Variant::Nmos, 0x3469);
+}
+
+#[test]
+#[ignore = "needs tests/roms/65C02_extended_opcodes_test.bin"]
+fn extended_65c02_test()
+{
+    klaus_test("65C02_extended_opcodes_test.bin", Variant::Wdc, 0x24f1);
+}
+
+
+//the decimal test's zero page variables
+const N1: usize = 0x00;                 //the operands, Y is the carry in
+const N2: usize = 0x01;
+const DA: usize = 0x04;                 //the accumulator after the decimal operation
+const AR: usize = 0x06;                 //and the prediction
+const ERROR: usize = 0x0b;              //0 when the test passes
+const STP: u8 = 0xdb;                   //the decimal test's end_of_test, which the NMOS part would run as DCP abs,Y
+
+#[test]
+#[ignore = "needs tests/roms/6502_decimal_test.bin"]
+fn decimal_test()
+{
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roms/6502_decimal_test.bin");
+    let binary: Vec<u8> = match fs::read(&path)
+    {
+        Ok(data) => data,
+        Err(e) => panic!("can't read {}: {} (see the top of tests/conformance.rs for where to get it)", path.display(), e)
+    };
+
+    let mut bus = FlatBus::new();
+    bus.load(0x0200, &binary);
+
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.reset = false;
+    cpu.pc = 0x0200;
+
+    for _ in 0..INSTRUCTION_LIMIT
+    {
+        if bus.ram[cpu.pc as usize] == STP { break }
+
+        let pc: u16 = cpu.pc;
+        if let Err(e) = cpu.execute(&mut bus) { panic!("CPU error at {:#06x}: {}", pc, e) }
+        assert_ne!(cpu.pc, pc, "the decimal test doesn't trap, it stops with STP");
+    }
+
+    assert_eq!(bus.ram[cpu.pc as usize], STP, "the decimal test didn't finish in {} instructions", INSTRUCTION_LIMIT);
+    assert_eq!(bus.ram[ERROR], 0, "decimal test failed on {:#04x} and {:#04x} with carry {}: gave {:#04x}, expected {:#04x}",
+        bus.ram[N1], bus.ram[N2], cpu.y, bus.ram[DA], bus.ram[AR]);
+}
@@ -0,0 +1,79 @@
+//The disassembler: operand formats for every addressing mode, branch targets, and listings.
+
+mod common;
+
+use common::FlatBus;
+use rust65::cpu::{CpuStatus, Variant};
+use rust65::disasm::{disassemble, disassemble_one, Line};
+
+
+fn text(variant: Variant, bytes: &[u8]) -> String //disassemble one instruction at $1000
+{
+    let mut bus = FlatBus::new();
+    bus.load(0x1000, bytes);
+    return disassemble_one(&bus, variant, 0x1000).text
+}
+
+
+#[test]
+fn addressing_modes()
+{
+    let cases: [(&[u8], &str); 14] = [
+        (&[0xea], "NOP"),
+        (&[0x0a], "ASL A"),
+        (&[0xa9, 0x0f], "LDA #$0F"),
+        (&[0xa5, 0x20], "LDA $20"),
+        (&[0xb5, 0x20], "LDA $20,X"),
+        (&[0xb6, 0x20], "LDX $20,Y"),
+        (&[0xad, 0x34, 0x12], "LDA $1234"),
+        (&[0xbd, 0x34, 0x12], "LDA $1234,X"),
+        (&[0xb9, 0x34, 0x12], "LDA $1234,Y"),
+        (&[0x6c, 0xfc, 0xff], "JMP ($FFFC)"),
+        (&[0xa1, 0x20], "LDA ($20,X)"),
+        (&[0xb1, 0x20], "LDA ($20),Y"),
+        (&[0xa7, 0x20], "LAX $20"),
+        (&[0x02], "JAM"),
+    ];
+
+    for (bytes, expected) in cases
+    {
+        assert_eq!(text(Variant::Nmos, bytes), expected);
+    }
+
+    assert_eq!(text(Variant::Cmos, &[0xb2, 0x20]), "LDA ($20)");
+    assert_eq!(text(Variant::Cmos, &[0x7c, 0x00, 0x20]), "JMP ($2000,X)");
+    assert_eq!(text(Variant::Wdc, &[0xb7, 0x20]), "SMB3 $20");
+}
+
+#[test]
+fn branch_targets()
+{
+    let mut bus = FlatBus::new();
+    bus.load(0x1000, &[0xd0, 0xfe, 0x10, 0x10, 0x20, 0x00, 0xe0, 0x8f, 0x20, 0x80]);  //BNE *, BPL +16, JSR $E000, BBS0 $20,-128
+
+    let lines: Vec<Line> = disassemble(&bus, Variant::Wdc, 0x1000, 4);
+
+    assert_eq!(lines[0].text, "BNE $1000");
+    assert_eq!(lines[0].target, Some(0x1000));
+    assert_eq!(lines[1].text, "BPL $1014");
+    assert_eq!(lines[2].text, "JSR $E000");
+    assert_eq!(lines[2].target, Some(0xe000));
+    assert_eq!(lines[3].text, "BBS0 $20,$0F8A");
+    assert_eq!(lines[3].next(), 0x100a);
+    assert_eq!(lines[2].to_string(), "1004  20 00 E0  JSR $E000");
+}
+
+#[test]
+fn last_instruction_address()
+{
+    let mut bus = FlatBus::new();
+    bus.load(0x0200, &[0xa9, 0x01, 0xea]);
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.reset = false;
+    cpu.pc = 0x0200;
+
+    cpu.execute(&mut bus).unwrap();
+    cpu.execute(&mut bus).unwrap();
+    assert_eq!(cpu.last_pc, 0x0202);
+    assert_eq!(disassemble_one(&bus, cpu.variant, cpu.last_pc).text, "NOP");
+}
@@ -0,0 +1,90 @@
+//The errors execute can stop with: illegal and JAM opcodes, bus faults and stack wrapping, and carrying on after them.
+
+mod common;
+
+use common::FlatBus;
+use rust65::bus::{MemoryMap, OpenBus, Segment};
+use rust65::cpu::{CpuError, CpuStatus, StackCheck};
+
+
+fn cpu_at(pc: u16) -> CpuStatus
+{
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.reset = false;
+    cpu.pc = pc;
+    cpu.sp = 0xff;
+
+    return cpu
+}
+
+
+#[test]
+fn jam_and_illegal_opcodes()
+{
+    let mut bus = FlatBus::new();
+    bus.load(0x0200, &[0x02, 0xa7, 0x10]);                          //JAM, LAX $10
+
+    let mut cpu = cpu_at(0x0200);
+    assert_eq!(cpu.execute(&mut bus), Err(CpuError::Jam { opcode: 0x02, addr: 0x0200 }));
+    assert_eq!(cpu.pc, 0x0200, "a JAM doesn't get past its opcode");
+
+    let mut cpu = cpu_at(0x0201);
+    cpu.strict = true;
+    assert_eq!(cpu.execute(&mut bus), Err(CpuError::IllegalOpcode { opcode: 0xa7, addr: 0x0201 }));
+
+    cpu.strict = false;
+    assert_eq!(cpu.execute(&mut bus), Ok(3), "and it runs once strict mode is off");
+}
+
+#[test]
+fn bus_faults_from_the_memory_map()
+{
+    let mut ram: [u8; 0x1000] = [0; 0x1000];
+    let mut rom: [u8; 0x1000] = [0; 0x1000];
+    rom[0..3].copy_from_slice(&[0xad, 0x00, 0x80]);                 //LDA $8000
+    rom[3..6].copy_from_slice(&[0x8d, 0x10, 0xf0]);                 //STA $F010
+    rom[6..9].copy_from_slice(&[0x8d, 0x00, 0x80]);                 //STA $8000
+    rom[9] = 0xe8;                                                  //INX
+
+    let mut memory = MemoryMap::new(vec![Segment::new(&mut ram, 0, true, true), Segment::new(&mut rom, 0xf000, false, true)]);
+    memory.open_bus = OpenBus::Error;
+    let mut cpu = cpu_at(0xf000);
+    cpu.a = 0x55;
+
+    assert_eq!(cpu.execute(&mut memory), Err(CpuError::BusFault { addr: 0x8000, write: false }));
+    assert_eq!(cpu.pc, 0xf003, "the instruction finishes before the fault is reported");
+    assert_eq!(cpu.execute(&mut memory), Err(CpuError::ReadOnly { addr: 0xf010, data: 0x80 }), "the unmapped read left $80 from the operand on the bus");
+    assert_eq!(cpu.execute(&mut memory), Err(CpuError::BusFault { addr: 0x8000, write: true }));
+    assert_eq!(cpu.execute(&mut memory), Ok(2));
+}
+
+#[test]
+fn dummy_reads_of_unmapped_addresses_are_ignored()
+{
+    let mut ram: [u8; 0x1000] = [0; 0x1000];
+    ram[0x200..0x203].copy_from_slice(&[0xbd, 0xff, 0xff]);         //LDA $FFFF,X: the NMOS part reads $FF00 before the carry
+    ram[0] = 0x77;
+
+    let mut memory = MemoryMap::new(vec![Segment::new(&mut ram, 0, true, true)]);
+    memory.open_bus = OpenBus::Error;
+    let mut cpu = cpu_at(0x0200);
+    cpu.cycle_accurate = true;
+    cpu.x = 0x01;
+
+    assert_eq!(cpu.execute(&mut memory), Ok(5), "the dummy read at $FF00 doesn't count");
+    assert_eq!(cpu.a, 0x77);
+}
+
+#[test]
+fn stack_errors()
+{
+    let mut bus = FlatBus::new();
+    bus.load(0x0200, &[0x48, 0x68]);                                //PHA, PLA
+
+    let mut cpu = cpu_at(0x0200);
+    cpu.stack_check = StackCheck::Halt;
+    cpu.sp = 0x00;
+    assert_eq!(cpu.execute(&mut bus), Err(CpuError::StackOverflow { pc: 0x0201 }));
+    assert_eq!(cpu.execute(&mut bus), Err(CpuError::StackUnderflow { pc: 0x0202 }));
+    assert_eq!(CpuError::StackUnderflow { pc: 0x0202 }.to_string(), "Stack underflow: pulled past $01ff, SP wrapped around to $00 at 0x0202");
+}
@@ -0,0 +1,216 @@
+//Interrupt timing: the level-triggered IRQ line, NMI edges, the one instruction delay after CLI, SEI and PLP,
+//and NMI taking over a BRK.
+
+mod common;
+
+use common::FlatBus;
+use rust65::bus::{Bus, MemoryMap, Segment};
+use rust65::cpu::{CpuStatus, BUS_SOURCE, INPUT_SOURCES};
+use rust65::devices::Device;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const IRQ_HANDLER: u16 = 0x0400;
+const NMI_HANDLER: u16 = 0x0500;
+
+
+fn setup(program: &[u8]) -> (CpuStatus, FlatBus) //program at $0200, NOPs in both handlers
+{
+    let mut bus = FlatBus::new();
+    bus.load(0x0200, program);
+    bus.load(IRQ_HANDLER, &[0xea; 16]);
+    bus.load(NMI_HANDLER, &[0xea; 16]);
+    bus.load(0xfffa, &[NMI_HANDLER as u8, (NMI_HANDLER >> 8) as u8]);
+    bus.load(0xfffe, &[IRQ_HANDLER as u8, (IRQ_HANDLER >> 8) as u8]);
+
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.reset = false;
+    cpu.pc = 0x0200;
+    cpu.sp = 0xff;
+
+    return (cpu, bus)
+}
+
+fn step(cpu: &mut CpuStatus, bus: &mut dyn Bus) -> u16 //run one instruction, returns the new PC
+{
+    if let Err(e) = cpu.execute(bus) { panic!("{}", e) }
+    return cpu.pc
+}
+
+
+#[test]
+fn masked_irq_waits_for_cli()
+{
+    let (mut cpu, mut bus) = setup(&[0xea, 0x58, 0xea, 0xea]);     //NOP, CLI, NOP, NOP
+    cpu.set_interrupt(true);
+    cpu.set_irq(3, true);
+
+    assert_eq!(step(&mut cpu, &mut bus), 0x0201, "IRQ is masked");
+    assert_eq!(step(&mut cpu, &mut bus), 0x0202, "CLI");
+    assert_eq!(step(&mut cpu, &mut bus), 0x0203, "the instruction after CLI runs before the IRQ is taken");
+    assert_eq!(step(&mut cpu, &mut bus), IRQ_HANDLER + 1, "then the IRQ, and the first instruction of the handler");
+    assert_eq!((bus.ram[0x01ff], bus.ram[0x01fe]), (0x02, 0x03));
+    assert_eq!(bus.ram[0x01fd] & 0x30, 0x20, "B is clear in the P pushed by an IRQ");
+}
+
+#[test]
+fn irq_taken_right_after_sei()
+{
+    let (mut cpu, mut bus) = setup(&[0x78, 0xea]);                 //SEI, NOP
+    cpu.set_interrupt(false);
+
+    assert_eq!(step(&mut cpu, &mut bus), 0x0201);
+    cpu.set_irq(0, true);
+    assert_eq!(step(&mut cpu, &mut bus), IRQ_HANDLER + 1, "the poll after SEI still sees I clear");
+    assert_eq!(bus.ram[0x01fd] & 0x04, 0x04, "the pushed P has I set");
+}
+
+#[test]
+fn irq_line_is_wired_or()
+{
+    let (mut cpu, mut bus) = setup(&[0x78, 0xea, 0x58, 0xea, 0xea]);   //SEI, NOP, CLI, NOP, NOP
+    cpu.set_irq(1, true);
+    cpu.set_irq(2, true);
+    cpu.set_irq(1, false);
+    assert!(cpu.irq_line(), "source 2 still holds the line");
+
+    cpu.set_irq(2, false);
+    assert!(!cpu.irq_line());
+
+    cpu.set_interrupt(true);
+    cpu.irq();                                                      //a one-shot request is held while I is set
+    for _ in 0..5 { step(&mut cpu, &mut bus); }
+    assert_eq!(cpu.pc, IRQ_HANDLER + 1);
+}
+
+#[test]
+fn nmi_is_edge_triggered()
+{
+    let (mut cpu, mut bus) = setup(&[0xea; 8]);
+    cpu.set_interrupt(true);
+
+    cpu.set_nmi(0, true);
+    assert_eq!(step(&mut cpu, &mut bus), NMI_HANDLER + 1, "NMI ignores I");
+    assert_eq!(step(&mut cpu, &mut bus), NMI_HANDLER + 2, "holding the line low doesn't retrigger");
+
+    cpu.set_nmi(1, true);
+    assert_eq!(step(&mut cpu, &mut bus), NMI_HANDLER + 3, "a second source on a line that's already low is no edge");
+
+    cpu.set_nmi(0, false);
+    cpu.set_nmi(1, false);
+    cpu.set_nmi(1, true);
+    assert_eq!(step(&mut cpu, &mut bus), NMI_HANDLER + 1);
+}
+
+#[test]
+fn break_flag_only_in_pushed_copy()
+{
+    let (mut cpu, mut bus) = setup(&[0x00, 0xff]);                  //BRK and its signature byte
+    bus.load(IRQ_HANDLER, &[0x40]);                                 //RTI
+    cpu.sr = 0x20;
+
+    assert_eq!(step(&mut cpu, &mut bus), IRQ_HANDLER);
+    assert_eq!(bus.ram[0x01fd], 0x30, "BRK pushes P with B set");
+    assert_eq!(cpu.sr & 0x14, 0x04, "I is set and B isn't, in the live register");
+
+    assert_eq!(step(&mut cpu, &mut bus), 0x0202, "RTI returns past the signature byte");
+    assert_eq!(cpu.sr, 0x20);
+}
+
+
+struct LateNmiBus //a flat bus that pulls NMI low after a given number of reads and writes
+{
+    ram: FlatBus,
+    accesses: u32,
+    nmi_at: u32,
+}
+
+impl Bus for LateNmiBus
+{
+    fn read(&mut self, addr: u16) -> u8
+    {
+        self.accesses += 1;
+        return self.ram.read(addr)
+    }
+
+    fn write(&mut self, addr: u16, data: u8)
+    {
+        self.accesses += 1;
+        self.ram.write(addr, data);
+    }
+
+    fn peek(&self, addr: u16) -> u8
+    {
+        return self.ram.peek(addr)
+    }
+
+    fn update_inputs(&self, cpu: &mut CpuStatus)
+    {
+        cpu.set_nmi(0, self.accesses >= self.nmi_at);
+    }
+}
+
+#[test]
+fn nmi_hijacks_brk()
+{
+    let (mut cpu, ram) = setup(&[0x00, 0xff]);
+    let mut bus = LateNmiBus { ram, accesses: 0, nmi_at: 3 };       //once the opcode is fetched and PC pushed
+
+    assert_eq!(step(&mut cpu, &mut bus), NMI_HANDLER, "BRK goes through the NMI vector");
+    assert_eq!(bus.ram.ram[0x01fd] & 0x10, 0x10, "with B still set in the pushed P");
+    assert_eq!(step(&mut cpu, &mut bus), NMI_HANDLER + 1, "and the NMI isn't taken a second time");
+}
+
+
+struct IrqPin //a device that's nothing but an IRQ output
+{
+    asserted: bool,
+}
+
+impl Device for IrqPin
+{
+    fn read(&mut self, _reg: u16) -> u8 { 0 }
+    fn write(&mut self, _reg: u16, _data: u8) {}
+    fn peek(&self, _reg: u16) -> u8 { 0 }
+    fn irq(&self) -> bool { self.asserted }
+}
+
+#[test]
+fn memory_map_devices_share_one_source()
+{
+    let mut ram: [u8; 0x100] = [0; 0x100];
+    let mut memory = MemoryMap::new(vec![Segment::new(&mut ram, 0, true, true)]);
+    let pins: Vec<Rc<RefCell<IrqPin>>> = (0..40).map(|_| Rc::new(RefCell::new(IrqPin { asserted: false }))).collect();
+    for (i, pin) in pins.iter().enumerate() { memory.attach(0x1000 + i as u16, 0x1000 + i as u16, pin.clone()); }
+
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.set_irq(0, true);                                           //an embedder's own line
+    memory.update_inputs(&mut cpu);
+    assert!(cpu.irq_line(), "the quiet devices don't release someone else's source");
+
+    cpu.set_irq(0, false);
+    pins[35].borrow_mut().asserted = true;
+    memory.update_inputs(&mut cpu);
+    assert!(cpu.irq_line(), "more than 32 devices is fine");
+
+    cpu.set_irq(BUS_SOURCE - 1, false);
+    assert!(cpu.irq_line(), "and nothing else releases the devices' source either");
+    pins[35].borrow_mut().asserted = false;
+    memory.update_inputs(&mut cpu);
+    assert!(!cpu.irq_line());
+}
+
+#[test]
+fn out_of_range_sources_are_ignored()
+{
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.set_irq(INPUT_SOURCES, true);
+    cpu.set_nmi(usize::MAX, true);
+    cpu.set_rdy(100, true);
+    cpu.set_so(INPUT_SOURCES, true);
+
+    assert!(!cpu.irq_line());
+    assert!(!cpu.rdy_held());
+    assert!(!cpu.overflow_flag());
+}
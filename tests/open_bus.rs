@@ -0,0 +1,70 @@
+//What reads and writes that nothing answers do under each open bus policy, and the statistics kept on them.
+
+use rust65::bus::{BusStats, MemoryMap, OpenBus, Segment};
+use rust65::cpu::{CpuError, CpuStatus};
+
+
+const PROGRAM: [u8; 9] = [0xad, 0x00, 0x80,                         //LDA $8000, nothing there
+                          0x8d, 0x00, 0xf0,                         //STA $F000, ROM
+                          0x8d, 0x00, 0x90];                        //STA $9000, nothing there
+
+fn run(open_bus: OpenBus) -> (Vec<Result<u32, CpuError>>, u8, BusStats) //runs PROGRAM from ROM at $F010, returns what each step gave, A, and the statistics
+{
+    let mut ram: [u8; 0x1000] = [0; 0x1000];
+    let mut rom: [u8; 0x1000] = [0; 0x1000];
+    rom[0x10..0x19].copy_from_slice(&PROGRAM[..]);
+
+    let mut memory = MemoryMap::new(vec![Segment::new(&mut ram, 0, true, true), Segment::new(&mut rom, 0xf000, false, true)]);
+    memory.open_bus = open_bus;
+
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.reset = false;
+    cpu.pc = 0xf010;
+
+    let results: Vec<Result<u32, CpuError>> = (0..3).map(|_| cpu.execute(&mut memory)).collect();
+    return (results, cpu.a, memory.stats)
+}
+
+
+#[test]
+fn floating_bus_reads_the_last_value()
+{
+    let (results, a, stats) = run(OpenBus::Float);
+
+    assert!(results.iter().all(|r| r.is_ok()));
+    assert_eq!(a, 0x80, "the high byte of the operand was the last thing on the bus");
+    assert_eq!(stats, BusStats { unmapped_reads: 1, unmapped_writes: 1, read_only_writes: 1 });
+}
+
+#[test]
+fn fill_byte()
+{
+    let (results, a, stats) = run(OpenBus::Fill(0xff));
+
+    assert!(results.iter().all(|r| r.is_ok()));
+    assert_eq!(a, 0xff);
+    assert_eq!(stats.unmapped_reads, 1);
+}
+
+#[test]
+fn trap_into_the_debugger()
+{
+    let (results, _, stats) = run(OpenBus::Trap);
+
+    assert_eq!(results, vec![Err(CpuError::BusTrap { addr: 0x8000, write: false }), Err(CpuError::BusTrap { addr: 0xf000, write: true }), Err(CpuError::BusTrap { addr: 0x9000, write: true })]);
+    assert_eq!(stats, BusStats { unmapped_reads: 1, unmapped_writes: 1, read_only_writes: 1 });
+}
+
+#[test]
+fn policy_names()
+{
+    assert_eq!(OpenBus::from_name("float"), Some(OpenBus::Float));
+    assert_eq!(OpenBus::from_name("Trap"), Some(OpenBus::Trap));
+    assert_eq!(OpenBus::from_name("error"), Some(OpenBus::Error));
+    assert_eq!(OpenBus::from_name("aa"), Some(OpenBus::Fill(0xaa)));
+    assert_eq!(OpenBus::from_name("0xFF"), Some(OpenBus::Fill(0xff)));
+    assert_eq!(OpenBus::from_name("$ea"), Some(OpenBus::Fill(0xea)));
+    assert_eq!(OpenBus::from_name("0x"), None);
+    assert_eq!(OpenBus::from_name("0x100"), None);
+    assert_eq!(OpenBus::from_name("sometimes"), None);
+}
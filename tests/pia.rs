@@ -0,0 +1,228 @@
+//The 6821 PIA: DDR or output register selected by CRx bit 2, C1 and C2 edge flags, the IRQA and IRQB outputs, and the
+//C2 handshake, pulse and manual output modes, with the handshake the Apple I display relies on.
+
+use rust65::devices::Device;
+use rust65::devices::pia::Pia6821;
+
+const PORT_A: u16 = 0;
+const CRA: u16 = 1;
+const PORT_B: u16 = 2;
+const CRB: u16 = 3;
+
+
+#[test]
+fn cr_bit_2_selects_ddr_or_output()
+{
+    let mut pia = Pia6821::new();
+    pia.set_port_a_input(0xa5);
+
+    pia.write(PORT_A, 0x0f);                                    //CRA is 0 after reset, so this is the DDR
+    assert_eq!(pia.a.ddr, 0x0f);
+    assert_eq!(pia.a.output, 0x00);
+    assert_eq!(pia.read(PORT_A), 0x0f, "the DDR reads back");
+
+    pia.write(CRA, 0x04);
+    pia.write(PORT_A, 0x3c);
+    assert_eq!((pia.a.ddr, pia.a.output), (0x0f, 0x3c));
+    assert_eq!(pia.read(PORT_A), 0xac, "output bits from the output register, input bits from the pins");
+    assert_eq!(pia.port_a_output(), 0xfc, "input bits float high on the pins");
+
+    pia.write(PORT_B, 0xff);
+    pia.write(CRB, 0x04);
+    pia.write(PORT_B, 0x81);
+    assert_eq!((pia.b.ddr, pia.b.output), (0xff, 0x81));
+    assert_eq!(pia.port_b_output(), 0x81);
+    assert_eq!(pia.peek(PORT_A), 0xac, "peek and read agree");
+}
+
+#[test]
+fn ca1_falling_edge_flag()
+{
+    let mut pia = Pia6821::new();
+    pia.write(CRA, 0x04);                                       //output register, CA1 active on the falling edge, IRQ off
+
+    pia.set_ca1(true);
+    assert_eq!(pia.read(CRA) & 0x80, 0, "a rising edge isn't the active one");
+    pia.set_ca1(false);
+    assert_eq!(pia.read(CRA) & 0x80, 0x80);
+    assert!(!pia.irqa(), "the flag is set but the interrupt isn't enabled");
+
+    pia.write(CRA, 0x05);
+    assert_eq!(pia.read(CRA) & 0x80, 0x80, "writing CRA leaves the flag alone");
+    assert!(pia.irqa() && pia.irq());
+
+    pia.read(PORT_A);
+    assert_eq!(pia.read(CRA) & 0x80, 0, "reading the data register clears the flag");
+    assert!(!pia.irqa() && !pia.irq());
+}
+
+#[test]
+fn cb1_rising_edge_flag()
+{
+    let mut pia = Pia6821::new();
+    pia.write(CRB, 0x03);                                       //DDR selected, CB1 active on the rising edge, IRQ on
+
+    pia.set_cb1(true);
+    assert_eq!(pia.read(CRB) & 0x80, 0x80);
+    assert!(pia.irqb() && pia.irq());
+    assert!(!pia.irqa());
+
+    pia.read(PORT_B);
+    assert!(pia.irqb(), "reading the DDR doesn't clear the flag");
+
+    pia.write(CRB, 0x07);
+    pia.read(PORT_B);
+    assert!(!pia.irqb(), "reading the data register does");
+
+    pia.set_cb1(false);
+    assert!(!pia.irqb(), "a falling edge isn't the active one");
+}
+
+#[test]
+fn c2_input_flags()
+{
+    let mut pia = Pia6821::new();
+    pia.write(CRA, 0x04 | 0x08 | 0x10);                         //CA2 input, IRQ on, active on the rising edge
+
+    pia.set_ca2(false);
+    assert!(!pia.irqa());
+    pia.set_ca2(true);
+    assert_eq!(pia.read(CRA) & 0x40, 0x40);
+    assert!(pia.irqa());
+
+    pia.read(PORT_A);
+    assert_eq!(pia.read(CRA) & 0xc0, 0, "reading the data register clears both flags");
+    assert!(!pia.irqa());
+
+    pia.write(CRB, 0x04);                                       //CB2 input, falling edge, IRQ off
+    pia.set_cb2(false);
+    assert_eq!(pia.read(CRB) & 0x40, 0x40);
+    assert!(!pia.irqb(), "the flag is set but the interrupt isn't enabled");
+}
+
+#[test]
+fn irqa_and_irqb_are_separate()
+{
+    let mut pia = Pia6821::new();
+    pia.write(CRA, 0x05);
+    pia.write(CRB, 0x05);
+
+    pia.set_ca1(true);
+    pia.set_ca1(false);
+    assert!(pia.irqa() && !pia.irqb());
+
+    pia.set_cb1(true);
+    pia.set_cb1(false);
+    assert!(pia.irqa() && pia.irqb());
+
+    pia.read(PORT_A);
+    assert!(!pia.irqa() && pia.irqb());
+    assert!(pia.irq(), "the IRQ output is either of them");
+
+    pia.read(PORT_B);
+    assert!(!pia.irq());
+}
+
+#[test]
+fn ca2_read_handshake()
+{
+    let mut pia = Pia6821::new();
+    pia.write(CRA, 0x24);                                       //CA2 output in handshake mode, CA1 active on the falling edge
+    assert!(pia.ca2(), "the handshake idles high");
+
+    pia.read(CRA);
+    assert!(pia.ca2(), "reading the control register isn't a data access");
+    pia.read(PORT_A);
+    assert!(!pia.ca2(), "reading port A takes CA2 low");
+    pia.tick();
+    assert!(!pia.ca2(), "and it stays low");
+
+    pia.set_ca1(true);
+    assert!(!pia.ca2(), "until the active edge of CA1");
+    pia.set_ca1(false);
+    assert!(pia.ca2());
+
+    pia.write(PORT_A, 0x55);
+    assert!(pia.ca2(), "writing port A doesn't start a handshake");
+}
+
+#[test]
+fn cb2_write_handshake()
+{
+    let mut pia = Pia6821::new();
+    pia.write(CRB, 0x26);                                       //CB2 output in handshake mode, CB1 active on the rising edge
+
+    pia.read(PORT_B);
+    assert!(pia.cb2(), "reading port B doesn't start a handshake");
+    pia.write(PORT_B, 0x41);
+    assert!(!pia.cb2(), "writing port B takes CB2 low");
+
+    pia.set_cb1(false);
+    assert!(!pia.cb2());
+    pia.set_cb1(true);
+    assert!(pia.cb2(), "the active edge of CB1 brings it back high");
+    assert_eq!(pia.read(CRB) & 0xc0, 0x80, "and sets IRQB1, IRQB2 always reads 0 while CB2 is an output");
+}
+
+#[test]
+fn c2_pulse_output()
+{
+    let mut pia = Pia6821::new();
+    pia.write(CRA, 0x2c);                                       //CA2 output in pulse mode
+    pia.write(CRB, 0x2c);
+
+    pia.read(PORT_A);
+    assert!(!pia.ca2(), "reading port A starts the pulse");
+    pia.set_ca1(true);
+    pia.set_ca1(false);
+    assert!(!pia.ca2(), "CA1 doesn't end a pulse");
+    pia.tick();
+    assert!(pia.ca2(), "it lasts one cycle");
+
+    pia.write(PORT_B, 0x00);
+    assert!(!pia.cb2());
+    pia.tick();
+    assert!(pia.cb2());
+    pia.tick();
+    assert!(pia.cb2());
+}
+
+#[test]
+fn c2_manual_output()
+{
+    let mut pia = Pia6821::new();
+    pia.write(CRA, 0x34);                                       //CA2 output, manual, low
+    assert!(!pia.ca2());
+    pia.read(PORT_A);
+    pia.set_ca1(true);
+    pia.set_ca1(false);
+    assert!(!pia.ca2(), "data accesses and CA1 leave a manual output alone");
+
+    pia.write(CRA, 0x3c);
+    assert!(pia.ca2(), "CRA bit 3 is the level");
+
+    pia.write(CRA, 0x24);
+    pia.read(PORT_A);
+    pia.write(CRA, 0x3c);
+    pia.write(CRA, 0x24);
+    assert!(pia.ca2(), "switching modes abandons a handshake in progress");
+}
+
+#[test]
+fn apple_display_handshake()
+{
+    let mut pia = Pia6821::new();
+    pia.write(PORT_B, 0x7f);                                    //what the Woz monitor does: PB7 in for the display's busy line
+    pia.write(CRB, 0xa7);                                       //then the output register, CB2 handshake, CB1 rising edge
+
+    pia.write(PORT_B, b'A' | 0x80);
+    assert!(!pia.cb2(), "the character is on PB0-PB6 and CB2 tells the display to take it");
+    assert_eq!(pia.port_b_output() & 0x7f, b'A');
+
+    pia.set_cb1(true);                                          //the display's ready strobe
+    pia.set_cb1(false);
+    assert!(pia.cb2(), "the display is done with it");
+    assert!(pia.irqb(), "CRB bit 0 is set, so the strobe also interrupts");
+    pia.read(PORT_B);
+    assert!(!pia.irqb());
+}
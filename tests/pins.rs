@@ -0,0 +1,173 @@
+//The RDY, SO and RESET inputs: stalling on read cycles, setting V on an SO edge, and the reset sequence.
+
+mod common;
+
+use common::FlatBus;
+use rust65::bus::Bus;
+use rust65::cpu::{CpuStatus, Variant};
+
+
+struct DmaBus //a flat bus that holds RDY low for a window of clock cycles, and logs every access
+{
+    ram: FlatBus,
+    clock: u32,
+    hold: (u32, u32),
+    log: Vec<(u16, bool)>,
+}
+
+impl Bus for DmaBus
+{
+    fn read(&mut self, addr: u16) -> u8
+    {
+        self.log.push((addr, true));
+        return self.ram.read(addr)
+    }
+
+    fn write(&mut self, addr: u16, data: u8)
+    {
+        self.log.push((addr, false));
+        self.ram.write(addr, data);
+    }
+
+    fn peek(&self, addr: u16) -> u8
+    {
+        return self.ram.peek(addr)
+    }
+
+    fn cycle(&mut self)
+    {
+        self.clock += 1;
+    }
+
+    fn update_inputs(&self, cpu: &mut CpuStatus)
+    {
+        cpu.set_rdy(0, self.clock >= self.hold.0 && self.clock < self.hold.1);
+    }
+}
+
+
+fn setup(variant: Variant, program: &[u8], hold: (u32, u32)) -> (CpuStatus, DmaBus) //program at $0200, cycle accurate
+{
+    let mut ram = FlatBus::new();
+    ram.load(0x0200, program);
+
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.variant = variant;
+    cpu.cycle_accurate = true;
+    cpu.reset = false;
+    cpu.pc = 0x0200;
+    cpu.sp = 0xff;
+
+    return (cpu, DmaBus { ram, clock: 0, hold, log: Vec::new() })
+}
+
+
+#[test]
+fn rdy_stretches_read_cycles()
+{
+    let (mut cpu, mut bus) = setup(Variant::Nmos, &[0xa5, 0x10], (2, 5));     //LDA $10, RDY low over its data read
+    bus.ram.ram[0x10] = 0x42;
+
+    assert_eq!(cpu.execute(&mut bus), Ok(6));
+    assert_eq!(cpu.a, 0x42);
+    assert_eq!(bus.log, vec![(0x0200, true), (0x0201, true), (0x0010, true), (0x0010, true), (0x0010, true), (0x0010, true)], "the stalled read repeats");
+}
+
+#[test]
+fn nmos_write_cycles_ignore_rdy()
+{
+    let (mut cpu, mut bus) = setup(Variant::Nmos, &[0x85, 0x10, 0xea], (2, 5));  //STA $10, NOP
+    cpu.a = 0x99;
+
+    assert_eq!(cpu.execute(&mut bus), Ok(3), "the write goes ahead");
+    assert_eq!(bus.ram.ram[0x10], 0x99);
+
+    assert_eq!(cpu.execute(&mut bus), Ok(1), "and the next opcode fetch stalls");
+    assert_eq!(cpu.pc, 0x0202);
+}
+
+#[test]
+fn cmos_write_cycles_wait_for_rdy()
+{
+    let (mut cpu, mut bus) = setup(Variant::Cmos, &[0x85, 0x10], (2, 5));
+    cpu.a = 0x99;
+
+    assert_eq!(cpu.execute(&mut bus), Ok(6));
+    assert_eq!(bus.log.last(), Some(&(0x0010, false)));
+    assert_eq!(bus.log.len(), 3, "stalled write cycles make no access");
+}
+
+#[test]
+fn rdy_stalls_at_instruction_boundary()
+{
+    let mut bus = FlatBus::new();
+    bus.load(0x0200, &[0xe8]);                                         //INX
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.reset = false;
+    cpu.pc = 0x0200;
+
+    cpu.set_rdy(5, true);
+    assert_eq!(cpu.execute(&mut bus), Ok(1));
+    assert_eq!((cpu.pc, cpu.x), (0x0200, 0));
+
+    cpu.set_rdy(5, false);
+    assert_eq!(cpu.execute(&mut bus), Ok(2));
+    assert_eq!((cpu.pc, cpu.x), (0x0201, 1));
+}
+
+#[test]
+fn so_sets_overflow_on_falling_edge()
+{
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.set_overflow(false);
+
+    cpu.set_so(0, true);
+    assert_eq!(cpu.sr & 0x40, 0x40);
+
+    cpu.set_overflow(false);
+    cpu.set_so(1, true);
+    assert_eq!(cpu.sr & 0x40, 0, "the line was already low");
+
+    cpu.set_so(0, false);
+    cpu.set_so(1, false);
+    cpu.set_so(1, true);
+    assert_eq!(cpu.sr & 0x40, 0x40);
+}
+
+#[test]
+fn reset_sequence()
+{
+    let mut bus = FlatBus::new();
+    bus.load(0xfffc, &[0x00, 0x03]);
+    bus.load(0x0300, &[0xea]);                                         //NOP
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.sp = 0xff;
+    cpu.sr = 0x20;
+
+    assert_eq!(cpu.execute(&mut bus), Ok(9), "seven cycles of reset, then the first instruction");
+    assert_eq!(cpu.pc, 0x0301);
+    assert_eq!(cpu.sp, 0xfc, "three pushes with writes turned off");
+    assert_eq!(cpu.sr, 0x24, "I is set");
+    assert_eq!(bus.ram[0x01fd..0x0200], [0, 0, 0], "and nothing was written");
+}
+
+#[test]
+fn reset_pin_holds_the_cpu()
+{
+    let mut bus = FlatBus::new();
+    bus.load(0xfffc, &[0x00, 0x03]);
+    bus.load(0x0200, &[0xea]);
+    bus.load(0x0300, &[0xea]);
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.reset = false;
+    cpu.pc = 0x0200;
+
+    cpu.set_reset(true);
+    assert_eq!(cpu.execute(&mut bus), Ok(1));
+    assert_eq!(cpu.execute(&mut bus), Ok(1));
+    assert_eq!(cpu.pc, 0x0200, "nothing runs while RESET is low");
+
+    cpu.set_reset(false);
+    assert_eq!(cpu.execute(&mut bus), Ok(9));
+    assert_eq!(cpu.pc, 0x0301);
+}
@@ -0,0 +1,246 @@
+//Single instruction tests in the format of the community SingleStepTests/65x02 suite.
+//
+//Each case gives the registers and the RAM a CPU starts with, the registers and RAM it should finish with after one
+//instruction, and every bus cycle the instruction makes along the way. The CPU runs in cycle accurate mode so the
+//bus traces can be compared, dummy accesses and all. A small set of cases ships in tests/single_step (one file per
+//opcode, laid out like the suite itself) so it runs offline. They aren't taken from the suite: they come from a
+//separate model of each CPU in tests/single_step/generate.py, which rebuilds them, so treat them as a smoke test. Set
+//SINGLE_STEP_TESTS to the root of a checkout of the full suite and run the ignored tests to run the real thing.
+
+use rust65::bus::Bus;
+use rust65::cpu::{CpuError, CpuStatus, Variant};
+
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SUITE_CPUS: [(&str, Variant); 4] = [("6502", Variant::Nmos), ("synertek65c02", Variant::Cmos), ("rockwell65c02", Variant::Rockwell), ("wdc65c02", Variant::Wdc)];
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Cycle //one bus cycle: address, data, and whether it was a read
+{
+    addr: u16,
+    data: u8,
+    read: bool,
+}
+
+struct RecordingBus //64K of RAM that logs every access made to it
+{
+    ram: Vec<u8>,
+    cycles: Vec<Cycle>,
+}
+
+impl Bus for RecordingBus
+{
+    fn read(&mut self, addr: u16) -> u8
+    {
+        let data: u8 = self.ram[addr as usize];
+        self.cycles.push(Cycle { addr, data, read: true });
+        return data
+    }
+
+    fn write(&mut self, addr: u16, data: u8)
+    {
+        self.ram[addr as usize] = data;
+        self.cycles.push(Cycle { addr, data, read: false });
+    }
+
+    fn peek(&self, addr: u16) -> u8
+    {
+        return self.ram[addr as usize]
+    }
+}
+
+
+#[derive(Default)]
+struct Tally //results for one opcode
+{
+    cases: u32,
+    registers: u32,
+    ram: u32,
+    cycles: u32,
+    trace: u32,
+    first_failure: Option<String>,
+}
+
+impl Tally
+{
+    fn failed(&self) -> bool
+    {
+        return self.registers + self.ram + self.cycles + self.trace > 0
+    }
+}
+
+
+fn number(value: &Value, key: &str) -> u16
+{
+    return value[key].as_u64().unwrap_or_else(|| panic!("missing {}", key)) as u16
+}
+
+fn ram_list(state: &Value) -> Vec<(u16, u8)>
+{
+    let mut list: Vec<(u16, u8)> = Vec::new();
+
+    for pair in state["ram"].as_array().expect("missing ram")
+    {
+        list.push((pair[0].as_u64().unwrap() as u16, pair[1].as_u64().unwrap() as u8));
+    }
+
+    return list
+}
+
+fn cycle_list(case: &Value) -> Vec<Cycle>
+{
+    let mut list: Vec<Cycle> = Vec::new();
+
+    for cycle in case["cycles"].as_array().expect("missing cycles")
+    {
+        list.push(Cycle { addr: cycle[0].as_u64().unwrap() as u16, data: cycle[1].as_u64().unwrap() as u8, read: cycle[2] == "read" });
+    }
+
+    return list
+}
+
+
+fn run_case(case: &Value, variant: Variant, tally: &mut Tally)
+{
+    let initial: &Value = &case["initial"];
+    let expected: &Value = &case["final"];
+    let expected_cycles: Vec<Cycle> = cycle_list(case);
+
+    let mut bus = RecordingBus { ram: vec![0; 0x10000], cycles: Vec::new() };
+    for (addr, data) in ram_list(initial) { bus.ram[addr as usize] = data }
+
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.variant = variant;
+    cpu.cycle_accurate = true;
+    cpu.reset = false;
+    cpu.pc = number(initial, "pc");
+    cpu.sp = number(initial, "s") as u8;
+    cpu.a = number(initial, "a") as u8;
+    cpu.x = number(initial, "x") as u8;
+    cpu.y = number(initial, "y") as u8;
+    cpu.sr = number(initial, "p") as u8;
+
+    let result: Result<u32, CpuError> = cpu.execute(&mut bus);
+
+    let mut problems: Vec<String> = Vec::new();
+
+    let registers: [(&str, u16, u16); 6] = [("pc", cpu.pc, number(expected, "pc")), ("s", cpu.sp as u16, number(expected, "s")), ("a", cpu.a as u16, number(expected, "a")),
+                                            ("x", cpu.x as u16, number(expected, "x")), ("y", cpu.y as u16, number(expected, "y")), ("p", cpu.sr as u16, number(expected, "p"))];
+    let wrong: Vec<String> = registers.iter().filter(|(_, got, want)| got != want).map(|(name, got, want)| format!("{} {:#x} not {:#x}", name, got, want)).collect();
+    if let Err(e) = &result { problems.push(e.to_string()) }
+    if !wrong.is_empty() || result.is_err()
+    {
+        tally.registers += 1;
+        problems.extend(wrong);
+    }
+
+    let wrong: Vec<String> = ram_list(expected).iter().filter(|(addr, data)| bus.ram[*addr as usize] != *data).map(|(addr, data)| format!("{:#06x} = {:#04x} not {:#04x}", addr, bus.ram[*addr as usize], data)).collect();
+    if !wrong.is_empty()
+    {
+        tally.ram += 1;
+        problems.extend(wrong);
+    }
+
+    if cpu.cycles_used as usize != expected_cycles.len()
+    {
+        tally.cycles += 1;
+        problems.push(format!("{} cycles not {}", cpu.cycles_used, expected_cycles.len()));
+    }
+
+    if bus.cycles != expected_cycles
+    {
+        tally.trace += 1;
+        problems.push(format!("bus trace {:?} not {:?}", bus.cycles, expected_cycles));
+    }
+
+    tally.cases += 1;
+    if !problems.is_empty() && tally.first_failure.is_none()
+    {
+        tally.first_failure = Some(format!("{}: {}", case["name"].as_str().unwrap_or("?"), problems.join(", ")));
+    }
+}
+
+
+fn run_directory(dir: &Path, variant: Variant, results: &mut BTreeMap<(String, u8), Tally>) //runs every <opcode>.json in dir
+{
+    let mut files: Vec<PathBuf> = fs::read_dir(dir).unwrap_or_else(|e| panic!("{}: {}", dir.display(), e)).map(|entry| entry.unwrap().path()).collect();
+    files.sort();
+
+    let cpu_name: String = dir.parent().and_then(|p| p.file_name()).map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+
+    for file in files
+    {
+        let opcode: u8 = match file.file_stem().and_then(|s| s.to_str()).and_then(|s| u8::from_str_radix(s, 16).ok())
+        {
+            Some(op) if file.extension().is_some_and(|e| e == "json") => op,
+            _ => continue,
+        };
+
+        let text: String = fs::read_to_string(&file).unwrap();
+        let cases: Value = serde_json::from_str(&text).unwrap_or_else(|e| panic!("{}: {}", file.display(), e));
+        let tally: &mut Tally = results.entry((cpu_name.clone(), opcode)).or_default();
+
+        for case in cases.as_array().expect("expected a list of test cases")
+        {
+            run_case(case, variant, tally);
+        }
+    }
+}
+
+
+fn summary(results: &BTreeMap<(String, u8), Tally>) -> bool //prints a table of the opcodes with mismatches, returns whether everything passed
+{
+    let cases: u32 = results.values().map(|tally| tally.cases).sum();
+
+    let rows: Vec<(&(String, u8), &Tally)> = results.iter().filter(|(_, tally)| tally.failed()).collect();
+    if !rows.is_empty()
+    {
+        println!("{:<14} {:>6} {:>6} {:>10} {:>6} {:>7} {:>6}  first failure", "cpu", "opcode", "cases", "registers", "ram", "cycles", "trace");
+
+        for ((cpu, opcode), tally) in rows.iter()
+        {
+            println!("{:<14} {:>6} {:>6} {:>10} {:>6} {:>7} {:>6}  {}", cpu, format!("{:02x}", opcode), tally.cases, tally.registers, tally.ram, tally.cycles, tally.trace, tally.first_failure.as_deref().unwrap_or(""));
+        }
+    }
+
+    println!("{} opcodes, {} cases", results.len(), cases);
+    return rows.is_empty()
+}
+
+
+fn run_suite(root: &Path)
+{
+    let mut results: BTreeMap<(String, u8), Tally> = BTreeMap::new();
+
+    for (name, variant) in SUITE_CPUS
+    {
+        let dir: PathBuf = root.join(name).join("v1");
+        if dir.is_dir() { run_directory(&dir, variant, &mut results) }
+    }
+
+    assert!(!results.is_empty(), "no test cases found under {}", root.display());
+    assert!(summary(&results), "single step tests failed, see the table above");
+}
+
+#[test]
+fn curated_cases()
+{
+    run_suite(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/single_step"));
+}
+
+#[test]
+#[ignore = "set SINGLE_STEP_TESTS"]
+fn full_suite()
+{
+    match env::var_os("SINGLE_STEP_TESTS")
+    {
+        Some(root) => run_suite(Path::new(&root)),
+        None => panic!("SINGLE_STEP_TESTS should be the root of a checkout of the SingleStepTests/65x02 suite"),
+    }
+}
@@ -0,0 +1,258 @@
+#!/usr/bin/env python3
+#Generates the single step cases in tests/single_step, in the format of the SingleStepTests/65x02 suite.
+#
+#This is a separate, deliberately simple model of each CPU: a handful of opcodes, each written out bus cycle by bus
+#cycle, sharing no code with the emulator. It makes the curated cases something that can be read, checked and
+#rebuilt here, but it's a second opinion, not hardware: the full suite (SINGLE_STEP_TESTS) is still the real oracle.
+#Every opcode has its own seed, so adding one doesn't change the others' cases.
+#
+#    python3 tests/single_step/generate.py
+
+import json, os, random
+
+class Sim: #one CPU, its registers and the RAM it can see, logging every bus cycle
+    def __init__(s, st, ram, cmos=False):
+        s.pc, s.s, s.a, s.x, s.y, s.p = st['pc'], st['s'], st['a'], st['x'], st['y'], st['p']
+        s.ram = dict(ram); s.cyc = []; s.cmos = cmos
+    def rd(s, a):
+        a &= 0xffff; v = s.ram.get(a, 0); s.cyc.append([a, v, 'read']); return v
+    def wr(s, a, v):
+        a &= 0xffff; s.ram[a] = v & 0xff; s.cyc.append([a, v & 0xff, 'write'])
+    def fetch(s):
+        v = s.rd(s.pc); s.pc = (s.pc + 1) & 0xffff; return v
+    def setnz(s, v):
+        s.p = (s.p & ~0x82) | (v & 0x80) | (0x02 if (v & 0xff) == 0 else 0)
+    def flag(s, m, on):
+        s.p = (s.p | m) if on else (s.p & ~m)
+    # addressing
+    def zp(s): return s.fetch()
+    def abs_(s): lo = s.fetch(); hi = s.fetch(); return hi << 8 | lo
+    #on a page crossing the NMOS part reads the unfixed address, the 65C02 re-reads the last instruction byte. That's from
+    #the "Microprocessor Operational Enhancements" table in the W65C02S and R65C02 datasheets, and goes for (zp),Y too
+    def absx(s, idx, always_dummy):
+        lo = s.fetch(); hi = s.fetch(); base = hi << 8 | lo; addr = (base + idx) & 0xffff
+        if always_dummy or (base ^ addr) & 0xff00:
+            s.rd((s.pc - 1) & 0xffff) if s.cmos and (base ^ addr) & 0xff00 else s.rd(hi << 8 | ((lo + idx) & 0xff))
+        return addr
+    def izx(s):
+        z = s.fetch(); s.rd(z); p = (z + s.x) & 0xff
+        lo = s.rd(p); hi = s.rd((p + 1) & 0xff); return hi << 8 | lo
+    def izy(s, always_dummy):
+        z = s.fetch(); lo = s.rd(z); hi = s.rd((z + 1) & 0xff); base = hi << 8 | lo; addr = (base + s.y) & 0xffff
+        if always_dummy or (base ^ addr) & 0xff00:
+            s.rd((s.pc - 1) & 0xffff) if s.cmos and (base ^ addr) & 0xff00 else s.rd(hi << 8 | ((lo + s.y) & 0xff))
+        return addr
+    def implied(s): s.rd(s.pc)
+    def adc(s, b):
+        c = s.p & 1; a = s.a
+        binary = a + b + c
+        if s.p & 8:
+            al = (a & 0xf) + (b & 0xf) + c
+            if al >= 0xa: al = ((al + 6) & 0xf) + 0x10
+            r = (a & 0xf0) + (b & 0xf0) + al
+            def sg(v): return v - 256 if v >= 128 else v
+            signed = sg(a & 0xf0) + sg(b & 0xf0) + al
+            s.flag(0x80, r & 0x80); s.flag(0x40, signed < -128 or signed > 127); s.flag(0x02, binary & 0xff == 0)
+            if r >= 0xa0: r += 0x60
+            s.flag(1, r > 0xff); s.a = r & 0xff
+            if s.cmos: s.setnz(s.a)
+        else:
+            s.flag(1, binary > 0xff); s.flag(0x40, (a ^ binary) & (b ^ binary) & 0x80)
+            s.a = binary & 0xff; s.setnz(s.a)
+    def sbc(s, b):
+        c = s.p & 1; a = s.a; borrow = 1 - c
+        binary = a + (b ^ 0xff) + c
+        s.flag(1, binary > 0xff); s.flag(0x40, (a ^ binary) & ((b ^ 0xff) ^ binary) & 0x80); s.setnz(binary & 0xff)
+        if s.p & 8:
+            al = (a & 0xf) - (b & 0xf) - borrow
+            if not s.cmos:
+                if al < 0: al = ((al - 6) & 0xf) - 0x10
+                r = (a & 0xf0) - (b & 0xf0) + al
+                if r < 0: r -= 0x60
+            else:
+                r = a - b - borrow
+                if r < 0: r -= 0x60
+                if al < 0: r -= 6
+            s.a = r & 0xff
+            if s.cmos: s.setnz(s.a)
+        else:
+            s.a = binary & 0xff
+    def branch(s, cond):
+        off = s.fetch()
+        if cond:
+            s.rd(s.pc)
+            t = (s.pc + (off - 256 if off >= 128 else off)) & 0xffff
+            if (t ^ s.pc) & 0xff00: s.rd((s.pc & 0xff00) | (t & 0xff))
+            s.pc = t
+
+def lda(s, v): s.a = v; s.setnz(v)
+
+OPS = {
+ 0xa9: lambda s: lda(s, s.fetch()),
+ 0xad: lambda s: lda(s, s.rd(s.abs_())),
+ 0xbd: lambda s: lda(s, s.rd(s.absx(s.x, False))),
+ 0xb9: lambda s: lda(s, s.rd(s.absx(s.y, False))),
+ 0xa1: lambda s: lda(s, s.rd(s.izx())),
+ 0xb1: lambda s: lda(s, s.rd(s.izy(False))),
+ 0x69: lambda s: s.adc(s.fetch()),
+ 0x65: lambda s: s.adc(s.rd(s.zp())),
+ 0xe9: lambda s: s.sbc(s.fetch()),
+ 0xf1: lambda s: s.sbc(s.rd(s.izy(False))),
+ 0x18: lambda s: (s.implied(), s.flag(1, False)),
+ 0xe8: lambda s: (s.implied(), setattr(s, 'x', (s.x + 1) & 0xff), s.setnz(s.x)),
+ 0xaa: lambda s: (s.implied(), setattr(s, 'x', s.a), s.setnz(s.x)),
+ 0x9d: lambda s: s.wr(s.absx(s.x, True), s.a),
+ 0x91: lambda s: s.wr(s.izy(True), s.a),
+ 0x85: lambda s: s.wr(s.zp(), s.a),
+ 0x24: lambda s: bit(s, s.rd(s.zp())),
+ 0xc9: lambda s: cmp(s, s.a, s.fetch()),
+ 0x4c: lambda s: setattr(s, 'pc', s.abs_()),
+}
+def bit(s, v):
+    s.flag(0x80, v & 0x80); s.flag(0x40, v & 0x40); s.flag(0x02, (v & s.a) == 0)
+def cmp(s, r, v):
+    s.flag(1, r >= v); s.flag(0x02, r == v); s.flag(0x80, (r - v) & 0x80)
+def asl_a(s):
+    s.implied(); s.flag(1, s.a & 0x80); s.a = (s.a << 1) & 0xff; s.setnz(s.a)
+OPS[0x0a] = asl_a
+def rmw(fn, mode):
+    def run(s):
+        addr = s.abs_() if mode == 'abs' else s.absx(s.x, True)
+        v = s.rd(addr); s.wr(addr, v); n = fn(s, v); s.wr(addr, n)
+    return run
+def inc(s, v): n = (v + 1) & 0xff; s.setnz(n); return n
+def asl(s, v): s.flag(1, v & 0x80); n = (v << 1) & 0xff; s.setnz(n); return n
+def lsr(s, v): s.flag(1, v & 1); n = v >> 1; s.setnz(n); return n
+OPS[0xee] = rmw(inc, 'abs'); OPS[0x1e] = rmw(asl, 'absx'); OPS[0x5e] = rmw(lsr, 'absx')
+def jmp_ind(s):
+    ptr = s.abs_(); lo = s.rd(ptr); hi = s.rd((ptr & 0xff00) | ((ptr + 1) & 0xff)); s.pc = hi << 8 | lo
+OPS[0x6c] = jmp_ind
+for op, m, want in ((0xd0, 0x02, False), (0xf0, 0x02, True), (0x10, 0x80, False), (0xb0, 0x01, True)):
+    OPS[op] = (lambda m, want: lambda s: s.branch(bool(s.p & m) == want))(m, want)
+# undocumented
+def lax(s, v): s.a = s.x = v; s.setnz(v)
+OPS[0xa7] = lambda s: lax(s, s.rd(s.zp()))
+OPS[0x8f] = lambda s: s.wr(s.abs_(), s.a & s.x)
+def sbx(s):
+    v = s.fetch(); t = s.a & s.x; cmp(s, t, v); s.x = (t - v) & 0xff
+OPS[0xcb] = sbx
+def dcp(s):
+    addr = s.zp(); v = s.rd(addr); s.wr(addr, v); n = (v - 1) & 0xff; s.wr(addr, n); cmp(s, s.a, n)
+OPS[0xc7] = dcp
+OPS[0x04] = lambda s: s.rd(s.zp())   # NOP zp
+
+
+def push(s, v): s.wr(0x100 | s.s, v); s.s = (s.s - 1) & 0xff
+def pull(s): s.s = (s.s + 1) & 0xff; return s.rd(0x100 | s.s)
+def stack_dummy(s): s.rd(0x100 | s.s)
+def pha(s): s.implied(); push(s, s.a)
+def php(s): s.implied(); push(s, s.p | 0x30)
+def pla(s): s.implied(); stack_dummy(s); s.a = pull(s); s.setnz(s.a)
+def plp(s): s.implied(); stack_dummy(s); v = pull(s); s.p = (s.p & 0x30) | (v & 0xcf)
+def jsr(s):
+    lo = s.fetch(); stack_dummy(s); push(s, s.pc >> 8); push(s, s.pc & 0xff); hi = s.rd(s.pc); s.pc = hi << 8 | lo
+def rts(s):
+    s.implied(); stack_dummy(s); lo = pull(s); hi = pull(s); s.rd(hi << 8 | lo); s.pc = ((hi << 8 | lo) + 1) & 0xffff
+def rti(s):
+    s.implied(); stack_dummy(s); v = pull(s); s.p = (s.p & 0x30) | (v & 0xcf); lo = pull(s); hi = pull(s); s.pc = hi << 8 | lo
+def txs(s): s.implied(); s.s = s.x
+def tsx(s): s.implied(); s.x = s.s; s.setnz(s.x)
+def phx(s): s.implied(); push(s, s.x)
+def ply(s): s.implied(); stack_dummy(s); s.y = pull(s); s.setnz(s.y)
+for op, fn in ((0x48, pha), (0x08, php), (0x68, pla), (0x28, plp), (0x20, jsr), (0x60, rts), (0x40, rti), (0x9a, txs), (0xba, tsx)):
+    OPS[op] = fn
+
+
+def brk(s):
+    s.implied(); s.pc = (s.pc + 1) & 0xffff
+    push(s, s.pc >> 8); push(s, s.pc & 0xff); push(s, s.p | 0x30)
+    s.p |= 0x04
+    if s.cmos: s.p &= ~0x08
+    lo = s.rd(0xfffe); hi = s.rd(0xffff); s.pc = hi << 8 | lo
+OPS[0x00] = brk
+OPS[0x58] = lambda s: (s.implied(), s.flag(0x04, False))
+OPS[0x78] = lambda s: (s.implied(), s.flag(0x04, True))
+
+CMOS_OPS = {
+ 0x64: lambda s: s.wr(s.zp(), 0),
+ 0x80: lambda s: s.branch(True),
+ 0x1a: lambda s: (s.implied(), setattr(s, 'a', (s.a + 1) & 0xff), s.setnz(s.a)),
+ 0xa9: OPS[0xa9], 0xb1: OPS[0xb1], 0xe8: OPS[0xe8],
+ 0xda: phx, 0x7a: ply, 0x20: jsr, 0x60: rts, 0x00: brk,
+}
+def tsb(s):
+    addr = s.zp(); v = s.rd(addr); s.rd(addr); s.flag(0x02, (v & s.a) == 0); s.wr(addr, v | s.a)
+CMOS_OPS[0x04] = tsb
+def lda_zpi(s):
+    z = s.fetch(); lo = s.rd(z); hi = s.rd((z + 1) & 0xff); lda(s, s.rd(hi << 8 | lo))
+CMOS_OPS[0xb2] = lda_zpi
+
+def make(cpu, op, table, cmos, n, tweak=None):
+    random.seed('%s %02x' % (cpu, op))
+    cases = []
+    for i in range(n):
+        st = {'pc': random.randrange(0x0200, 0xfff0), 's': random.randrange(256), 'a': random.randrange(256), 'x': random.randrange(256), 'y': random.randrange(256), 'p': random.randrange(256) | 0x20}
+        st['p'] &= ~0x10
+        ram = {}
+        ops = [random.randrange(256) for _ in range(2)]
+        ram[st['pc']] = op; ram[(st['pc'] + 1) & 0xffff] = ops[0]; ram[(st['pc'] + 2) & 0xffff] = ops[1]
+        if tweak: tweak(i, st, ram)
+        # fill every address that will be touched with a random value first
+        while True:
+            probe = Sim(st, ram, cmos); probe.fetch(); table[op](probe)
+            fresh = [c[0] for c in probe.cyc if c[0] not in ram]
+            if not fresh: break
+            for a in fresh: ram[a] = random.randrange(256)
+        sim = Sim(st, ram, cmos); sim.fetch(); table[op](sim)
+        for c in sim.cyc:
+            if c[0] not in ram: ram[c[0]] = 0
+        final = {'pc': sim.pc, 's': sim.s, 'a': sim.a, 'x': sim.x, 'y': sim.y, 'p': sim.p & 0xff,
+                 'ram': sorted([[a, v] for a, v in sim.ram.items()])}
+        init = dict(st); init['ram'] = sorted([[a, v] for a, v in ram.items()])
+        name = ' '.join('%02x' % ram.get((st['pc'] + k) & 0xffff, 0) for k in range(3))
+        cases.append({'name': name, 'initial': init, 'final': final, 'cycles': sim.cyc})
+    return cases
+
+def pagecross(i, st, ram):   # make every other case cross a page
+    if i % 2 == 0: ram[(st['pc'] + 1) & 0xffff] = 0xf0 | (ram[(st['pc'] + 1) & 0xffff] & 0x0f); st['x'] = st['y'] = 0x80 | st['x']
+def decimal(i, st, ram):
+    if i % 2 == 0: st['p'] |= 0x08
+    else: st['p'] &= ~0x08
+def izx_wrap(i, st, ram):
+    if i == 0: ram[(st['pc'] + 1) & 0xffff] = 0x7f; st['x'] = 0x80
+def ptr_cross(i, st, ram):
+    if i % 2 == 0:
+        z = ram[(st['pc'] + 1) & 0xffff]; ram[z] = 0xf8; st['y'] = 0x90
+def jmp_bug(i, st, ram):
+    if i == 0: ram[(st['pc'] + 1) & 0xffff] = 0xff
+def branch_far(i, st, ram):   # alternate taken / not taken, and push half the taken ones across a page
+    st['p'] = (st['p'] & ~0x83) | [0x00, 0x83][i % 2]
+    if i >= 2:
+        ram[(st['pc'] + 1) & 0xffff] = 0x7c if i % 4 < 2 else 0x84
+def stack_edge(i, st, ram):   # push and pull across both ends of the stack page
+    if i == 0: st['s'] = 0x00
+    if i == 1: st['s'] = 0xff
+    if i == 2: st['s'] = 0xfe
+def run_all():
+    out = {}
+    nm = [(0xa9, None), (0xad, None), (0xbd, pagecross), (0xb9, pagecross), (0xa1, izx_wrap), (0xb1, ptr_cross),
+          (0x69, decimal), (0x65, decimal), (0xe9, decimal), (0xf1, decimal), (0x18, None), (0xe8, None), (0xaa, None),
+          (0x0a, None), (0x9d, pagecross), (0x91, ptr_cross), (0x85, None), (0x24, None), (0xc9, None), (0x4c, None),
+          (0xee, None), (0x1e, pagecross), (0x5e, pagecross), (0x6c, jmp_bug),
+          (0xd0, branch_far), (0xf0, branch_far), (0x10, branch_far), (0xb0, branch_far),
+          (0xa7, None), (0x8f, None), (0xcb, None), (0xc7, None), (0x04, None),
+          (0x48, stack_edge), (0x08, stack_edge), (0x68, stack_edge), (0x28, stack_edge), (0x20, stack_edge), (0x60, stack_edge), (0x40, stack_edge), (0x9a, None), (0xba, None),
+          (0x00, stack_edge), (0x58, None), (0x78, None)]
+    for op, tw in nm:
+        out[('6502', op)] = make('6502', op, OPS, False, 8, tw)
+    for op, tw in [(0x64, None), (0x80, None), (0x1a, None), (0x04, None), (0xb2, None), (0xa9, None), (0xb1, ptr_cross), (0xe8, None), (0xda, stack_edge), (0x7a, stack_edge), (0x20, stack_edge), (0x60, stack_edge), (0x00, None)]:
+        out[('wdc65c02', op)] = make('wdc65c02', op, CMOS_OPS, True, 6, tw)
+    return out
+
+cases = run_all()
+base = os.path.dirname(os.path.abspath(__file__))
+for (cpu, op), cs in cases.items():
+    d = os.path.join(base, cpu, 'v1'); os.makedirs(d, exist_ok=True)
+    with open(os.path.join(d, '%02x.json' % op), 'w') as f:
+        f.write('[\n' + ',\n'.join(json.dumps(c, separators=(', ', ': ')) for c in cs) + '\n]\n')
+print('%d opcodes, %d cases' % (len(cases), sum(len(c) for c in cases.values())))
@@ -0,0 +1,53 @@
+//The stack: which way it grows, wrapping around the stack page, and the ways a wrap can be reported.
+
+mod common;
+
+use common::FlatBus;
+use rust65::cpu::{CpuError, CpuStatus, StackCheck};
+
+
+#[test]
+fn stack_wraps_around_the_page()
+{
+    let mut bus = FlatBus::new();
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.reset = false;
+
+    bus.load(0x0200, &[0x48, 0x68]);       //PHA, PLA
+    cpu.pc = 0x0200;
+    cpu.sp = 0x00;
+    cpu.a = 0x5a;
+
+    assert_eq!(cpu.execute(&mut bus), Ok(3));
+    assert_eq!((cpu.sp, bus.ram[0x0100]), (0xff, 0x5a), "PHA writes to $0100 + SP, then decrements");
+
+    cpu.a = 0;
+    assert_eq!(cpu.execute(&mut bus), Ok(4));
+    assert_eq!((cpu.sp, cpu.a), (0x00, 0x5a), "PLA increments SP, then reads");
+
+    cpu.stack_check = StackCheck::Halt;
+    cpu.pc = 0x0200;
+    assert!(cpu.execute(&mut bus).is_err(), "overflow should halt when stack_check is Halt");
+    assert_eq!(cpu.sp, 0xff);
+}
+
+#[test]
+fn wraps_are_warnings_for_the_front_end()
+{
+    let mut bus = FlatBus::new();
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.reset = false;
+
+    bus.load(0x0200, &[0x48, 0x68, 0x48]);  //PHA, PLA, PHA
+    cpu.pc = 0x0200;
+    cpu.sp = 0x00;
+
+    assert_eq!(cpu.execute(&mut bus), Ok(3), "Off says nothing, like the real chip");
+    assert!(cpu.take_warnings().is_empty());
+
+    cpu.stack_check = StackCheck::Warn;
+    assert_eq!(cpu.execute(&mut bus), Ok(4), "Warn carries on");
+    assert_eq!(cpu.execute(&mut bus), Ok(3));
+    assert_eq!(cpu.take_warnings(), vec![CpuError::StackUnderflow { pc: 0x0202 }, CpuError::StackOverflow { pc: 0x0203 }]);
+    assert!(cpu.take_warnings().is_empty(), "taking them clears them");
+}
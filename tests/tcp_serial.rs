@@ -0,0 +1,59 @@
+//The TCP serial backend: output the socket isn't ready for is queued and sent later, not dropped.
+
+use rust65::devices::serial::{SerialBackend, TcpSerial};
+
+use std::io::Read;
+use std::net::TcpStream;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const SENT: usize = 6 << 20;        //more than the socket buffers hold with the client's cut down, so some writes would block
+
+
+#[cfg(unix)]
+fn shrink_receive_buffer(client: &TcpStream)
+{
+    use std::os::unix::io::AsRawFd;
+
+    let size: libc::c_int = 65536;
+    unsafe { libc::setsockopt(client.as_raw_fd(), libc::SOL_SOCKET, libc::SO_RCVBUF, &size as *const libc::c_int as *const libc::c_void, std::mem::size_of::<libc::c_int>() as libc::socklen_t) };
+}
+
+#[cfg(not(unix))]
+fn shrink_receive_buffer(_client: &TcpStream) {}
+
+
+#[test]
+fn output_survives_a_full_socket()
+{
+    let mut serial = TcpSerial::new(0).unwrap();
+    let client = TcpStream::connect(("127.0.0.1", serial.port().unwrap())).unwrap();
+    shrink_receive_buffer(&client);
+    let data: Vec<u8> = (0..SENT).map(|i| (i % 251) as u8).collect();
+
+    let (go, wait) = mpsc::channel::<()>();
+    let reader = thread::spawn(move || //only starts reading once the emulator side has filled the socket
+    {
+        let mut client: TcpStream = client;
+        let mut received: Vec<u8> = vec![0; SENT];
+        wait.recv().unwrap();
+        client.read_exact(&mut received).unwrap();
+        return received
+    });
+
+    let start = Instant::now();
+    while serial.receive().is_none() && start.elapsed() < Duration::from_millis(100) {}   //let it accept the connection
+
+    for &byte in &data { serial.transmit(byte) }
+    go.send(()).unwrap();
+
+    let start = Instant::now();
+    while !reader.is_finished()
+    {
+        assert!(start.elapsed() < Duration::from_secs(10), "the queued bytes never arrived");
+        serial.tick();
+    }
+
+    assert!(reader.join().unwrap() == data, "every byte arrives, in order");
+}
@@ -0,0 +1,96 @@
+//The instruction trace: nestest and CSV lines, the ring buffer, and the log output.
+
+mod common;
+
+use common::FlatBus;
+use rust65::cpu::{CpuStatus, Variant};
+use rust65::trace::{TraceEntry, TraceFormat};
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+
+#[derive(Clone)]
+struct SharedLog(Rc<RefCell<Vec<u8>>>); //a log the test can still read after handing it to the tracer
+
+impl Write for SharedLog
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+    {
+        self.0.borrow_mut().extend_from_slice(buf);
+        return Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()>
+    {
+        return Ok(())
+    }
+}
+
+
+fn setup(capacity: usize) -> (CpuStatus, FlatBus)
+{
+    let mut bus = FlatBus::new();
+    bus.load(0x0200, &[0xa9, 0x01, 0x04, 0xa9, 0x48, 0xe8, 0x4c, 0x00, 0x02]);   //LDA #$01, NOP $A9 (undocumented), PHA, INX, JMP $0200
+
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.reset = false;
+    cpu.pc = 0x0200;
+    cpu.sp = 0xfd;
+    cpu.tracer.set_capacity(capacity);
+
+    return (cpu, bus)
+}
+
+
+#[test]
+fn nestest_layout()
+{
+    let (mut cpu, mut bus) = setup(8);
+    let log = SharedLog(Rc::new(RefCell::new(Vec::new())));
+    cpu.tracer.log_to_writer(Box::new(log.clone()));
+
+    for _ in 0..3 { cpu.execute(&mut bus).unwrap(); }
+
+    let text: String = String::from_utf8(log.0.borrow().clone()).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines, vec![
+        "0200  A9 01     LDA #$01                        A:00 X:00 Y:00 P:24 SP:FD CYC:0",
+        "0202  04 A9    *NOP $A9                         A:01 X:00 Y:00 P:24 SP:FD CYC:2",
+        "0204  48        PHA                             A:01 X:00 Y:00 P:24 SP:FD CYC:5",
+    ]);
+}
+
+#[test]
+fn ring_buffer_keeps_the_last_few()
+{
+    let (mut cpu, mut bus) = setup(3);
+
+    for _ in 0..6 { cpu.execute(&mut bus).unwrap(); }               //LDA NOP PHA INX JMP LDA
+
+    let pcs: Vec<u16> = cpu.tracer.recent(10).map(|entry| entry.pc).collect();
+    assert_eq!(pcs, vec![0x0205, 0x0206, 0x0200]);
+    assert_eq!(cpu.tracer.recent(1).next().map(|entry| entry.cycles), Some(13));
+    assert_eq!(cpu.tracer.recent(1).next().map(|entry| entry.sp), Some(0xfc), "registers as they were before the instruction");
+}
+
+#[test]
+fn off_by_default()
+{
+    let (mut cpu, mut bus) = setup(0);
+    assert!(!cpu.tracer.active());
+
+    cpu.execute(&mut bus).unwrap();
+    assert_eq!(cpu.tracer.recent(10).count(), 0);
+}
+
+#[test]
+fn csv_format()
+{
+    let entry = TraceEntry { pc: 0xe000, bytes: [0x20, 0x00, 0xff], a: 0x8d, x: 1, y: 2, p: 0x24, sp: 0xfb, cycles: 99, variant: Variant::Nmos };
+
+    assert_eq!(entry.format(TraceFormat::Csv), "E000,20 00 FF,\"JSR $FF00\",8D,01,02,24,FB,99");
+    assert_eq!(TraceFormat::from_name("CSV"), Some(TraceFormat::Csv));
+    assert_eq!(TraceFormat::from_name("nintendulator"), None);
+}
@@ -0,0 +1,284 @@
+//The 6522 VIA: T1 one-shot and free-run, T2, the shift register modes, IFR and IER with their bit 7 semantics, and the
+//IRQ output.
+
+use rust65::devices::Device;
+use rust65::devices::via::Via6522;
+
+const T1C_L: u16 = 0x4;
+const T1C_H: u16 = 0x5;
+const T1L_H: u16 = 0x7;
+const T2C_L: u16 = 0x8;
+const T2C_H: u16 = 0x9;
+const SR: u16 = 0xa;
+const ACR: u16 = 0xb;
+const IFR: u16 = 0xd;
+const IER: u16 = 0xe;
+
+const T1: u8 = 0x40;
+const T2: u8 = 0x20;
+const SR_DONE: u8 = 0x04;
+const CA1: u8 = 0x02;
+
+
+fn ticks_until(via: &mut Via6522, flag: u8, limit: u32) -> Option<u32> //clock the VIA until an IFR flag comes up, returns how many ticks it took
+{
+    for n in 1..=limit
+    {
+        via.tick();
+        if via.peek(IFR) & flag != 0 { return Some(n) }
+    }
+
+    return None
+}
+
+fn shift_clock(via: &mut Via6522, cycles: u32) -> Vec<(u32, bool)> //clock the VIA, noting the cycle and CB2 at each rising edge of CB1
+{
+    let mut edges: Vec<(u32, bool)> = Vec::new();
+    let mut clock: bool = via.cb1();
+
+    for n in 1..=cycles
+    {
+        via.tick();
+        if via.cb1() && !clock { edges.push((n, via.cb2())) }
+        clock = via.cb1();
+    }
+
+    return edges
+}
+
+fn bits(byte: u8) -> Vec<bool> //most significant first, the order the shift register sends them in
+{
+    return (0..8).rev().map(|bit| byte >> bit & 1 != 0).collect()
+}
+
+
+#[test]
+fn t1_one_shot()
+{
+    let mut via = Via6522::new();
+    via.write(T1C_L, 0x03);
+    via.write(T1C_H, 0x00);
+    assert_eq!(via.t1_counter, 3, "writing T1C-H loads the counter from the latch");
+
+    assert_eq!(ticks_until(&mut via, T1, 10), Some(4), "the flag comes up as the counter passes zero");
+    assert_eq!(via.read(T1C_L), 0xff, "it keeps counting down, through $FFFF");
+    assert_eq!(via.peek(IFR) & T1, 0, "reading T1C-L clears the flag");
+
+    assert_eq!(ticks_until(&mut via, T1, 0x20000), None, "one-shot only interrupts once per write to T1C-H");
+
+    via.write(T1C_H, 0x00);
+    assert_eq!(ticks_until(&mut via, T1, 10), Some(4), "until it's written again");
+}
+
+#[test]
+fn t1_free_run()
+{
+    let mut via = Via6522::new();
+    via.write(ACR, 0x40);
+    via.write(T1C_L, 0x03);
+    via.write(T1C_H, 0x00);
+
+    assert_eq!(ticks_until(&mut via, T1, 10), Some(4));
+    for _ in 0..3
+    {
+        via.read(T1C_L);
+        assert_eq!(ticks_until(&mut via, T1, 10), Some(5), "a period of N + 2 cycles, reloading from the latch");
+    }
+
+    via.tick();                                                 //the reload
+    via.write(T1L_H, 0x01);
+    assert_eq!(via.peek(IFR) & T1, 0, "writing T1L-H clears the flag");
+    assert_eq!(ticks_until(&mut via, T1, 1000), Some(4), "the current period finishes with the old count");
+    via.read(T1C_L);
+    assert_eq!(ticks_until(&mut via, T1, 1000), Some(0x103 + 2), "the next one uses the new latch");
+}
+
+#[test]
+fn t2_one_shot()
+{
+    let mut via = Via6522::new();
+    via.write(T2C_L, 0x02);
+    assert_eq!(via.peek(IFR) & T2, 0);
+    via.write(T2C_H, 0x00);
+
+    assert_eq!(ticks_until(&mut via, T2, 10), Some(3));
+    via.read(T2C_L);
+    assert_eq!(via.peek(IFR) & T2, 0, "reading T2C-L clears the flag");
+    assert_eq!(ticks_until(&mut via, T2, 0x20000), None, "T2 is always one-shot");
+
+    via.write(T2C_H, 0x00);
+    assert_eq!(ticks_until(&mut via, T2, 10), Some(3), "writing T2C-H starts it again, from the low byte written before");
+}
+
+#[test]
+fn t2_counts_pb6_pulses()
+{
+    let mut via = Via6522::new();
+    via.write(ACR, 0x20);
+    via.write(T2C_L, 0x02);
+    via.write(T2C_H, 0x00);
+
+    assert_eq!(ticks_until(&mut via, T2, 100), None, "the clock doesn't count in this mode");
+    for pulse in 1..=2
+    {
+        via.set_port_b_input(0xff);
+        via.set_port_b_input(0xbf);
+        assert_eq!(via.peek(IFR) & T2 != 0, pulse == 2, "counting falling edges on PB6");
+    }
+}
+
+#[test]
+fn ier_bit_7_sets_or_clears()
+{
+    let mut via = Via6522::new();
+    assert_eq!(via.read(IER), 0x80, "bit 7 reads as 1");
+
+    via.write(IER, 0x80 | T1 | CA1);
+    assert_eq!(via.read(IER), 0x80 | T1 | CA1);
+    via.write(IER, 0x80 | T2);
+    assert_eq!(via.read(IER), 0x80 | T1 | T2 | CA1, "bit 7 set: 1 bits enable, 0 bits are left alone");
+
+    via.write(IER, T1 | 0x01);
+    assert_eq!(via.read(IER), 0x80 | T2 | CA1, "bit 7 clear: 1 bits disable");
+}
+
+#[test]
+fn ifr_and_the_irq_line()
+{
+    let mut via = Via6522::new();
+    via.set_ca1(false);                                         //PCR is 0, so CA1 is active on the falling edge
+    assert_eq!(via.read(IFR), CA1, "the flag is set, but bit 7 only shows an enabled one");
+    assert!(!via.irq());
+
+    via.write(IER, 0x80 | CA1);
+    assert_eq!(via.read(IFR), 0x80 | CA1);
+    assert!(via.irq());
+
+    via.write(IFR, 0x80);
+    assert!(via.irq(), "writing bit 7 doesn't clear anything");
+    via.write(IFR, CA1);
+    assert_eq!(via.read(IFR), 0, "writing a 1 clears that flag");
+    assert!(!via.irq());
+
+    via.write(T1C_L, 0x01);
+    via.write(T1C_H, 0x00);
+    ticks_until(&mut via, T1, 10);
+    assert!(!via.irq(), "T1 isn't enabled");
+    via.write(IER, 0x80 | T1);
+    assert!(via.irq(), "enabling a flag that's already set raises IRQ");
+    via.write(IER, T1);
+    assert!(!via.irq());
+}
+
+#[test]
+fn shift_out_under_phi2()
+{
+    let mut via = Via6522::new();
+    via.write(ACR, 0x18);                                       //shift out under the system clock
+    via.write(SR, 0xa5);
+
+    let edges: Vec<(u32, bool)> = shift_clock(&mut via, 40);
+    assert_eq!(edges.iter().map(|edge| edge.0).collect::<Vec<u32>>(), vec![2, 4, 6, 8, 10, 12, 14, 16], "a bit every two cycles, then the clock stops");
+    assert_eq!(edges.iter().map(|edge| edge.1).collect::<Vec<bool>>(), bits(0xa5), "CB2 has each bit by the rising edge of CB1");
+    assert_eq!(via.read(SR), 0xa5, "the bits recirculate, so the register ends up where it started");
+}
+
+#[test]
+fn shift_flag_after_8_bits()
+{
+    let mut via = Via6522::new();
+    via.write(ACR, 0x18);
+    via.write(SR, 0x00);
+
+    assert_eq!(ticks_until(&mut via, SR_DONE, 100), Some(16), "with the eighth rising edge of CB1");
+    assert_eq!(via.peek(IFR) & SR_DONE, SR_DONE);
+    via.write(IER, 0x80 | SR_DONE);
+    assert!(via.irq());
+
+    via.read(SR);
+    assert_eq!(via.peek(IFR) & SR_DONE, 0, "reading or writing SR clears the flag");
+    assert!(!via.irq());
+    assert_eq!(ticks_until(&mut via, SR_DONE, 100), Some(16), "and starts another 8 bits");
+}
+
+#[test]
+fn shift_in_under_t2()
+{
+    let mut via = Via6522::new();
+    via.write(ACR, 0x04);                                       //shift in at the T2 rate
+    via.write(T2C_L, 0x03);                                     //so CB1 changes every 3 + 2 cycles
+    via.read(SR);
+
+    let incoming: Vec<bool> = bits(0x6c);
+    let mut clock: bool = via.cb1();
+    let mut edges: Vec<u32> = Vec::new();
+    for n in 1..=100
+    {
+        via.set_cb2(incoming[edges.len().min(7)]);
+        via.tick();
+        if via.cb1() && !clock { edges.push(n) }
+        clock = via.cb1();
+    }
+
+    assert_eq!(edges, vec![10, 20, 30, 40, 50, 60, 70, 80], "a bit every 2 * (N + 2) cycles");
+    assert_eq!(via.read(SR), 0x6c, "sampled on the rising edges of CB1");
+}
+
+#[test]
+fn shift_out_under_t2()
+{
+    let mut via = Via6522::new();
+    via.write(ACR, 0x14);
+    via.write(T2C_L, 0x00);
+    via.write(SR, 0x81);
+
+    let edges: Vec<(u32, bool)> = shift_clock(&mut via, 100);
+    assert_eq!(edges.iter().map(|edge| edge.0).collect::<Vec<u32>>(), vec![4, 8, 12, 16, 20, 24, 28, 32]);
+    assert_eq!(edges.iter().map(|edge| edge.1).collect::<Vec<bool>>(), bits(0x81));
+    assert_eq!(via.peek(IFR) & SR_DONE, SR_DONE);
+}
+
+#[test]
+fn shift_under_cb1()
+{
+    let mut via = Via6522::new();
+    via.write(ACR, 0x0c);                                       //shift in, clocked by the outside world on CB1
+    via.read(SR);
+    assert_eq!(ticks_until(&mut via, SR_DONE, 100), None, "nothing happens without CB1");
+
+    for bit in bits(0xd2)
+    {
+        via.set_cb2(bit);
+        via.set_cb1(false);
+        via.set_cb1(true);                                      //rising edges shift in
+    }
+    assert_eq!(via.peek(IFR) & SR_DONE, SR_DONE);
+    assert_eq!(via.read(SR), 0xd2);
+
+    via.write(ACR, 0x1c);                                       //and shift out, also clocked by CB1
+    via.write(SR, 0x5a);
+    let mut sent: Vec<bool> = Vec::new();
+    for _ in 0..8
+    {
+        assert_eq!(via.peek(IFR) & SR_DONE, 0);
+        via.set_cb1(false);                                     //falling edges shift out
+        sent.push(via.cb2());
+        via.set_cb1(true);
+    }
+    assert_eq!(sent, bits(0x5a));
+    assert_eq!(via.peek(IFR) & SR_DONE, SR_DONE);
+}
+
+#[test]
+fn free_running_shift_out()
+{
+    let mut via = Via6522::new();
+    via.write(ACR, 0x10);                                       //shift out free running at the T2 rate
+    via.write(T2C_L, 0x00);
+    via.write(SR, 0xc4);
+
+    let edges: Vec<(u32, bool)> = shift_clock(&mut via, 4 * 24);
+    assert_eq!(edges.len(), 24, "the clock never stops");
+    assert_eq!(edges.iter().map(|edge| edge.1).collect::<Vec<bool>>(), bits(0xc4).repeat(3), "the same byte, over and over");
+    assert_eq!(via.peek(IFR) & SR_DONE, 0, "and the flag never comes up");
+}
@@ -0,0 +1,136 @@
+//Watchpoints on the CPU's reads and writes: ranges, access kinds, value filters, and device registers.
+
+mod common;
+
+use common::FlatBus;
+use rust65::asm::assemble;
+use rust65::bus::{Access, Bus, MemoryMap, Segment, Watchpoints};
+use rust65::cpu::{CpuError, CpuStatus, Variant};
+use rust65::devices::Device;
+
+
+fn cpu_at(pc: u16) -> CpuStatus
+{
+    let mut cpu = CpuStatus::new(1_000_000);
+    cpu.reset = false;
+    cpu.pc = pc;
+    cpu.sp = 0xff;
+
+    return cpu
+}
+
+fn run_to_stop(cpu: &mut CpuStatus, bus: &mut dyn Bus) -> CpuError //run until execute returns an error
+{
+    for _ in 0..1000
+    {
+        if let Err(e) = cpu.execute(bus) { return e }
+    }
+
+    panic!("never stopped, PC is {:#06x}", cpu.pc)
+}
+
+
+#[test]
+fn write_watch_reports_the_culprit()
+{
+    let mut bus = FlatBus::new();
+    bus.load(0x0200, &assemble(Variant::Nmos, 0x0200, "loop: LDA $21\nINC $20\nJMP loop").unwrap());
+    bus.ram[0x20] = 0x05;
+
+    let mut cpu = cpu_at(0x0200);
+    let id: usize = cpu.watchpoints.add(0x20, 0x20, Access::Write, None);
+
+    assert_eq!(run_to_stop(&mut cpu, &mut bus), CpuError::Watchpoint { addr: 0x20, pc: 0x0202, old: 0x05, new: 0x06, write: true });
+    assert_eq!(cpu.pc, 0x0204, "INC finishes before the stop is reported");
+
+    assert!(cpu.watchpoints.remove(id));
+    cpu.watchpoints.add(0x10, 0x2f, Access::Both, Some(0x09));
+    assert_eq!(run_to_stop(&mut cpu, &mut bus), CpuError::Watchpoint { addr: 0x20, pc: 0x0202, old: 0x08, new: 0x09, write: true }, "the value filter skips 7 and 8");
+    assert_eq!(cpu.watchpoints.list()[0].hits, 1);
+}
+
+#[test]
+fn read_watch()
+{
+    let mut bus = FlatBus::new();
+    bus.load(0x0200, &assemble(Variant::Nmos, 0x0200, "STA $30\nLDX $30").unwrap());
+
+    let mut cpu = cpu_at(0x0200);
+    cpu.a = 0x44;
+    cpu.watchpoints.add(0x30, 0x30, Access::Read, None);
+
+    assert_eq!(cpu.execute(&mut bus), Ok(3), "the write goes by");
+    assert_eq!(cpu.execute(&mut bus), Err(CpuError::Watchpoint { addr: 0x30, pc: 0x0202, old: 0x44, new: 0x44, write: false }));
+}
+
+
+struct Latch //a device register that counts down every time it's read
+{
+    value: u8,
+}
+
+impl Device for Latch
+{
+    fn read(&mut self, _reg: u16) -> u8
+    {
+        self.value = self.value.wrapping_sub(1);
+        return self.value
+    }
+
+    fn write(&mut self, _reg: u16, data: u8)
+    {
+        self.value = data;
+    }
+
+    fn peek(&self, _reg: u16) -> u8
+    {
+        return self.value
+    }
+}
+
+#[test]
+fn device_registers()
+{
+    let mut ram: [u8; 0x1000] = [0; 0x1000];
+    let code: Vec<u8> = assemble(Variant::Nmos, 0x0200, "LDA #$10\nSTA $D012\nloop: LDA $D012\nBNE loop").unwrap();
+    ram[0x200..0x200 + code.len()].copy_from_slice(&code);
+
+    let mut memory = MemoryMap::new(vec![Segment::new(&mut ram, 0, true, true)]);
+    memory.attach(0xd012, 0xd012, Latch { value: 0xff });
+
+    let mut cpu = cpu_at(0x0200);
+    cpu.watchpoints.add(0xd012, 0xd012, Access::Write, None);
+    cpu.watchpoints.add(0xd012, 0xd012, Access::Read, Some(0x00));
+
+    assert_eq!(run_to_stop(&mut cpu, &mut memory), CpuError::Watchpoint { addr: 0xd012, pc: 0x0202, old: 0xff, new: 0x10, write: true });
+    assert_eq!(run_to_stop(&mut cpu, &mut memory), CpuError::Watchpoint { addr: 0xd012, pc: 0x0205, old: 0x00, new: 0x00, write: false });
+    assert_eq!(cpu.watchpoints.list()[1].hits, 1);
+}
+
+#[test]
+fn opcode_fetches_and_interrupts()
+{
+    let mut bus = FlatBus::new();
+    bus.load(0x0200, &assemble(Variant::Nmos, 0x0200, "NOP\nNOP\nNOP").unwrap());
+    bus.load(0xfffe, &[0x00, 0x03]);
+
+    let mut cpu = cpu_at(0x0200);
+    cpu.set_interrupt(false);
+    cpu.watchpoints.add(0x0201, 0x0201, Access::Read, None);
+    assert_eq!(cpu.execute(&mut bus), Ok(2));
+    assert_eq!(cpu.execute(&mut bus), Err(CpuError::Watchpoint { addr: 0x0201, pc: 0x0201, old: 0xea, new: 0xea, write: false }), "the fetch belongs to the instruction it fetches");
+
+    cpu.watchpoints.add(0x01fd, 0x01ff, Access::Write, None);
+    cpu.set_irq(0, true);
+    assert_eq!(cpu.execute(&mut bus), Err(CpuError::Watchpoint { addr: 0x01ff, pc: 0xfffe, old: 0x00, new: 0x02, write: true }), "the pushes belong to the vector, not the last instruction");
+}
+
+#[test]
+fn ids_start_at_1()
+{
+    let mut watchpoints: Watchpoints = Watchpoints::default();
+    assert_eq!(watchpoints.add(0x0200, 0x0200, Access::Write, None), 1);
+    assert_eq!(watchpoints.add(0x0300, 0x03ff, Access::Read, None), 2);
+    assert!(watchpoints.remove(1));
+    assert_eq!(watchpoints.add(0x0400, 0x0400, Access::Write, None), 3, "ids aren't reused");
+}